@@ -19,7 +19,7 @@ impl Task {
 
 impl Delayed for Task {
     fn delayed(&self) -> i64 {
-        self.deadline - chrono::Local::now().timestamp_nanos()
+        self.deadline - chrono::Local::now().timestamp_nanos_opt().unwrap()
     }
 }
 
@@ -31,7 +31,7 @@ impl Ord for Task {
 
 impl PartialOrd for Task {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.deadline.cmp(&other.deadline))
+        Some(self.cmp(other))
     }
 }
 
@@ -46,7 +46,9 @@ fn main() {
             for index in 0..TOTAL_COUNT {
                 let v = rand::random::<u64>() % 10000;
                 queue.put(Task::new(
-                    after(Duration::milliseconds(v as i64)).timestamp_nanos(),
+                    after(Duration::milliseconds(v as i64))
+                        .timestamp_nanos_opt()
+                        .unwrap(),
                     format!("index: {}. delay for {}ms", index, v),
                 ));
             }
@@ -61,7 +63,7 @@ fn main() {
                 for _i in 0..TOTAL_COUNT / THREAD_COUNT {
                     let task = queue.take();
                     let now = chrono::Local::now();
-                    let diff = (now.timestamp_nanos() - task.deadline) / 1000;
+                    let diff = (now.timestamp_nanos_opt().unwrap() - task.deadline) / 1000;
                     if diff <= 100 {
                         *map.entry(100).or_default() += 1;
                     } else if diff <= 200 {
@@ -0,0 +1,181 @@
+#![no_main]
+
+//! Drives random interleavings of the queue API from several producer and
+//! consumer threads and checks the deliveries against a shadow model: every
+//! element is delivered exactly once and the run finishes in time, so a lost
+//! wakeup in the leader/follower protocol shows up as a crash.
+
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use arbitrary::Arbitrary;
+use delayqueue::{DeadlineMode, DelayQueue, Delayed};
+use libfuzzer_sys::fuzz_target;
+
+const MAX_DELAY_US: u64 = 2_000;
+const DEADLOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+struct Item {
+    id: u64,
+    deadline: Instant,
+}
+
+impl Delayed for Item {
+    fn delayed(&self) -> i64 {
+        let now = Instant::now();
+        if self.deadline > now {
+            (self.deadline - now).as_nanos() as i64
+        } else {
+            -((now - self.deadline).as_nanos() as i64)
+        }
+    }
+}
+
+impl PartialEq for Item {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Item {}
+
+impl Ord for Item {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deadline
+            .cmp(&other.deadline)
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Item {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+    Put { delay_us: u16 },
+    PutNow,
+    TryPut { delay_us: u16 },
+    Prepare { delay_us: u16, commit: bool },
+    Take,
+    Revalidate,
+    ShiftLater { us: u8 },
+    ShiftEarlier { us: u8 },
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    captured: bool,
+    capacity: Option<u8>,
+    threads: Vec<Vec<Op>>,
+}
+
+impl Op {
+    /// Whether the op always ends with one more element in the queue.
+    fn inserts(&self) -> bool {
+        match self {
+            Op::Put { .. } | Op::PutNow | Op::TryPut { .. } => true,
+            Op::Prepare { commit, .. } => *commit,
+            _ => false,
+        }
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let mode = if input.captured {
+        DeadlineMode::Captured
+    } else {
+        DeadlineMode::Dynamic
+    };
+    let queue: DelayQueue<Item> = match input.capacity {
+        // Large enough for every element of one input, so that try_put and
+        // prepare never fail and the number of insertions is known upfront.
+        Some(capacity) => DelayQueue::bounded(capacity as usize + 8 * 64),
+        None => DelayQueue::with_deadline_mode(mode),
+    };
+    let threads = input
+        .threads
+        .into_iter()
+        .take(8)
+        .map(|ops| ops.into_iter().take(64).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let total = threads.iter().flatten().filter(|op| op.inserts()).count();
+    let takes = threads
+        .iter()
+        .flatten()
+        .filter(|op| matches!(op, Op::Take))
+        .count();
+
+    let (tx, rx) = mpsc::channel();
+    let consumers = takes.clamp(1, 8);
+    for index in 0..consumers {
+        let mut queue = queue.clone();
+        let tx = tx.clone();
+        let count = total / consumers + usize::from(index < total % consumers);
+        thread::spawn(move || {
+            for _ in 0..count {
+                tx.send(queue.take().id).unwrap();
+            }
+        });
+    }
+    drop(tx);
+
+    for (thread_id, ops) in threads.into_iter().enumerate() {
+        let mut queue = queue.clone();
+        thread::spawn(move || {
+            let mut next = (thread_id as u64) << 32;
+            let mut item = |delay_us: u64| {
+                next += 1;
+                Item {
+                    id: next,
+                    deadline: Instant::now() + Duration::from_micros(delay_us % MAX_DELAY_US),
+                }
+            };
+            for op in ops {
+                match op {
+                    Op::Put { delay_us } => queue.put(item(delay_us as u64)),
+                    Op::PutNow => queue.put_now(item(0)),
+                    Op::TryPut { delay_us } => {
+                        assert!(queue.try_put(item(delay_us as u64)).is_ok());
+                    }
+                    Op::Prepare { delay_us, commit } => {
+                        let prepared = queue
+                            .prepare(item(delay_us as u64))
+                            .expect("prepare failed on a queue with free slots");
+                        if commit {
+                            queue.commit(prepared);
+                        } else {
+                            queue.abort(prepared);
+                        }
+                    }
+                    // Consumers take concurrently; the op only sizes them.
+                    Op::Take => {}
+                    Op::Revalidate => queue.revalidate(),
+                    Op::ShiftLater { us } => {
+                        queue.shift_deadlines_later(Duration::from_micros(us as u64))
+                    }
+                    Op::ShiftEarlier { us } => {
+                        queue.shift_deadlines_earlier(Duration::from_micros(us as u64))
+                    }
+                }
+            }
+        });
+    }
+
+    let mut seen = HashSet::new();
+    for _ in 0..total {
+        let id = rx
+            .recv_timeout(DEADLOCK_TIMEOUT)
+            .expect("consumers stalled: possible lost wakeup");
+        assert!(seen.insert(id), "element {} delivered twice", id);
+    }
+    assert!(queue.is_empty());
+});
@@ -0,0 +1,274 @@
+//! Awaiting a [`DelayQueue`] from async code instead of blocking a thread of
+//! the runtime.
+
+use std::{pin::pin, sync::Arc};
+
+use tokio::time::{self, Instant};
+
+use crate::{
+    DeadlineMode, DelayQueue, Delayed, Delivery, Options, PutError, Reservation, TokioClock,
+};
+
+/// A [`DelayQueue`] whose waiting operations are `async`, built on
+/// `tokio::sync::Notify` and `sleep_until`. Semantics are those of the
+/// blocking methods of the same name.
+///
+/// Queues created here run on [`TokioClock`], so tests can drive them with
+/// `tokio::time::pause` and `advance`. The elements are shared with
+/// [`blocking`](Self::blocking), and threads and tasks can wait on the same
+/// queue.
+pub struct AsyncDelayQueue<T: Delayed> {
+    queue: DelayQueue<T>,
+}
+
+impl<T: Delayed> Clone for AsyncDelayQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<T: Delayed> Default for AsyncDelayQueue<T> {
+    fn default() -> Self {
+        Self::new(Options::default())
+    }
+}
+
+/// Wraps a queue configured with [`DelayQueue::builder`]; give it
+/// [`TokioClock`] for `tokio::time::pause` to apply.
+impl<T: Delayed> From<DelayQueue<T>> for AsyncDelayQueue<T> {
+    fn from(queue: DelayQueue<T>) -> Self {
+        Self { queue }
+    }
+}
+
+impl<T: Delayed> AsyncDelayQueue<T> {
+    pub fn with_deadline_mode(mode: DeadlineMode) -> Self {
+        Self::new(Options {
+            mode,
+            ..Options::default()
+        })
+    }
+
+    /// See [`DelayQueue::bounded`]; [`put`](Self::put) waits while it is
+    /// full.
+    pub fn bounded(capacity: usize) -> Self {
+        Self::new(Options {
+            capacity: Some(capacity),
+            ..Options::default()
+        })
+    }
+
+    fn new(options: Options) -> Self {
+        Self {
+            queue: DelayQueue::new(Options {
+                clock: Arc::new(TokioClock),
+                ..options
+            }),
+        }
+    }
+
+    /// The same queue with blocking methods, e.g. for producer threads.
+    pub fn blocking(&self) -> DelayQueue<T> {
+        self.queue.clone()
+    }
+}
+
+impl<T> AsyncDelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// See [`DelayQueue::close`].
+    pub fn close(&self) {
+        self.queue.close();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.queue.is_closed()
+    }
+
+    /// Inserts `t`, waiting while the queue is full. On a closed queue, or
+    /// beyond the maximum horizon, `t` goes to the
+    /// [`on_discard`](DelayQueue::on_discard) hook like with
+    /// [`DelayQueue::put`].
+    pub async fn put(&self, t: T) {
+        let mut t = t;
+        loop {
+            self.wait_for_capacity().await;
+            match self.queue.try_put(t) {
+                Ok(()) => return,
+                Err(PutError::Full(back)) => t = back,
+                Err(err) => {
+                    let hook = self.queue.queue.lock().on_discard.clone();
+                    DelayQueue::<T>::discard(hook, vec![Arc::new(err.into_inner())]);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// See [`DelayQueue::try_put`].
+    pub fn try_put(&self, t: T) -> Result<(), PutError<T>> {
+        self.queue.try_put(t)
+    }
+
+    /// Waits until the queue has a free slot or is closed, see
+    /// [`DelayQueue::wait_for_capacity`].
+    pub async fn wait_for_capacity(&self) {
+        loop {
+            let mut notified = pin!(self.queue.not_full.notified());
+            notified.as_mut().enable();
+            {
+                let guard = self.queue.queue.lock();
+                if !guard.is_full() || guard.closed {
+                    return;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    /// Waits until `n` slots are free and holds them, like
+    /// `tokio::sync::mpsc::Sender::reserve`, see
+    /// [`DelayQueue::reserve_slots`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` exceeds the capacity of a bounded queue.
+    pub async fn reserve_slots(&self, n: usize) -> Reservation<T> {
+        loop {
+            let mut notified = pin!(self.queue.not_full.notified());
+            notified.as_mut().enable();
+            if let Some(reservation) = self.queue.try_reserve_slots(n) {
+                return reservation;
+            }
+            notified.await;
+        }
+    }
+
+    /// Waits until an element expires and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the queue is closed and empty.
+    pub async fn take(&self) -> Arc<T> {
+        self.take_or_closed()
+            .await
+            .expect("take on a closed and empty AsyncDelayQueue")
+    }
+
+    /// Like [`take`](Self::take), but returns `None` once the queue is
+    /// closed and every remaining element has been delivered.
+    pub async fn take_or_closed(&self) -> Option<Arc<T>> {
+        self.take_delivery_or_closed()
+            .await
+            .map(Delivery::into_item)
+    }
+
+    /// Like [`take_or_closed`](Self::take_or_closed), but also returns the
+    /// delivery metadata.
+    pub async fn take_delivery_or_closed(&self) -> Option<Delivery<T>> {
+        loop {
+            let mut notified = pin!(self.queue.available.notified());
+            notified.as_mut().enable();
+            match self.queue.poll_delivery() {
+                Ok((delivery, delay)) => {
+                    if let Some(delay) = delay {
+                        time::sleep(delay).await;
+                    }
+                    return Some(delivery);
+                }
+                Err(Some(due)) => {
+                    let _ = time::timeout_at(Instant::from_std(due), notified).await;
+                }
+                Err(None) if self.queue.is_closed() => return None,
+                Err(None) => notified.await,
+            }
+        }
+    }
+
+    /// See [`DelayQueue::try_take`].
+    pub fn try_take(&self) -> Option<Arc<T>> {
+        self.queue.try_take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::Fixed;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_async_delay_queue() {
+        runtime().block_on(async {
+            let queue = AsyncDelayQueue::with_deadline_mode(DeadlineMode::Captured);
+            let start = Instant::now();
+            queue.put(Fixed(3_600_000_000_000)).await;
+            queue.put(Fixed(60_000_000_000)).await;
+            assert_eq!(Fixed(60_000_000_000), *queue.take().await);
+            assert_eq!(Duration::from_secs(60), start.elapsed());
+
+            let producer = {
+                let queue = queue.clone();
+                tokio::spawn(async move {
+                    time::sleep(Duration::from_secs(5)).await;
+                    queue.blocking().try_put(Fixed(0)).unwrap();
+                })
+            };
+            assert_eq!(Fixed(0), *queue.take().await);
+            assert_eq!(Duration::from_secs(65), start.elapsed());
+            producer.await.unwrap();
+
+            queue.close();
+            assert!(queue.take_or_closed().await.is_some());
+            assert!(queue.take_or_closed().await.is_none());
+        });
+    }
+
+    #[test]
+    fn test_async_capacity() {
+        runtime().block_on(async {
+            let queue = AsyncDelayQueue::from(
+                DelayQueue::builder()
+                    .deadline_mode(DeadlineMode::Captured)
+                    .capacity(2)
+                    .clock(TokioClock)
+                    .build(),
+            );
+            queue.put(Fixed(1_000_000_000)).await;
+            let mut reservation = queue.reserve_slots(1).await;
+            assert_eq!(Err(PutError::Full(Fixed(0))), queue.try_put(Fixed(0)));
+
+            let producer = {
+                let queue = queue.clone();
+                tokio::spawn(async move { queue.put(Fixed(0)).await })
+            };
+            tokio::task::yield_now().await;
+            assert!(!producer.is_finished());
+            reservation.put(Fixed(2_000_000_000)).unwrap();
+            assert_eq!(Fixed(1_000_000_000), *queue.take().await);
+            producer.await.unwrap();
+            assert_eq!(Fixed(0), *queue.take().await);
+            queue.wait_for_capacity().await;
+        });
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{ConfigChange, DelayQueue, Delayed};
+
+/// An administrative operation recorded by an [`AuditSink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AdminAction {
+    /// [`DelayQueue::clear`], with the number of elements dropped.
+    Clear { removed: usize },
+    /// [`DelayQueue::shift_deadlines_later`].
+    ShiftLater { by: Duration },
+    /// [`DelayQueue::shift_deadlines_earlier`].
+    ShiftEarlier { by: Duration },
+    /// [`DelayQueue::cancel_range`], with the number of elements removed.
+    CancelRange {
+        from: Instant,
+        to: Instant,
+        removed: usize,
+    },
+    /// [`DelayQueue::reconfigure`].
+    Reconfigure { change: ConfigChange },
+}
+
+/// One entry of the audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AuditRecord {
+    /// Wall-clock time the operation completed at.
+    pub at: SystemTime,
+    /// See [`DelayQueue::name`].
+    pub queue: Option<String>,
+    /// Who performed the operation, see
+    /// [`AdminHandle::with_actor`](crate::AdminHandle::with_actor); `None`
+    /// when called on the queue directly.
+    pub actor: Option<String>,
+    pub action: AdminAction,
+}
+
+/// Receives a record of every administrative operation on a queue, e.g. to
+/// append it to a log kept for compliance. Records are passed in the order
+/// operations complete, outside the queue lock.
+pub trait AuditSink: Send + Sync + 'static {
+    fn record(&self, record: &AuditRecord);
+}
+
+impl<F> AuditSink for F
+where
+    F: Fn(&AuditRecord) + Send + Sync + 'static,
+{
+    fn record(&self, record: &AuditRecord) {
+        self(record)
+    }
+}
+
+impl<T: Delayed> DelayQueue<T> {
+    /// Sends a record of every clear, deadline shift, range cancellation and
+    /// reconfiguration to `sink`, replacing the previous sink.
+    pub fn set_audit_sink<S: AuditSink>(&self, sink: S) {
+        self.queue.lock().audit = Some(Arc::new(sink));
+    }
+
+    /// Records `action`, performed by `actor`, with the sink if any. Must be
+    /// called without holding the lock.
+    pub(crate) fn audit(&self, actor: Option<&str>, action: AdminAction) {
+        let sink = self.queue.lock().audit.clone();
+        if let Some(sink) = sink {
+            sink.record(&AuditRecord {
+                at: SystemTime::now(),
+                queue: self.name().map(str::to_owned),
+                actor: actor.map(str::to_owned),
+                action,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::testing::Fixed;
+
+    #[test]
+    fn test_audit() {
+        let queue = DelayQueue::with_name("reminders");
+        let log = Arc::new(Mutex::new(Vec::new()));
+        {
+            let log = Arc::clone(&log);
+            queue.set_audit_sink(move |record: &AuditRecord| log.lock().push(record.clone()));
+        }
+        queue.try_put(Fixed(3_600_000_000_000)).unwrap();
+        let second = Duration::from_secs(1);
+        queue.shift_deadlines_later(second);
+        let admin = queue.admin().with_actor("alice");
+        admin.shift_deadlines_earlier(second);
+        admin.reconfigure(ConfigChange::new().capacity(Some(8)));
+        assert_eq!(1, admin.clear());
+
+        let log = log.lock();
+        assert_eq!(
+            vec![
+                (None, AdminAction::ShiftLater { by: second }),
+                (Some("alice"), AdminAction::ShiftEarlier { by: second }),
+                (
+                    Some("alice"),
+                    AdminAction::Reconfigure {
+                        change: ConfigChange::new().capacity(Some(8))
+                    }
+                ),
+                (Some("alice"), AdminAction::Clear { removed: 1 }),
+            ],
+            log.iter()
+                .map(|record| (record.actor.as_deref(), record.action.clone()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some("reminders"), log[0].queue.as_deref());
+    }
+}
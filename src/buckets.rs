@@ -0,0 +1,140 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// Pending deadlines grouped into fixed-width buckets, kept next to the heap
+/// so range queries skip the buckets outside the range instead of scanning
+/// every element. Deadlines before `origin` fall into the first bucket.
+#[derive(Debug, Clone)]
+pub(crate) struct BucketIndex {
+    origin: Instant,
+    width: Duration,
+    buckets: BTreeMap<u64, Bucket>,
+}
+
+/// The deadlines of one bucket, with the number of elements due at each.
+#[derive(Debug, Clone, Default)]
+struct Bucket {
+    len: usize,
+    deadlines: BTreeMap<Instant, usize>,
+}
+
+impl BucketIndex {
+    pub(crate) fn new(origin: Instant, width: Duration) -> Self {
+        Self {
+            origin,
+            width: width.max(Duration::from_nanos(1)),
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    fn bucket(&self, deadline: Instant) -> u64 {
+        let offset = deadline.saturating_duration_since(self.origin);
+        (offset.as_nanos() / self.width.as_nanos()).min(u64::MAX as u128) as u64
+    }
+
+    pub(crate) fn insert(&mut self, deadline: Instant) {
+        let bucket = self.buckets.entry(self.bucket(deadline)).or_default();
+        bucket.len += 1;
+        *bucket.deadlines.entry(deadline).or_insert(0) += 1;
+    }
+
+    pub(crate) fn remove(&mut self, deadline: Instant) {
+        let key = self.bucket(deadline);
+        let bucket = match self.buckets.get_mut(&key) {
+            Some(bucket) => bucket,
+            None => return,
+        };
+        if let Some(count) = bucket.deadlines.get_mut(&deadline) {
+            *count -= 1;
+            bucket.len -= 1;
+            if *count == 0 {
+                bucket.deadlines.remove(&deadline);
+            }
+        }
+        if bucket.len == 0 {
+            self.buckets.remove(&key);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.buckets.clear();
+    }
+
+    /// Replaces the content of the index by `deadlines`.
+    pub(crate) fn rebuild(&mut self, deadlines: impl Iterator<Item = Instant>) {
+        self.clear();
+        for deadline in deadlines {
+            self.insert(deadline);
+        }
+    }
+
+    /// Counts the deadlines in `from..=to`. Buckets entirely inside the range
+    /// are counted without looking at their deadlines.
+    pub(crate) fn count(&self, from: Instant, to: Instant) -> usize {
+        if from > to {
+            return 0;
+        }
+        let (first, last) = (self.bucket(from), self.bucket(to));
+        let partial = |bucket: &Bucket| {
+            bucket
+                .deadlines
+                .range(from..=to)
+                .map(|(_, count)| count)
+                .sum::<usize>()
+        };
+        self.buckets
+            .range(first..=last)
+            .map(|(key, bucket)| {
+                if *key == first || *key == last {
+                    partial(bucket)
+                } else {
+                    bucket.len
+                }
+            })
+            .sum()
+    }
+
+    /// Counts the deadlines up to and including `to`.
+    pub(crate) fn count_until(&self, to: Instant) -> usize {
+        let last = self.bucket(to);
+        let before = self
+            .buckets
+            .range(..last)
+            .map(|(_, bucket)| bucket.len)
+            .sum::<usize>();
+        before
+            + self.buckets.get(&last).map_or(0, |bucket| {
+                bucket.deadlines.range(..=to).map(|(_, count)| count).sum()
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bucket_index() {
+        let origin = Instant::now();
+        let minute = Duration::from_secs(60);
+        let mut index = BucketIndex::new(origin + minute, minute);
+        let at = |secs| origin + Duration::from_secs(secs);
+        for secs in &[0, 70, 70, 130, 150, 200, 400] {
+            index.insert(at(*secs));
+        }
+        assert_eq!(3, index.count_until(at(70)));
+        assert_eq!(5, index.count_until(at(190)));
+        assert_eq!(4, index.count(at(70), at(150)));
+        assert_eq!(2, index.count(at(100), at(190)));
+        assert_eq!(0, index.count(at(201), at(399)));
+
+        index.remove(at(70));
+        index.remove(at(500));
+        assert_eq!(4, index.count_until(at(190)));
+        index.rebuild(std::iter::once(at(10)));
+        assert_eq!(1, index.count_until(at(10)));
+        assert_eq!(1, index.count_until(at(400)));
+    }
+}
@@ -0,0 +1,204 @@
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+use crate::{Clock, DeadlineMode, DelayQueue, Delayed, Options, OrderingMode};
+
+/// Configures a [`DelayQueue`] option by option, see
+/// [`DelayQueue::builder`]. Unset options keep the defaults of
+/// [`DelayQueue::default`].
+pub struct DelayQueueBuilder<T> {
+    options: Options,
+    item: PhantomData<fn() -> T>,
+}
+
+impl<T: Delayed> DelayQueue<T> {
+    pub fn builder() -> DelayQueueBuilder<T> {
+        DelayQueueBuilder {
+            options: Options::default(),
+            item: PhantomData,
+        }
+    }
+}
+
+impl<T> DelayQueueBuilder<T> {
+    pub fn deadline_mode(mut self, mode: DeadlineMode) -> Self {
+        self.options.mode = mode;
+        self
+    }
+
+    pub fn ordering(mut self, ordering: OrderingMode) -> Self {
+        self.options.ordering = ordering;
+        self
+    }
+
+    /// See [`DelayQueue::bounded`].
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.options.capacity = Some(capacity);
+        self
+    }
+
+    /// See [`DelayQueue::with_clock`].
+    pub fn clock<C: Clock>(mut self, clock: C) -> Self {
+        self.options.clock = Arc::new(clock);
+        self
+    }
+
+    /// See [`DelayQueue::with_min_spacing`].
+    pub fn min_spacing(mut self, spacing: Duration) -> Self {
+        self.options.min_spacing = Some(spacing);
+        self
+    }
+
+    /// See [`DelayQueue::with_notify_coalescing`].
+    pub fn notify_coalescing(mut self, threshold: Duration) -> Self {
+        self.options.notify_threshold = Some(threshold);
+        self
+    }
+
+    /// Delivers elements ahead of their deadline by a running estimate of
+    /// how late wakeups are, so steady workloads see close to zero mean
+    /// lateness. Disabled by default.
+    pub fn lateness_compensation(mut self, enabled: bool) -> Self {
+        self.options.compensate_lateness = enabled;
+        self
+    }
+
+    /// Maintains an index of pending deadlines in buckets `width` wide next
+    /// to the heap, so windowed queries like [`DelayQueue::due_within`] do
+    /// not scan every element. Disabled by default.
+    pub fn bucket_index(mut self, width: Duration) -> Self {
+        self.options.bucket_width = Some(width);
+        self
+    }
+
+    /// Refuses elements due more than `horizon` ahead with
+    /// [`PutError::DeadlineTooFar`](crate::PutError::DeadlineTooFar), e.g.
+    /// to catch delays computed from garbage or in the wrong unit. Elements
+    /// due in the past are always accepted and delivered right away.
+    /// Unlimited by default.
+    pub fn max_horizon(mut self, horizon: Duration) -> Self {
+        self.options.max_horizon = Some(horizon);
+        self
+    }
+
+    /// When the head is due more than `granularity` ahead, wakes the waiting
+    /// consumer at the next multiple of `granularity` after the deadline
+    /// instead of exactly at it, so far-off wakeups line up and the host can
+    /// stay asleep longer, e.g. on battery. Such elements are delivered up
+    /// to `granularity` late. Disabled by default.
+    pub fn coarse_timers(mut self, granularity: Duration) -> Self {
+        self.options.coarse_timers = Some(granularity);
+        self
+    }
+
+    /// Keeps the contents in delivery order in shared chunks next to the
+    /// heap, so [`DelayQueue::view`] is O(1) and views taken between changes
+    /// share every chunk that did not change, instead of copying the whole
+    /// queue after each change. Costs an extra O(log n + 128) per insert and
+    /// removal. Disabled by default.
+    pub fn chunked_views(mut self, enabled: bool) -> Self {
+        self.options.chunked_views = enabled;
+        self
+    }
+
+    /// See [`DelayQueue::with_name`].
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.options.name = Some(name.into().into());
+        self
+    }
+
+    /// Adds a label describing the queue, e.g. its tenant, readable with
+    /// [`DelayQueue::labels`] and attached to its stats, health, events and
+    /// log records.
+    pub fn label(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.labels.insert(name, value);
+        self
+    }
+}
+
+impl<T> DelayQueueBuilder<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    pub fn build(self) -> DelayQueue<T> {
+        let queue = DelayQueue::new(self.options);
+        queue.subscribe_clock();
+        queue
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::*;
+    use crate::{testing::Fixed, PutError, SimClock};
+
+    #[test]
+    fn test_builder() {
+        let clock = SimClock::new();
+        let queue = DelayQueue::builder()
+            .deadline_mode(DeadlineMode::Captured)
+            .ordering(OrderingMode::DeadlineThenFifo)
+            .capacity(1)
+            .clock(clock.clone())
+            .name("reminders")
+            .label("tenant", "acme")
+            .build();
+        let events = queue.subscribe();
+        assert_eq!(Some(1), queue.capacity());
+        assert_eq!(Some("reminders"), queue.stats().name.as_deref());
+        assert_eq!(Some("acme"), queue.labels().get("tenant"));
+        assert_eq!(queue.labels(), &queue.stats().labels);
+        assert_eq!(queue.labels(), &queue.health().labels);
+        queue.try_put(Fixed(1_000_000_000)).unwrap();
+        assert_eq!(
+            Some("acme"),
+            events.try_recv().unwrap().labels().get("tenant")
+        );
+        assert_eq!(Err(PutError::Full(Fixed(0))), queue.try_put(Fixed(0)));
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(Fixed(1_000_000_000), *queue.take_or_closed().unwrap());
+    }
+
+    #[test]
+    fn test_max_horizon() {
+        let queue = DelayQueue::builder()
+            .deadline_mode(DeadlineMode::Captured)
+            .max_horizon(Duration::from_secs(60))
+            .build();
+        assert_eq!(
+            Err(PutError::DeadlineTooFar(Fixed(i64::MAX))),
+            queue.try_put(Fixed(i64::MAX))
+        );
+        queue.try_put(Fixed(i64::MIN)).unwrap();
+        queue.try_put(Fixed(1_000_000_000)).unwrap();
+        assert_eq!(Fixed(i64::MIN), *queue.take_or_closed().unwrap());
+        assert_eq!(1, queue.len());
+    }
+
+    #[test]
+    fn test_coarse_timers() {
+        let granularity = Duration::from_millis(30);
+        let queue = DelayQueue::builder()
+            .deadline_mode(DeadlineMode::Captured)
+            .coarse_timers(granularity)
+            .build();
+        queue.try_put(Fixed(45_000_000)).unwrap();
+        let deadline = queue.queue.lock().peek().unwrap().deadline;
+        let taker = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.take_or_closed().unwrap())
+        };
+        let wakes_at = loop {
+            if let Some(wakes_at) = queue.queue.lock().leader_wakes_at {
+                break wakes_at;
+            }
+            thread::yield_now();
+        };
+        let epoch = queue.queue.lock().epoch.0;
+        assert!(wakes_at >= deadline && wakes_at < deadline + granularity);
+        assert_eq!(0, (wakes_at - epoch).as_nanos() % granularity.as_nanos());
+        assert_eq!(Fixed(45_000_000), *taker.join().unwrap());
+    }
+}
@@ -0,0 +1,262 @@
+use std::{slice, sync::Arc};
+
+use crate::Entry;
+
+/// Entries per chunk after a split; chunks are split once they reach twice
+/// this many.
+const CHUNK: usize = 64;
+
+/// Chunks of entries in delivery order, shared between a queue and the views
+/// taken of it.
+pub(crate) type Chunks<T> = Arc<Vec<Arc<Vec<Entry<T>>>>>;
+
+/// The pending entries in delivery order, kept next to the heap in chunks of
+/// up to `2 * CHUNK` so views share every chunk a later change did not touch.
+///
+/// While no view holds the chunks, changes happen in place. Otherwise the
+/// first change after a view copies the list of chunk pointers and the chunk
+/// it touches, O(n / CHUNK + CHUNK), and leaves the view's chunks alone.
+pub(crate) struct ChunkedEntries<T> {
+    chunks: Chunks<T>,
+    len: usize,
+}
+
+impl<T> Clone for ChunkedEntries<T> {
+    fn clone(&self) -> Self {
+        Self {
+            chunks: Arc::clone(&self.chunks),
+            len: self.len,
+        }
+    }
+}
+
+impl<T> Default for ChunkedEntries<T> {
+    fn default() -> Self {
+        Self {
+            chunks: Arc::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<T: Ord> ChunkedEntries<T> {
+    /// Chunks `entries`, which must already be in delivery order.
+    pub(crate) fn from_sorted(entries: Vec<Entry<T>>) -> Self {
+        let len = entries.len();
+        let mut chunks = Vec::with_capacity(len.div_ceil(CHUNK));
+        let mut entries = entries.into_iter();
+        loop {
+            let chunk = entries.by_ref().take(CHUNK).collect::<Vec<_>>();
+            if chunk.is_empty() {
+                break;
+            }
+            chunks.push(Arc::new(chunk));
+        }
+        Self {
+            chunks: Arc::new(chunks),
+            len,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The current chunks, shared until the next change.
+    pub(crate) fn share(&self) -> Chunks<T> {
+        Arc::clone(&self.chunks)
+    }
+
+    pub(crate) fn insert(&mut self, entry: Entry<T>) {
+        let chunks = Arc::make_mut(&mut self.chunks);
+        self.len += 1;
+        if chunks.is_empty() {
+            chunks.push(Arc::new(vec![entry]));
+            return;
+        }
+        let index = chunks
+            .partition_point(|chunk| chunk.last().is_some_and(|last| *last < entry))
+            .min(chunks.len() - 1);
+        let chunk = Arc::make_mut(&mut chunks[index]);
+        let at = chunk.partition_point(|queued| *queued < entry);
+        chunk.insert(at, entry);
+        if chunk.len() >= 2 * CHUNK {
+            let tail = chunk.split_off(CHUNK);
+            chunks.insert(index + 1, Arc::new(tail));
+        }
+    }
+
+    /// Removes the entry with the sequence number of `entry`, which sorts
+    /// equal to it.
+    pub(crate) fn remove(&mut self, entry: &Entry<T>) {
+        let index = self
+            .chunks
+            .partition_point(|chunk| chunk.last().is_some_and(|last| last < entry));
+        let found = self.chunks.get(index).and_then(|chunk| {
+            let at = chunk.partition_point(|queued| queued < entry);
+            chunk[at..]
+                .iter()
+                .take_while(|queued| *queued == entry)
+                .position(|queued| queued.seq == entry.seq)
+                .map(|offset| at + offset)
+        });
+        let at = match found {
+            Some(at) => at,
+            None => {
+                debug_assert!(false, "entry {} is not in the chunks", entry.seq);
+                return;
+            }
+        };
+        let chunks = Arc::make_mut(&mut self.chunks);
+        let chunk = Arc::make_mut(&mut chunks[index]);
+        chunk.remove(at);
+        if chunk.is_empty() {
+            chunks.remove(index);
+        }
+        self.len -= 1;
+    }
+}
+
+/// Iterates over the elements of a [`QueueView`](crate::QueueView) in
+/// delivery order.
+pub struct ViewIter<'a, T> {
+    chunks: slice::Iter<'a, Arc<Vec<Entry<T>>>>,
+    chunk: slice::Iter<'a, Entry<T>>,
+    remaining: usize,
+}
+
+impl<'a, T> ViewIter<'a, T> {
+    pub(crate) fn new(chunks: &'a Chunks<T>, len: usize) -> Self {
+        Self {
+            chunks: chunks.iter(),
+            chunk: [].iter(),
+            remaining: len,
+        }
+    }
+}
+
+impl<'a, T> Iterator for ViewIter<'a, T> {
+    type Item = &'a Arc<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.chunk.next() {
+                self.remaining -= 1;
+                return Some(&entry.item);
+            }
+            self.chunk = self.chunks.next()?.iter();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for ViewIter<'_, T> {}
+
+/// Hands out the elements of a [`QueueView`](crate::QueueView) in delivery
+/// order, keeping its chunks alive until dropped.
+pub struct ViewIntoIter<T> {
+    chunks: Chunks<T>,
+    chunk: usize,
+    offset: usize,
+    remaining: usize,
+}
+
+impl<T> ViewIntoIter<T> {
+    pub(crate) fn new(chunks: Chunks<T>, len: usize) -> Self {
+        Self {
+            chunks,
+            chunk: 0,
+            offset: 0,
+            remaining: len,
+        }
+    }
+}
+
+impl<T> Iterator for ViewIntoIter<T> {
+    type Item = Arc<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let chunk = self.chunks.get(self.chunk)?;
+            if let Some(entry) = chunk.get(self.offset) {
+                self.offset += 1;
+                self.remaining -= 1;
+                return Some(Arc::clone(&entry.item));
+            }
+            self.chunk += 1;
+            self.offset = 0;
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for ViewIntoIter<T> {}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{testing::Fixed, OrderingMode};
+
+    fn entry(at: Instant, millis: u64, seq: u64) -> Entry<Fixed> {
+        Entry {
+            deadline: at + Duration::from_millis(millis),
+            seq,
+            urgent: false,
+            pinned: false,
+            shift: 0,
+            ordering: OrderingMode::DeadlineThenFifo,
+            item: Arc::new(Fixed(seq as i64)),
+            headers: None,
+        }
+    }
+
+    #[test]
+    fn test_chunked_entries() {
+        let at = Instant::now();
+        let mut chunked = ChunkedEntries::default();
+        for seq in 0..500 {
+            chunked.insert(entry(at, (seq * 7919) % 500, seq));
+        }
+        let shared = chunked.share();
+        assert!(shared.iter().all(|chunk| chunk.len() < 2 * CHUNK));
+
+        for seq in (0..500).step_by(2) {
+            chunked.remove(&entry(at, (seq * 7919) % 500, seq));
+        }
+        assert_eq!(250, chunked.len());
+        // The shared chunks are untouched.
+        assert_eq!(500, ViewIter::new(&shared, 500).count());
+        let current = chunked.share();
+        let deadlines = current
+            .iter()
+            .flat_map(|chunk| chunk.iter())
+            .map(|entry| entry.deadline)
+            .collect::<Vec<_>>();
+        assert!(deadlines.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(250, ViewIter::new(&current, 250).len());
+
+        let mut chunked = ChunkedEntries::from_sorted(
+            current
+                .iter()
+                .flat_map(|chunk| chunk.iter().cloned())
+                .collect(),
+        );
+        let before = chunked.share();
+        chunked.insert(entry(at, 1_000, 1_000));
+        let after = chunked.share();
+        let untouched = before
+            .iter()
+            .zip(after.iter())
+            .filter(|(a, b)| Arc::ptr_eq(a, b))
+            .count();
+        assert_eq!(before.len() - 1, untouched);
+    }
+}
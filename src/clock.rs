@@ -0,0 +1,110 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+/// Callback registered by a queue to be told that virtual time moved; returns
+/// `false` once the queue is gone.
+pub type AdvanceHook = Box<dyn Fn() -> bool + Send + Sync>;
+
+/// The time source a queue turns relative delays into deadlines with.
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> Instant;
+
+    /// Whether time only moves when told to. Queues on a virtual clock never
+    /// sleep on the OS timer and instead wait for an advance.
+    fn is_virtual(&self) -> bool {
+        false
+    }
+
+    /// Registers `hook` to be invoked after every advance of a virtual clock.
+    fn on_advance(&self, hook: AdvanceHook) {
+        let _ = hook;
+    }
+}
+
+/// The monotonic OS clock; the default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Tokio's clock, which follows `tokio::time::pause` and `advance` in
+/// tests; the default of an [`AsyncDelayQueue`](crate::AsyncDelayQueue).
+/// Outside a runtime it reads the OS clock.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioClock;
+
+#[cfg(feature = "tokio")]
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+}
+
+/// A virtual clock that only moves on [`advance`](SimClock::advance), for
+/// deterministic tests of code that embeds a queue.
+///
+/// With [`DeadlineMode::Dynamic`](crate::DeadlineMode::Dynamic) the elements'
+/// own `delayed` must be computed from this clock as well; otherwise prefer
+/// [`DeadlineMode::Captured`](crate::DeadlineMode::Captured).
+#[derive(Clone)]
+pub struct SimClock {
+    inner: Arc<SimClockInner>,
+}
+
+struct SimClockInner {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+    hooks: Mutex<Vec<AdvanceHook>>,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimClock {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(SimClockInner {
+                start: Instant::now(),
+                elapsed: Mutex::new(Duration::ZERO),
+                hooks: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Virtual time passed since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.inner.elapsed.lock()
+    }
+
+    /// Moves virtual time forward and wakes the queues using this clock.
+    pub fn advance(&self, by: Duration) {
+        *self.inner.elapsed.lock() += by;
+        self.inner.hooks.lock().retain(|hook| hook());
+    }
+}
+
+impl Clock for SimClock {
+    fn now(&self) -> Instant {
+        self.inner.start + self.elapsed()
+    }
+
+    fn is_virtual(&self) -> bool {
+        true
+    }
+
+    fn on_advance(&self, hook: AdvanceHook) {
+        self.inner.hooks.lock().push(hook);
+    }
+}
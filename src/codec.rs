@@ -0,0 +1,213 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+
+use crate::{delayed_until, instant_after, DeadlineMode, DelayQueue, Delayed};
+
+/// Encodes elements to bytes and back at the edges of a [`ByteDelayQueue`].
+pub trait Codec<T> {
+    type Error;
+
+    fn encode(&self, item: &T) -> Result<Bytes, Self::Error>;
+    fn decode(&self, bytes: &[u8]) -> Result<T, Self::Error>;
+}
+
+/// Encodes with `serde_json`.
+#[cfg(feature = "serde_json")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonCodec;
+
+#[cfg(feature = "serde_json")]
+impl<T> Codec<T> for JsonCodec
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    type Error = serde_json::Error;
+
+    fn encode(&self, item: &T) -> Result<Bytes, Self::Error> {
+        serde_json::to_vec(item).map(Bytes::from)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, Self::Error> {
+        serde_json::from_slice(bytes)
+    }
+}
+
+/// Encodes with `bincode`.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl<T> Codec<T> for BincodeCodec
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    type Error = bincode::Error;
+
+    fn encode(&self, item: &T) -> Result<Bytes, Self::Error> {
+        bincode::serialize(item).map(Bytes::from)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, Self::Error> {
+        bincode::deserialize(bytes)
+    }
+}
+
+/// Encodes protobuf messages with `prost`.
+#[cfg(feature = "prost")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProstCodec;
+
+#[cfg(feature = "prost")]
+impl<T> Codec<T> for ProstCodec
+where
+    T: prost::Message + Default,
+{
+    type Error = prost::DecodeError;
+
+    fn encode(&self, item: &T) -> Result<Bytes, Self::Error> {
+        Ok(Bytes::from(item.encode_to_vec()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, Self::Error> {
+        T::decode(bytes)
+    }
+}
+
+/// An encoded element, as stored in the core queue.
+struct Encoded {
+    deadline: Instant,
+    payload: Bytes,
+}
+
+impl Delayed for Encoded {
+    fn delayed(&self) -> i64 {
+        delayed_until(Instant::now(), self.deadline)
+    }
+}
+
+// Encoded elements with equal deadlines are delivered in insertion order.
+unordered!(Encoded);
+
+/// A type-erased delay queue of encoded payloads, so producers of different
+/// types can share one queue and backends only ever deal with bytes.
+#[derive(Clone)]
+pub struct ByteDelayQueue {
+    queue: DelayQueue<Encoded>,
+}
+
+impl Default for ByteDelayQueue {
+    fn default() -> Self {
+        Self {
+            queue: DelayQueue::with_deadline_mode(DeadlineMode::Captured),
+        }
+    }
+}
+
+impl ByteDelayQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Schedules `payload` to expire after `delay`, which saturates beyond
+    /// the range of `Instant`. Goes through the same checks as
+    /// [`DelayQueue::put`], so it is dropped on a closed queue.
+    pub fn put_bytes(&self, payload: Bytes, delay: Duration) {
+        let deadline = instant_after(self.queue.clock.now(), delay);
+        self.queue
+            .put_blocking(deadline, true, Arc::new(Encoded { deadline, payload }));
+    }
+
+    /// Encodes `item` with `codec` and schedules it to expire after `delay`.
+    pub fn put<T, C: Codec<T>>(
+        &self,
+        codec: &C,
+        item: &T,
+        delay: Duration,
+    ) -> Result<(), C::Error> {
+        self.put_bytes(codec.encode(item)?, delay);
+        Ok(())
+    }
+
+    /// Blocks until a payload expires and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the queue is closed and empty.
+    pub fn take_bytes(&self) -> Bytes {
+        self.queue.take_delivery().item.payload.clone()
+    }
+
+    /// Like [`take_bytes`](Self::take_bytes), decoded with `codec`.
+    pub fn take<T, C: Codec<T>>(&self, codec: &C) -> Result<T, C::Error> {
+        codec.decode(&self.take_bytes())
+    }
+
+    /// Stops accepting new payloads, see [`DelayQueue::close`].
+    pub fn close(&self) {
+        self.queue.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Utf8;
+
+    impl Codec<String> for Utf8 {
+        type Error = std::string::FromUtf8Error;
+
+        fn encode(&self, item: &String) -> Result<Bytes, Self::Error> {
+            Ok(Bytes::from(item.clone()))
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<String, Self::Error> {
+            String::from_utf8(bytes.to_vec())
+        }
+    }
+
+    #[test]
+    fn test_byte_queue() {
+        let queue = ByteDelayQueue::new();
+        queue
+            .put(&Utf8, &"later".to_string(), Duration::from_millis(20))
+            .unwrap();
+        queue.put_bytes(Bytes::from_static(&[0xff]), Duration::from_millis(10));
+        queue
+            .put(&Utf8, &"first".to_string(), Duration::ZERO)
+            .unwrap();
+
+        assert_eq!("first", queue.take(&Utf8).unwrap());
+        assert!(queue.take(&Utf8).is_err());
+        assert_eq!("later", queue.take(&Utf8).unwrap());
+
+        queue.put_bytes(Bytes::new(), Duration::MAX);
+        assert_eq!(1, queue.len());
+        queue.close();
+        queue.put_bytes(Bytes::new(), Duration::ZERO);
+        assert_eq!(1, queue.len());
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_json_codec() {
+        let queue = ByteDelayQueue::new();
+        queue
+            .put(&JsonCodec, &vec![1, 2, 3], Duration::ZERO)
+            .unwrap();
+        assert_eq!(&b"[1,2,3]"[..], &queue.take_bytes()[..]);
+    }
+}
@@ -0,0 +1,135 @@
+use std::{error::Error, fmt, io};
+
+use bytes::Bytes;
+
+use crate::Codec;
+
+/// A compression algorithm for [`Compressed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// LZ4 with the uncompressed size prepended; fast, moderate ratio.
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Zstandard at the given level, 1 to 22; `0` picks the library default.
+    #[cfg(feature = "zstd")]
+    Zstd { level: i32 },
+}
+
+/// Wraps a [`Codec`] to compress what it encodes, e.g. large JSON payloads
+/// of a [`ByteDelayQueue`](crate::ByteDelayQueue) or a persisted
+/// [`DelayedEntry`](crate::DelayedEntry). Decoding expects the same
+/// compression.
+#[derive(Debug, Clone, Copy)]
+pub struct Compressed<C> {
+    codec: C,
+    compression: Compression,
+}
+
+impl<C> Compressed<C> {
+    pub fn new(codec: C, compression: Compression) -> Self {
+        Self { codec, compression }
+    }
+}
+
+/// Returned by [`Compressed`] when the inner codec or the compression
+/// fails.
+#[derive(Debug)]
+pub enum CompressedError<E> {
+    Codec(E),
+    Compression(io::Error),
+}
+
+impl<E: fmt::Display> fmt::Display for CompressedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompressedError::Codec(err) => err.fmt(f),
+            CompressedError::Compression(err) => write!(f, "compression failed: {}", err),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for CompressedError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CompressedError::Codec(err) => Some(err),
+            CompressedError::Compression(err) => Some(err),
+        }
+    }
+}
+
+impl<T, C: Codec<T>> Codec<T> for Compressed<C> {
+    type Error = CompressedError<C::Error>;
+
+    fn encode(&self, item: &T) -> Result<Bytes, Self::Error> {
+        let encoded = self.codec.encode(item).map_err(CompressedError::Codec)?;
+        let compressed = match self.compression {
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => lz4_flex::compress_prepend_size(&encoded),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { level } => {
+                zstd::encode_all(&encoded[..], level).map_err(CompressedError::Compression)?
+            }
+        };
+        Ok(Bytes::from(compressed))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, Self::Error> {
+        let decompressed = match self.compression {
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => lz4_flex::decompress_size_prepended(bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+                .map_err(CompressedError::Compression)?,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { .. } => {
+                zstd::decode_all(bytes).map_err(CompressedError::Compression)?
+            }
+        };
+        self.codec
+            .decode(&decompressed)
+            .map_err(CompressedError::Codec)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Utf8;
+
+    impl Codec<String> for Utf8 {
+        type Error = std::string::FromUtf8Error;
+
+        fn encode(&self, item: &String) -> Result<Bytes, Self::Error> {
+            Ok(Bytes::from(item.clone()))
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<String, Self::Error> {
+            String::from_utf8(bytes.to_vec())
+        }
+    }
+
+    fn round_trip(compression: Compression) {
+        let codec = Compressed::new(Utf8, compression);
+        let payload = "{\"reminder\":\"standup\"}".repeat(64);
+        let encoded = codec.encode(&payload).unwrap();
+        assert!(encoded.len() < payload.len() / 4);
+        assert_eq!(payload, codec.decode(&encoded).unwrap());
+        assert!(matches!(
+            codec.decode(b"garbage"),
+            Err(CompressedError::Compression(_))
+        ));
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_lz4() {
+        round_trip(Compression::Lz4);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd() {
+        round_trip(Compression::Zstd { level: 3 });
+    }
+}
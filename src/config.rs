@@ -0,0 +1,204 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AdminAction, DeadlineMode, DelayQueue, DelayQueueBuilder, Delayed, OrderingMode, QueueEvent,
+};
+
+/// Runtime settings of a queue, deserializable from a service's config file
+/// with the `serde` feature. Missing fields keep their defaults.
+///
+/// Covers every builder setting except the [clock](DelayQueueBuilder::clock),
+/// which is code rather than configuration.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+#[non_exhaustive]
+pub struct QueueConfig {
+    /// `None` for an unbounded queue.
+    pub capacity: Option<usize>,
+    pub deadline_mode: DeadlineMode,
+    pub ordering: OrderingMode,
+    /// Delivers at most one element per this many milliseconds, see
+    /// [`DelayQueue::with_min_spacing`].
+    pub min_spacing_ms: Option<u64>,
+    /// See [`DelayQueue::with_notify_coalescing`].
+    pub notify_coalescing_ms: Option<u64>,
+    /// See [`DelayQueueBuilder::coarse_timers`].
+    pub coarse_timers_ms: Option<u64>,
+    /// See [`DelayQueueBuilder::bucket_index`].
+    pub bucket_width_ms: Option<u64>,
+    /// See [`DelayQueueBuilder::max_horizon`].
+    pub max_horizon_ms: Option<u64>,
+    /// See [`DelayQueueBuilder::lateness_compensation`].
+    pub lateness_compensation: bool,
+    /// See [`DelayQueueBuilder::chunked_views`].
+    pub chunked_views: bool,
+    /// See [`DelayQueue::with_name`].
+    pub name: Option<String>,
+    /// See [`DelayQueueBuilder::label`].
+    pub labels: BTreeMap<String, String>,
+    /// The delays of the tiers of a [`RetryTiers`](crate::RetryTiers) built
+    /// with [`from_config`](crate::RetryTiers::from_config); a plain queue
+    /// does not retry.
+    pub retry_delays_ms: Vec<u64>,
+}
+
+impl<T> DelayQueueBuilder<T> {
+    /// Applies every setting of `config`.
+    pub fn config(mut self, config: &QueueConfig) -> Self {
+        self = self
+            .deadline_mode(config.deadline_mode)
+            .ordering(config.ordering);
+        if let Some(capacity) = config.capacity {
+            self = self.capacity(capacity);
+        }
+        if let Some(ms) = config.min_spacing_ms {
+            self = self.min_spacing(Duration::from_millis(ms));
+        }
+        if let Some(ms) = config.notify_coalescing_ms {
+            self = self.notify_coalescing(Duration::from_millis(ms));
+        }
+        if let Some(ms) = config.coarse_timers_ms {
+            self = self.coarse_timers(Duration::from_millis(ms));
+        }
+        if let Some(ms) = config.bucket_width_ms {
+            self = self.bucket_index(Duration::from_millis(ms));
+        }
+        if let Some(ms) = config.max_horizon_ms {
+            self = self.max_horizon(Duration::from_millis(ms));
+        }
+        if let Some(name) = config.name.as_ref() {
+            self = self.name(name.as_str());
+        }
+        for (name, value) in config.labels.iter() {
+            self = self.label(name.as_str(), value.as_str());
+        }
+        self.lateness_compensation(config.lateness_compensation)
+            .chunked_views(config.chunked_views)
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    pub fn from_config(config: &QueueConfig) -> Self {
+        Self::builder().config(config).build()
+    }
+}
+
+/// Settings to change on a live queue with [`DelayQueue::reconfigure`];
+/// settings left unset keep their current value.
+///
+/// Only the capacity, the rate limit and notification coalescing can be
+/// changed; every other setting is fixed when the queue is built.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConfigChange {
+    capacity: Option<Option<usize>>,
+    min_spacing: Option<Option<Duration>>,
+    notify_coalescing: Option<Option<Duration>>,
+}
+
+impl ConfigChange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `None` makes the queue unbounded. Shrinking below the current length
+    /// keeps every element and only blocks further puts.
+    pub fn capacity(mut self, capacity: Option<usize>) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// `None` lifts the rate limit.
+    pub fn min_spacing(mut self, spacing: Option<Duration>) -> Self {
+        self.min_spacing = Some(spacing);
+        self
+    }
+
+    pub fn notify_coalescing(mut self, threshold: Option<Duration>) -> Self {
+        self.notify_coalescing = Some(threshold);
+        self
+    }
+}
+
+impl<T: Delayed> DelayQueue<T> {
+    /// Applies `change` to the live queue and emits
+    /// [`QueueEvent::Reconfigured`], see [`ConfigChange`] for what can be
+    /// changed. Blocked producers and consumers are woken
+    /// to pick up the new settings.
+    pub fn reconfigure(&self, change: ConfigChange) {
+        self.reconfigure_as(change, None);
+    }
+
+    pub(crate) fn reconfigure_as(&self, change: ConfigChange, actor: Option<&str>) {
+        {
+            let mut guard = self.queue.lock();
+            if let Some(capacity) = change.capacity {
+                guard.capacity = capacity;
+            }
+            if let Some(spacing) = change.min_spacing {
+                guard.min_spacing = spacing;
+            }
+            if let Some(threshold) = change.notify_coalescing {
+                guard.notify_threshold = threshold;
+            }
+            guard.emit(QueueEvent::Reconfigured {
+                labels: Arc::clone(&self.labels),
+            });
+        }
+        self.not_full.notify_all();
+        self.available.notify_all();
+        self.audit(actor, AdminAction::Reconfigure { change });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing::Fixed, PutError};
+
+    #[test]
+    fn test_reconfigure() {
+        let queue = DelayQueue::bounded(1);
+        let events = queue.subscribe();
+        queue.try_put(Fixed(-1)).unwrap();
+        assert_eq!(Err(PutError::Full(Fixed(-2))), queue.try_put(Fixed(-2)));
+
+        queue.reconfigure(ConfigChange::new().capacity(Some(2)));
+        queue.try_put(Fixed(-2)).unwrap();
+        assert_eq!(Some(2), queue.capacity());
+        assert!(events
+            .try_iter()
+            .any(|event| matches!(event, QueueEvent::Reconfigured { .. })));
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_from_json() {
+        let config: QueueConfig = serde_json::from_str(
+            r#"{ "capacity": 8, "ordering": "deadline_then_fifo", "min_spacing_ms": 5,
+                 "retry_delays_ms": [1000, 60000], "max_horizon_ms": 60000,
+                 "name": "reminders", "labels": { "tenant": "acme" }, "chunked_views": true }"#,
+        )
+        .unwrap();
+        assert_eq!(OrderingMode::DeadlineThenFifo, config.ordering);
+        assert_eq!(DeadlineMode::Dynamic, config.deadline_mode);
+        assert!(config.chunked_views);
+
+        let queue = DelayQueue::<Fixed>::from_config(&config);
+        assert_eq!(Some(8), queue.capacity());
+        assert_eq!(vec![1000, 60000], config.retry_delays_ms);
+        assert_eq!(Some("reminders"), queue.name());
+        assert_eq!(Some("acme"), queue.labels().get("tenant"));
+        assert_eq!(
+            Err(PutError::DeadlineTooFar(Fixed(61_000_000_000))),
+            queue.try_put(Fixed(61_000_000_000))
+        );
+        assert!(serde_json::from_str::<QueueConfig>(r#"{ "capacty": 8 }"#).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use std::{collections::BTreeSet, ops::Range, sync::Arc, time::Instant};
+
+use crate::Headers;
+
+/// An element handed out by the queue together with its delivery metadata.
+#[derive(Debug)]
+pub struct Delivery<T> {
+    pub item: Arc<T>,
+    /// Monotonically increasing per queue, starting at the value given to
+    /// [`DelayQueue::resume_delivery_seq`](crate::DelayQueue::resume_delivery_seq).
+    pub seq: u64,
+    pub deadline: Instant,
+    pub delivered_at: Instant,
+    /// Attached with [`DelayQueue::try_put_with_headers`](crate::DelayQueue::try_put_with_headers).
+    pub headers: Option<Arc<Headers>>,
+}
+
+impl<T> Delivery<T> {
+    pub fn into_item(self) -> Arc<T> {
+        self.item
+    }
+}
+
+/// Tracks observed delivery sequence numbers and reports the missing ones.
+///
+/// Useful for downstream exactly-once bookkeeping, e.g. to find deliveries
+/// that were lost between the last checkpoint and a crash.
+#[derive(Debug, Default, Clone)]
+pub struct SequenceTracker {
+    next: u64,
+    ahead: BTreeSet<u64>,
+}
+
+impl SequenceTracker {
+    /// Starts tracking at `next`, the first sequence number expected.
+    pub fn starting_at(next: u64) -> Self {
+        Self {
+            next,
+            ahead: BTreeSet::new(),
+        }
+    }
+
+    /// Records `seq`; returns `false` if it was already observed.
+    pub fn observe(&mut self, seq: u64) -> bool {
+        if seq < self.next || !self.ahead.insert(seq) {
+            return false;
+        }
+        while self.ahead.remove(&self.next) {
+            self.next += 1;
+        }
+        true
+    }
+
+    /// The lowest sequence number below which everything was observed.
+    pub fn contiguous_until(&self) -> u64 {
+        self.next
+    }
+
+    /// Missing ranges between the contiguous prefix and the highest observed.
+    pub fn gaps(&self) -> Vec<Range<u64>> {
+        let mut gaps = Vec::new();
+        let mut start = self.next;
+        for &seq in &self.ahead {
+            if seq > start {
+                gaps.push(start..seq);
+            }
+            start = seq + 1;
+        }
+        gaps
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sequence_gaps() {
+        let mut tracker = SequenceTracker::starting_at(3);
+        assert!(tracker.observe(3));
+        assert!(tracker.observe(6));
+        assert!(tracker.observe(9));
+        assert!(!tracker.observe(6));
+        assert!(!tracker.observe(2));
+        assert_eq!(4, tracker.contiguous_until());
+        assert_eq!(vec![4..6, 7..9], tracker.gaps());
+        assert!(tracker.observe(4));
+        assert!(tracker.observe(5));
+        assert_eq!(7, tracker.contiguous_until());
+        assert_eq!(vec![7..9], tracker.gaps());
+    }
+}
@@ -0,0 +1,152 @@
+//! Counters of the waiting machinery, kept when the `diagnostics` feature is
+//! enabled; no-ops otherwise, so the hot paths call them unconditionally.
+
+#[cfg(feature = "diagnostics")]
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use parking_lot::{Mutex, MutexGuard};
+
+#[cfg(feature = "diagnostics")]
+use crate::{DelayQueue, Delayed};
+
+/// How a taker's wait ended, other than by its cutoff passing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Wakeup {
+    /// The leader's timer fired or it was notified.
+    Leader,
+    /// A follower was notified.
+    Follower,
+}
+
+#[derive(Default)]
+pub(crate) struct Diagnostics {
+    #[cfg(feature = "diagnostics")]
+    spurious_wakeups: AtomicU64,
+    #[cfg(feature = "diagnostics")]
+    futile_leader_wakeups: AtomicU64,
+    #[cfg(feature = "diagnostics")]
+    lock_waits: AtomicU64,
+    #[cfg(feature = "diagnostics")]
+    lock_wait_nanos: AtomicU64,
+}
+
+impl Diagnostics {
+    /// Locks `mutex`, counting the acquisition and the time spent if it was
+    /// held by someone else.
+    pub(crate) fn lock<'a, T>(&self, mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+        #[cfg(feature = "diagnostics")]
+        {
+            if let Some(guard) = mutex.try_lock() {
+                return guard;
+            }
+            let started = Instant::now();
+            let guard = mutex.lock();
+            let waited = started.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+            self.lock_waits.fetch_add(1, Ordering::Relaxed);
+            self.lock_wait_nanos.fetch_add(waited, Ordering::Relaxed);
+            guard
+        }
+        #[cfg(not(feature = "diagnostics"))]
+        mutex.lock()
+    }
+
+    /// Records that a taker woke up as `wakeup` and found nothing to
+    /// deliver; `None` if it did not wait.
+    #[cfg_attr(not(feature = "diagnostics"), allow(unused_variables))]
+    pub(crate) fn woke_for_nothing(&self, wakeup: Option<Wakeup>) {
+        #[cfg(feature = "diagnostics")]
+        match wakeup {
+            Some(Wakeup::Leader) => self.futile_leader_wakeups.fetch_add(1, Ordering::Relaxed),
+            Some(Wakeup::Follower) => self.spurious_wakeups.fetch_add(1, Ordering::Relaxed),
+            None => 0,
+        };
+    }
+
+    #[cfg(feature = "diagnostics")]
+    fn report(&self) -> DiagnosticsReport {
+        DiagnosticsReport {
+            spurious_wakeups: self.spurious_wakeups.load(Ordering::Relaxed),
+            futile_leader_wakeups: self.futile_leader_wakeups.load(Ordering::Relaxed),
+            lock_waits: self.lock_waits.load(Ordering::Relaxed),
+            lock_wait_time: Duration::from_nanos(self.lock_wait_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Counters of how takers and producers waited, see
+/// [`DelayQueue::diagnostics`].
+#[cfg(feature = "diagnostics")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DiagnosticsReport {
+    /// Followers woken with nothing to deliver.
+    pub spurious_wakeups: u64,
+    /// Times the leader woke, by its timer or a notification, with nothing
+    /// to deliver and went back to waiting.
+    pub futile_leader_wakeups: u64,
+    /// Times putting or taking found the queue locked by another thread.
+    pub lock_waits: u64,
+    /// Total time spent in those waits.
+    pub lock_wait_time: Duration,
+}
+
+#[cfg(feature = "diagnostics")]
+impl fmt::Display for DiagnosticsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} spurious wakeups, {} futile leader wakeups, {} lock waits totalling {:?}",
+            self.spurious_wakeups, self.futile_leader_wakeups, self.lock_waits, self.lock_wait_time
+        )
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl<T: Delayed> DelayQueue<T> {
+    /// Counters of the waiting machinery since the queue was created, for
+    /// tuning high-throughput deployments. The report is also logged when
+    /// the queue is closed, at the level of
+    /// [`LogLevels::close`](crate::LogLevels::close) if the `log` feature is
+    /// enabled.
+    pub fn diagnostics(&self) -> DiagnosticsReport {
+        self.diagnostics.report()
+    }
+}
+
+#[cfg(all(test, feature = "diagnostics"))]
+mod test {
+    use std::thread;
+
+    use super::*;
+    use crate::testing::Fixed;
+
+    #[test]
+    fn test_diagnostics() {
+        let queue = DelayQueue::default();
+        queue.try_put(Fixed(60_000_000_000)).unwrap();
+        let takers: Vec<_> = (0..2)
+            .map(|_| {
+                let queue = queue.clone();
+                thread::spawn(move || queue.take_or_closed())
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(20));
+        // Earlier than the head, so a taker is woken but cannot deliver yet.
+        queue.try_put(Fixed(30_000_000_000)).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let report = queue.diagnostics();
+        assert!(
+            report.futile_leader_wakeups + report.spurious_wakeups >= 1,
+            "{}",
+            report
+        );
+        assert_eq!(2, queue.close_and_drain().len());
+        for taker in takers {
+            assert!(taker.join().unwrap().is_none());
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use crate::{delayed_until, worker::Periodic, DelayQueue, Delayed, QueueEvent};
+
+/// Background drift checks, see [`DelayQueue::spawn_drift_monitor`].
+pub struct DriftMonitor {
+    worker: Periodic,
+}
+
+impl DriftMonitor {
+    pub fn stop(&mut self) {
+        self.worker.stop();
+    }
+}
+
+impl<T: Delayed> DelayQueue<T> {
+    /// Nanoseconds the wall clock moved ahead of the queue's monotonic
+    /// clock, negative if it fell behind, since the queue was created or
+    /// drift was last reported.
+    ///
+    /// NTP steps and VM pauses show up here; elements scheduled from
+    /// wall-clock times fire off by this much.
+    pub fn clock_drift(&self) -> i64 {
+        let (instant, wall) = self.queue.lock().epoch;
+        let monotonic = delayed_until(instant, self.clock.now());
+        let wall = match SystemTime::now().duration_since(wall) {
+            Ok(ahead) => ahead.as_nanos().min(i64::MAX as u128) as i64,
+            Err(behind) => -(behind.duration().as_nanos().min(i64::MAX as u128) as i64),
+        };
+        wall.saturating_sub(monotonic)
+    }
+
+    /// Emits [`QueueEvent::ClockDrift`] and starts measuring afresh if the
+    /// drift exceeds `threshold` either way; returns the drift if it did.
+    pub fn check_clock_drift(&self, threshold: Duration) -> Option<i64> {
+        let drift = self.clock_drift();
+        if drift.unsigned_abs() as u128 <= threshold.as_nanos() {
+            return None;
+        }
+        let mut guard = self.queue.lock();
+        guard.epoch = (self.clock.now(), SystemTime::now());
+        guard.emit(QueueEvent::ClockDrift {
+            drift,
+            labels: Arc::clone(&self.labels),
+        });
+        Some(drift)
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    /// Runs [`check_clock_drift`](Self::check_clock_drift) every `interval`
+    /// on a background thread until the returned monitor is stopped or
+    /// dropped.
+    pub fn spawn_drift_monitor(&self, interval: Duration, threshold: Duration) -> DriftMonitor {
+        let queue = self.clone();
+        DriftMonitor {
+            worker: Periodic::spawn(interval, move || {
+                queue.check_clock_drift(threshold);
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing::Fixed, DeadlineMode, SimClock};
+
+    #[test]
+    fn test_clock_drift() {
+        let clock = SimClock::new();
+        let queue = DelayQueue::<Fixed>::with_clock(DeadlineMode::Captured, clock.clone());
+        let events = queue.subscribe();
+        assert!(queue.check_clock_drift(Duration::from_secs(1)).is_none());
+
+        // Monotonic time jumping an hour ahead looks like the wall clock
+        // falling behind.
+        clock.advance(Duration::from_secs(3600));
+        let drift = queue.check_clock_drift(Duration::from_secs(1)).unwrap();
+        assert!(drift < -3_590_000_000_000);
+        assert!(matches!(
+            events.try_recv(),
+            Ok(QueueEvent::ClockDrift { .. })
+        ));
+        assert!(queue.check_clock_drift(Duration::from_secs(1)).is_none());
+    }
+}
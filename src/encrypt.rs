@@ -0,0 +1,206 @@
+use std::{error::Error, fmt};
+
+use bytes::Bytes;
+use chacha20poly1305::{
+    aead::{Aead, OsRng, Payload},
+    AeadCore, ChaCha20Poly1305, Key, KeyInit, Nonce,
+};
+
+use crate::Codec;
+
+/// Bytes of the key id and nonce written before the ciphertext.
+const HEADER: usize = 4 + 12;
+
+/// Supplies the keys of [`Encrypted`], so keys can come from a secret store
+/// and be rotated without losing payloads encrypted under older ones.
+pub trait KeyProvider {
+    /// The id and key new payloads are encrypted with.
+    fn current(&self) -> (u32, [u8; 32]);
+
+    /// The key with `id`, or `None` if it is unknown or retired.
+    fn key(&self, id: u32) -> Option<[u8; 32]>;
+}
+
+/// A single fixed key with id `0`.
+impl KeyProvider for [u8; 32] {
+    fn current(&self) -> (u32, [u8; 32]) {
+        (0, *self)
+    }
+
+    fn key(&self, id: u32) -> Option<[u8; 32]> {
+        (id == 0).then_some(*self)
+    }
+}
+
+/// Wraps a [`Codec`] to encrypt what it encodes with ChaCha20-Poly1305, so
+/// payloads holding personal data are never persisted in plaintext. Each
+/// payload carries the id of its key and a random nonce. Deliberately not
+/// `Debug`, which would print the keys.
+#[derive(Clone, Copy)]
+pub struct Encrypted<C, K> {
+    codec: C,
+    keys: K,
+}
+
+impl<C, K: KeyProvider> Encrypted<C, K> {
+    pub fn new(codec: C, keys: K) -> Self {
+        Self { codec, keys }
+    }
+}
+
+/// Returned by [`Encrypted`] when the inner codec fails or a payload cannot
+/// be decrypted.
+#[derive(Debug)]
+pub enum EncryptedError<E> {
+    Codec(E),
+    /// The payload names a key the [`KeyProvider`] does not know.
+    UnknownKey(u32),
+    /// The payload is truncated, was tampered with, or the key is wrong.
+    Invalid,
+}
+
+impl<E: fmt::Display> fmt::Display for EncryptedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptedError::Codec(err) => err.fmt(f),
+            EncryptedError::UnknownKey(id) => write!(f, "unknown encryption key {}", id),
+            EncryptedError::Invalid => write!(f, "payload failed to decrypt"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for EncryptedError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EncryptedError::Codec(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl<T, C: Codec<T>, K: KeyProvider> Codec<T> for Encrypted<C, K> {
+    type Error = EncryptedError<C::Error>;
+
+    fn encode(&self, item: &T) -> Result<Bytes, Self::Error> {
+        let encoded = self.codec.encode(item).map_err(EncryptedError::Codec)?;
+        let (id, key) = self.keys.current();
+        let id = id.to_be_bytes();
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &encoded,
+                    aad: &id,
+                },
+            )
+            .map_err(|_| EncryptedError::Invalid)?;
+        let mut sealed = Vec::with_capacity(HEADER + ciphertext.len());
+        sealed.extend_from_slice(&id);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(Bytes::from(sealed))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, Self::Error> {
+        if bytes.len() < HEADER {
+            return Err(EncryptedError::Invalid);
+        }
+        let (id, rest) = bytes.split_at(4);
+        let (nonce, ciphertext) = rest.split_at(12);
+        let key_id = u32::from_be_bytes([id[0], id[1], id[2], id[3]]);
+        let key = self
+            .keys
+            .key(key_id)
+            .ok_or(EncryptedError::UnknownKey(key_id))?;
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: id,
+                },
+            )
+            .map_err(|_| EncryptedError::Invalid)?;
+        self.codec.decode(&plaintext).map_err(EncryptedError::Codec)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Utf8;
+
+    impl Codec<String> for Utf8 {
+        type Error = std::string::FromUtf8Error;
+
+        fn encode(&self, item: &String) -> Result<Bytes, Self::Error> {
+            Ok(Bytes::from(item.clone()))
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<String, Self::Error> {
+            String::from_utf8(bytes.to_vec())
+        }
+    }
+
+    /// Key 1 only, as before a rotation.
+    struct OldKey;
+
+    impl KeyProvider for OldKey {
+        fn current(&self) -> (u32, [u8; 32]) {
+            (1, [1; 32])
+        }
+
+        fn key(&self, id: u32) -> Option<[u8; 32]> {
+            (id == 1).then_some([1; 32])
+        }
+    }
+
+    /// Encrypts with key 2 and still decrypts payloads of key 1.
+    struct Rotated;
+
+    impl KeyProvider for Rotated {
+        fn current(&self) -> (u32, [u8; 32]) {
+            (2, [2; 32])
+        }
+
+        fn key(&self, id: u32) -> Option<[u8; 32]> {
+            matches!(id, 1 | 2).then_some([id as u8; 32])
+        }
+    }
+
+    #[test]
+    fn test_encrypted() {
+        let codec = Encrypted::new(Utf8, [7; 32]);
+        let payload = "alice@example.com".to_owned();
+        let sealed = codec.encode(&payload).unwrap();
+        assert!(!sealed.windows(5).any(|window| window == b"alice"));
+        assert_eq!(payload, codec.decode(&sealed).unwrap());
+        assert!(matches!(
+            Encrypted::new(Utf8, [8; 32]).decode(&sealed),
+            Err(EncryptedError::Invalid)
+        ));
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let old = Encrypted::new(Utf8, OldKey);
+        let new = Encrypted::new(Utf8, Rotated);
+        let payload = "alice@example.com".to_owned();
+        let sealed = old.encode(&payload).unwrap();
+        assert_eq!(payload, new.decode(&sealed).unwrap());
+        let resealed = new.encode(&payload).unwrap();
+        assert!(matches!(
+            old.decode(&resealed),
+            Err(EncryptedError::UnknownKey(2))
+        ));
+
+        let mut tampered = resealed.to_vec();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            new.decode(&tampered),
+            Err(EncryptedError::Invalid)
+        ));
+    }
+}
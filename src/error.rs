@@ -0,0 +1,33 @@
+use std::{error::Error, fmt};
+
+/// Returned when an element could not be inserted; gives the element back.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PutError<T> {
+    /// The queue is bounded and has no free slot.
+    Full(T),
+    /// The queue was closed.
+    Closed(T),
+    /// The element is due further ahead than the queue's
+    /// [maximum horizon](crate::DelayQueueBuilder::max_horizon).
+    DeadlineTooFar(T),
+}
+
+impl<T> PutError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            PutError::Full(t) | PutError::Closed(t) | PutError::DeadlineTooFar(t) => t,
+        }
+    }
+}
+
+impl<T> fmt::Display for PutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PutError::Full(_) => write!(f, "delay queue is full"),
+            PutError::Closed(_) => write!(f, "delay queue is closed"),
+            PutError::DeadlineTooFar(_) => write!(f, "deadline is beyond the queue's horizon"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> Error for PutError<T> {}
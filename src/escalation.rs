@@ -0,0 +1,279 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{delayed_until, instant_after, DeadlineMode, DelayQueue, Delayed};
+
+/// The steps of an escalation chain: each payload is delivered its interval
+/// after the previous step went unacknowledged, the first one its interval
+/// after [`EscalationQueue::start`].
+pub struct EscalationPolicy<T> {
+    steps: Vec<(Duration, Arc<T>)>,
+}
+
+impl<T> Default for EscalationPolicy<T> {
+    fn default() -> Self {
+        Self { steps: Vec::new() }
+    }
+}
+
+impl<T> Clone for EscalationPolicy<T> {
+    fn clone(&self) -> Self {
+        Self {
+            steps: self.steps.clone(),
+        }
+    }
+}
+
+impl<T> EscalationPolicy<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a step delivering `payload`, e.g. a page to the next on-call
+    /// engineer, `after` the previous step.
+    pub fn step(mut self, after: Duration, payload: T) -> Self {
+        self.steps.push((after, Arc::new(payload)));
+        self
+    }
+}
+
+/// A delivered step of an escalation chain.
+#[derive(Debug)]
+pub struct Escalation<T> {
+    /// The chain to [`ack`](EscalationQueue::ack) to stop escalating.
+    pub chain: u64,
+    /// The index of the step in its policy.
+    pub step: usize,
+    pub item: Arc<T>,
+}
+
+/// The pending step of a chain, as stored in the core queue.
+struct Step<T> {
+    chain: u64,
+    index: usize,
+    deadline: Instant,
+    item: Arc<T>,
+}
+
+impl<T> Delayed for Step<T> {
+    fn delayed(&self) -> i64 {
+        delayed_until(Instant::now(), self.deadline)
+    }
+}
+
+// Steps with equal deadlines are delivered in insertion order.
+unordered!(Step<T>);
+
+/// The steps of a started chain, shared by its pending entries.
+type Steps<T> = Arc<[(Duration, Arc<T>)]>;
+
+/// The chains not acknowledged yet, by id. Whoever removes a chain from
+/// here stops it; core entries of a removed chain are stale and skipped.
+struct Chains<T> {
+    steps: HashMap<u64, Steps<T>>,
+    next_id: u64,
+    /// Core entries of acknowledged chains still in the core queue.
+    stale: usize,
+}
+
+/// A delay queue of escalation chains: each delivered step that is not
+/// acknowledged schedules the next step of its [`EscalationPolicy`], until
+/// [`ack`](Self::ack) is called or the policy runs out of steps.
+///
+/// Acknowledging leaves the pending step in the core queue, where it is
+/// skipped when it comes due. Such steps are swept in one pass once they
+/// outnumber the live chains, so `ack` costs amortized O(1).
+pub struct EscalationQueue<T> {
+    queue: DelayQueue<Step<T>>,
+    chains: Arc<Mutex<Chains<T>>>,
+}
+
+impl<T> Default for EscalationQueue<T> {
+    fn default() -> Self {
+        Self {
+            queue: DelayQueue::with_deadline_mode(DeadlineMode::Captured),
+            chains: Arc::new(Mutex::new(Chains {
+                steps: HashMap::new(),
+                next_id: 0,
+                stale: 0,
+            })),
+        }
+    }
+}
+
+impl<T> Clone for EscalationQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+            chains: Arc::clone(&self.chains),
+        }
+    }
+}
+
+impl<T: Send + Sync> EscalationQueue<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Chains started and neither acknowledged nor run out of steps.
+    pub fn len(&self) -> usize {
+        self.chains.lock().steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Starts a chain following `policy` and returns its id. A policy
+    /// without steps, or a closed queue, schedules nothing.
+    pub fn start(&self, policy: &EscalationPolicy<T>) -> u64 {
+        let mut chains = self.chains.lock();
+        let chain = chains.next_id;
+        chains.next_id += 1;
+        if let Some((after, item)) = policy.steps.first() {
+            let deadline = instant_after(self.queue.clock.now(), *after);
+            let step = Step {
+                chain,
+                index: 0,
+                deadline,
+                item: Arc::clone(item),
+            };
+            if self.queue.put_at(deadline, Arc::new(step)) {
+                chains.steps.insert(chain, policy.steps.clone().into());
+            }
+        }
+        chain
+    }
+
+    /// Stops escalating `chain`. Returns `false` if it was already
+    /// acknowledged or ran out of steps.
+    pub fn ack(&self, chain: u64) -> bool {
+        let mut chains = self.chains.lock();
+        if chains.steps.remove(&chain).is_none() {
+            return false;
+        }
+        chains.stale += 1;
+        if chains.stale > chains.steps.len() {
+            self.sweep(&mut chains);
+        }
+        true
+    }
+
+    fn sweep(&self, chains: &mut Chains<T>) {
+        let steps = &chains.steps;
+        self.queue
+            .remove_where(|step| !steps.contains_key(&step.chain));
+        chains.stale = 0;
+    }
+
+    /// Blocks until a step is due, schedules the next step of its chain and
+    /// returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the queue is closed and empty.
+    pub fn take(&self) -> Escalation<T> {
+        self.take_or_closed()
+            .expect("take on a closed and empty EscalationQueue")
+    }
+
+    /// Like [`take`](Self::take), but returns `None` once the queue is
+    /// closed and empty.
+    pub fn take_or_closed(&self) -> Option<Escalation<T>> {
+        loop {
+            let step = self.queue.take_or_closed()?;
+            let mut chains = self.chains.lock();
+            let steps = match chains.steps.get(&step.chain) {
+                Some(steps) => Arc::clone(steps),
+                None => {
+                    chains.stale = chains.stale.saturating_sub(1);
+                    continue;
+                }
+            };
+            let scheduled = match steps.get(step.index + 1) {
+                Some((after, item)) => {
+                    let deadline = instant_after(self.queue.clock.now(), *after);
+                    let next = Step {
+                        chain: step.chain,
+                        index: step.index + 1,
+                        deadline,
+                        item: Arc::clone(item),
+                    };
+                    self.queue.put_at(deadline, Arc::new(next))
+                }
+                None => false,
+            };
+            if !scheduled {
+                chains.steps.remove(&step.chain);
+                // Don't leave a consumer of a closed queue waiting for steps
+                // that are all stale.
+                if chains.steps.is_empty() && chains.stale > 0 {
+                    self.sweep(&mut chains);
+                }
+            }
+            return Some(Escalation {
+                chain: step.chain,
+                step: step.index,
+                item: Arc::clone(&step.item),
+            });
+        }
+    }
+
+    /// Stops accepting new chains and steps, see [`DelayQueue::close`].
+    pub fn close(&self) {
+        self.queue.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_escalation() {
+        let queue = EscalationQueue::new();
+        let policy = EscalationPolicy::new()
+            .step(Duration::ZERO, "primary")
+            .step(Duration::from_millis(10), "secondary")
+            .step(Duration::MAX, "manager");
+        let unacked = queue.start(&policy);
+        let acked = queue.start(&policy);
+        assert_eq!(2, queue.len());
+
+        let first = queue.take();
+        assert_eq!(
+            (unacked, 0, "primary"),
+            (first.chain, first.step, *first.item)
+        );
+        let first = queue.take();
+        assert_eq!((acked, 0), (first.chain, first.step));
+        assert!(queue.ack(acked));
+        assert!(!queue.ack(acked));
+
+        let second = queue.take();
+        assert_eq!(
+            (unacked, 1, "secondary"),
+            (second.chain, second.step, *second.item)
+        );
+        assert_eq!(1, queue.len());
+        assert!(queue.ack(unacked));
+        queue.close();
+        assert!(queue.take_or_closed().is_none());
+    }
+
+    #[test]
+    fn test_ack_sweeps_stale_steps() {
+        let queue = EscalationQueue::new();
+        let policy = EscalationPolicy::new().step(Duration::from_secs(3600), "page");
+        let chains = (0..3).map(|_| queue.start(&policy)).collect::<Vec<_>>();
+        assert!(queue.ack(chains[0]));
+        assert_eq!((2, 3), (queue.len(), queue.queue.len()));
+        assert!(queue.ack(chains[1]));
+        assert_eq!((1, 1), (queue.len(), queue.queue.len()));
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    any::Any,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::{logging, worker::Periodic, DelayQueue, Delayed, ExecutorLayer, Handler, Headers};
+
+type FailureHook<T> = Arc<dyn Fn(Failure<T>) + Send + Sync>;
+
+/// Why a handler invocation failed.
+#[non_exhaustive]
+pub enum FailureReason {
+    /// The handler panicked with this payload.
+    Panicked(Box<dyn Any + Send>),
+    /// The handler has been running for longer than the task timeout. It is
+    /// reported once and keeps running, since threads cannot be aborted.
+    TimedOut(Duration),
+}
+
+impl fmt::Debug for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureReason::Panicked(_) => f
+                .debug_tuple("Panicked")
+                .field(&self.message().unwrap_or("<non-string payload>"))
+                .finish(),
+            FailureReason::TimedOut(elapsed) => f.debug_tuple("TimedOut").field(elapsed).finish(),
+        }
+    }
+}
+
+impl FailureReason {
+    /// The panic message, if the payload is a string.
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            FailureReason::Panicked(payload) => payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str)),
+            FailureReason::TimedOut(_) => None,
+        }
+    }
+}
+
+/// An element whose handler failed, reported to
+/// [`ExecutorBuilder::on_failure`].
+#[derive(Debug)]
+pub struct Failure<T> {
+    pub item: Arc<T>,
+    pub reason: FailureReason,
+}
+
+/// Configures an [`Executor`], see [`Executor::builder`].
+pub struct ExecutorBuilder<T: Delayed> {
+    queue: DelayQueue<T>,
+    workers: usize,
+    on_failure: Option<FailureHook<T>>,
+    layers: Vec<Box<dyn ExecutorLayer<T>>>,
+    task_timeout: Option<Duration>,
+}
+
+/// The invocation a worker is currently running.
+struct Running<T> {
+    item: Arc<T>,
+    started: Instant,
+    flagged: bool,
+}
+
+/// State shared by the workers, the watchdog and the [`Executor`].
+struct Shared<T> {
+    running: Vec<Mutex<Option<Running<T>>>>,
+    stopping: AtomicBool,
+    completed: AtomicUsize,
+    live: Mutex<usize>,
+    exited: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn check_timeouts(
+        &self,
+        queue: Option<&str>,
+        labels: &Headers,
+        timeout: Duration,
+        on_failure: &Option<FailureHook<T>>,
+    ) {
+        for slot in &self.running {
+            let mut slot = slot.lock();
+            let running = match slot.as_mut() {
+                Some(running) if !running.flagged => running,
+                _ => continue,
+            };
+            let elapsed = running.started.elapsed();
+            if elapsed < timeout {
+                continue;
+            }
+            running.flagged = true;
+            let item = Arc::clone(&running.item);
+            drop(slot);
+            let reason = FailureReason::TimedOut(elapsed);
+            logging::dead_letter(queue, labels, &reason);
+            if let Some(on_failure) = on_failure {
+                on_failure(Failure { item, reason });
+            }
+        }
+    }
+}
+
+impl<T> ExecutorBuilder<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    /// Number of worker threads, one by default.
+    pub fn workers(mut self, workers: usize) -> Self {
+        assert!(workers > 0, "an executor needs at least one worker");
+        self.workers = workers;
+        self
+    }
+
+    /// Called on the worker thread for every failed handler invocation.
+    ///
+    /// Without a hook failures are only visible through the panic hook.
+    pub fn on_failure<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Failure<T>) + Send + Sync + 'static,
+    {
+        self.on_failure = Some(Arc::new(f));
+        self
+    }
+
+    /// Wraps the handler with `layer`. The first layer added is the
+    /// outermost one.
+    pub fn layer<L: ExecutorLayer<T>>(mut self, layer: L) -> Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+
+    /// Reports handler invocations running longer than `timeout` as
+    /// [`FailureReason::TimedOut`], so a stuck element does not hold a worker
+    /// unnoticed.
+    pub fn with_task_timeout(mut self, timeout: Duration) -> Self {
+        self.task_timeout = Some(timeout);
+        self
+    }
+
+    /// Starts the workers, each running `handler` on every expired element.
+    pub fn spawn<F>(self, handler: F) -> Executor<T>
+    where
+        F: Fn(&T) + Send + Sync + 'static,
+    {
+        let handler: Handler<T> = Arc::new(handler);
+        let handler = self
+            .layers
+            .iter()
+            .rev()
+            .fold(handler, |inner, layer| layer.layer(inner));
+        let shared = Arc::new(Shared {
+            running: (0..self.workers).map(|_| Mutex::new(None)).collect(),
+            stopping: AtomicBool::new(false),
+            completed: AtomicUsize::new(0),
+            live: Mutex::new(self.workers),
+            exited: Condvar::new(),
+        });
+        let watchdog = self.task_timeout.map(|timeout| {
+            let shared = Arc::clone(&shared);
+            let queue = self.queue.clone();
+            let on_failure = self.on_failure.clone();
+            Periodic::spawn(timeout / 4, move || {
+                shared.check_timeouts(queue.name(), queue.labels(), timeout, &on_failure)
+            })
+        });
+        let workers = (0..self.workers)
+            .map(|index| {
+                let queue = self.queue.clone();
+                let handler = Arc::clone(&handler);
+                let on_failure = self.on_failure.clone();
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || {
+                    let slot = &shared.running[index];
+                    while let Some(delivery) = queue.take_unless(&shared.stopping) {
+                        #[cfg(feature = "otel")]
+                        let _context = delivery.trace_context().attach();
+                        let item = delivery.item;
+                        *slot.lock() = Some(Running {
+                            item: Arc::clone(&item),
+                            started: Instant::now(),
+                            flagged: false,
+                        });
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| handler(&item)));
+                        *slot.lock() = None;
+                        if shared.stopping.load(Ordering::SeqCst) {
+                            shared.completed.fetch_add(1, Ordering::SeqCst);
+                        }
+                        if let Err(payload) = result {
+                            let reason = FailureReason::Panicked(payload);
+                            logging::dead_letter(queue.name(), queue.labels(), &reason);
+                            if let Some(on_failure) = &on_failure {
+                                on_failure(Failure { item, reason });
+                            }
+                        }
+                    }
+                    *shared.live.lock() -= 1;
+                    shared.exited.notify_all();
+                })
+            })
+            .collect();
+        Executor {
+            queue: self.queue,
+            shared,
+            workers,
+            _watchdog: watchdog,
+        }
+    }
+}
+
+/// What [`Executor::shutdown`] left behind.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ShutdownReport<T> {
+    /// Handler invocations in flight at shutdown that finished in time.
+    pub completed: usize,
+    /// Elements whose handler was still running at the deadline. Their
+    /// workers are detached and finish in the background.
+    pub abandoned: Vec<Arc<T>>,
+    /// Elements left in the queue for whoever takes from it next.
+    pub requeued: usize,
+}
+
+/// Worker threads running a handler on every element as it expires.
+///
+/// A panicking handler is caught and reported; the worker keeps going. The
+/// workers exit once the queue is closed and empty; dropping the executor
+/// closes the queue and joins them.
+pub struct Executor<T: Delayed> {
+    queue: DelayQueue<T>,
+    shared: Arc<Shared<T>>,
+    workers: Vec<JoinHandle<()>>,
+    _watchdog: Option<Periodic>,
+}
+
+impl<T> Executor<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    pub fn builder(queue: DelayQueue<T>) -> ExecutorBuilder<T> {
+        ExecutorBuilder {
+            queue,
+            workers: 1,
+            on_failure: None,
+            layers: Vec::new(),
+            task_timeout: None,
+        }
+    }
+
+    pub fn queue(&self) -> &DelayQueue<T> {
+        &self.queue
+    }
+
+    /// Closes the queue and waits until every remaining element has been
+    /// handled.
+    pub fn join(self) {}
+
+    /// Stops taking new elements and waits for in-flight handlers until
+    /// `deadline`. Unlike [`join`](Self::join) the queue stays open and
+    /// pending elements stay in it.
+    pub fn shutdown(mut self, deadline: Instant) -> ShutdownReport<T> {
+        self.shared.stopping.store(true, Ordering::SeqCst);
+        self.queue.wake_all();
+        let mut live = self.shared.live.lock();
+        while *live > 0 {
+            if self
+                .shared
+                .exited
+                .wait_until(&mut live, deadline)
+                .timed_out()
+            {
+                break;
+            }
+        }
+        let finished = *live == 0;
+        drop(live);
+
+        let abandoned = self
+            .shared
+            .running
+            .iter()
+            .filter_map(|slot| {
+                slot.lock()
+                    .as_ref()
+                    .map(|running| Arc::clone(&running.item))
+            })
+            .collect();
+        let workers = std::mem::take(&mut self.workers);
+        if finished {
+            for worker in workers {
+                let _ = worker.join();
+            }
+        }
+        ShutdownReport {
+            completed: self.shared.completed.load(Ordering::SeqCst),
+            abandoned,
+            requeued: self.queue.len(),
+        }
+    }
+}
+
+impl<T: Delayed> Drop for Executor<T> {
+    fn drop(&mut self) {
+        if self.workers.is_empty() {
+            return;
+        }
+        self.queue.close();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::Fixed;
+
+    #[test]
+    fn test_panic_is_reported() {
+        let mut queue = DelayQueue::default();
+        for i in 0..10 {
+            queue.put(Fixed(-i));
+        }
+        let handled = Arc::new(AtomicUsize::new(0));
+        let failed = Arc::new(Mutex::new(Vec::new()));
+        let executor = {
+            let handled = Arc::clone(&handled);
+            let failed = Arc::clone(&failed);
+            Executor::builder(queue)
+                .workers(2)
+                .on_failure(move |failure: Failure<Fixed>| {
+                    let message = failure.reason.message().unwrap().to_string();
+                    failed.lock().push((-failure.item.0, message));
+                })
+                .spawn(move |item: &Fixed| {
+                    if item.0 % 3 == 0 {
+                        panic!("bad item {}", -item.0);
+                    }
+                    handled.fetch_add(1, Ordering::SeqCst);
+                })
+        };
+        executor.join();
+
+        let mut failed = failed.lock().clone();
+        failed.sort();
+        assert_eq!(6, handled.load(Ordering::SeqCst));
+        assert_eq!(
+            vec![
+                (0, "bad item 0".to_string()),
+                (3, "bad item 3".to_string()),
+                (6, "bad item 6".to_string()),
+                (9, "bad item 9".to_string()),
+            ],
+            failed
+        );
+    }
+
+    #[test]
+    fn test_task_timeout() {
+        let queue = DelayQueue::default();
+        let timed_out = Arc::new(Mutex::new(Vec::new()));
+        let executor = {
+            let timed_out = Arc::clone(&timed_out);
+            Executor::builder(queue.clone())
+                .with_task_timeout(Duration::from_millis(20))
+                .on_failure(move |failure: Failure<Fixed>| {
+                    if let FailureReason::TimedOut(elapsed) = failure.reason {
+                        assert!(elapsed >= Duration::from_millis(20));
+                        timed_out.lock().push(-failure.item.0);
+                    }
+                })
+                .spawn(|item: &Fixed| {
+                    if item.0 == -1 {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                })
+        };
+        queue.put_now(Fixed(0));
+        queue.put_now(Fixed(-1));
+        queue.put_now(Fixed(-2));
+        executor.join();
+        assert_eq!(vec![1], *timed_out.lock());
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let queue = DelayQueue::default();
+        let executor =
+            Executor::builder(queue.clone())
+                .workers(2)
+                .spawn(|item: &Fixed| match item.0 {
+                    0 => thread::sleep(Duration::from_millis(20)),
+                    -1 => thread::sleep(Duration::from_secs(1)),
+                    _ => {}
+                });
+        queue.put_now(Fixed(0));
+        queue.put_now(Fixed(-1));
+        queue.try_put(Fixed(3_600_000_000_000)).unwrap();
+        while queue.len() > 1 {
+            thread::yield_now();
+        }
+
+        let report = executor.shutdown(Instant::now() + Duration::from_millis(100));
+        assert_eq!(1, report.completed);
+        assert_eq!(
+            vec![1],
+            report
+                .abandoned
+                .iter()
+                .map(|item| -item.0)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, report.requeued);
+        assert!(!queue.is_closed());
+    }
+}
@@ -0,0 +1,153 @@
+//! Faults injected into a queue when the `failpoints` feature is enabled, to
+//! chaos-test retry and recovery handling around it; no-ops otherwise.
+
+use std::{io, time::Duration};
+
+#[cfg(feature = "failpoints")]
+use crate::DelayQueue;
+use crate::{DelayQueueInner, Delayed};
+
+/// Faults to inject into a queue, see [`DelayQueue::set_failpoints`]. All
+/// are off by default.
+#[cfg(feature = "failpoints")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Failpoints {
+    drop_notification: bool,
+    delay_delivery: Option<Duration>,
+    fail_persistence_after: Option<usize>,
+}
+
+#[cfg(feature = "failpoints")]
+impl Failpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts no longer wake a blocked taker, as if the notification were
+    /// lost; takers only notice new elements when they wake for another
+    /// reason.
+    pub fn drop_notification(mut self, enabled: bool) -> Self {
+        self.drop_notification = enabled;
+        self
+    }
+
+    /// Takers hold on to every delivery for `delay` before returning it.
+    pub fn delay_delivery(mut self, delay: Option<Duration>) -> Self {
+        self.delay_delivery = delay;
+        self
+    }
+
+    /// Snapshot writes fail with an I/O error once `written` elements were
+    /// written, leaving a partial snapshot behind.
+    pub fn fail_persistence_after(mut self, written: Option<usize>) -> Self {
+        self.fail_persistence_after = written;
+        self
+    }
+}
+
+#[cfg(feature = "failpoints")]
+impl<T: Delayed> DelayQueue<T> {
+    /// Injects `failpoints` into this queue, replacing the previous ones.
+    pub fn set_failpoints(&self, failpoints: Failpoints) {
+        self.queue.lock().failpoints = failpoints;
+    }
+}
+
+impl<T: Delayed> DelayQueueInner<T> {
+    /// Whether a put should skip waking a taker.
+    pub(crate) fn notification_dropped(&self) -> bool {
+        #[cfg(feature = "failpoints")]
+        return self.failpoints.drop_notification;
+        #[cfg(not(feature = "failpoints"))]
+        false
+    }
+
+    /// How long a taker holds on to a delivery.
+    pub(crate) fn delivery_delay(&self) -> Option<Duration> {
+        #[cfg(feature = "failpoints")]
+        return self.failpoints.delay_delivery;
+        #[cfg(not(feature = "failpoints"))]
+        None
+    }
+
+    /// How many elements a snapshot writes before failing.
+    pub(crate) fn persistence_fails_after(&self) -> Option<usize> {
+        #[cfg(feature = "failpoints")]
+        return self.failpoints.fail_persistence_after;
+        #[cfg(not(feature = "failpoints"))]
+        None
+    }
+}
+
+/// The error injected persistence writes fail with.
+pub(crate) fn persistence_failure() -> io::Error {
+    io::Error::other("failpoint: persistence write failed")
+}
+
+#[cfg(all(test, feature = "failpoints"))]
+mod test {
+    use std::{io::Write, thread, time::Instant};
+
+    use super::*;
+    use crate::testing::Fixed;
+
+    #[test]
+    fn test_delay_delivery() {
+        let queue = DelayQueue::default();
+        let delay = Duration::from_millis(20);
+        queue.set_failpoints(Failpoints::new().delay_delivery(Some(delay)));
+        queue.try_put(Fixed(-1)).unwrap();
+        let started = Instant::now();
+        queue.take_or_closed().unwrap();
+        assert!(started.elapsed() >= delay);
+
+        let takes: [fn(&DelayQueue<Fixed>) -> usize; 2] = [
+            |queue| queue.try_take().into_iter().count(),
+            |queue| queue.run_pending(drop),
+        ];
+        for take in takes {
+            queue.try_put(Fixed(-1)).unwrap();
+            let started = Instant::now();
+            assert_eq!(1, take(&queue));
+            assert!(started.elapsed() >= delay);
+        }
+    }
+
+    #[test]
+    fn test_drop_notification() {
+        let puts: [fn(&DelayQueue<Fixed>); 3] = [
+            |queue| queue.try_put(Fixed(-1)).unwrap(),
+            |queue| queue.commit(queue.prepare(Fixed(-1)).unwrap()),
+            |queue| queue.reserve_slots(1).put(Fixed(-1)).unwrap(),
+        ];
+        for put in puts {
+            let queue = DelayQueue::default();
+            queue.set_failpoints(Failpoints::new().drop_notification(true));
+            let cutoff = Instant::now() + Duration::from_millis(50);
+            let taker = {
+                let queue = queue.clone();
+                thread::spawn(move || queue.take_until(cutoff))
+            };
+            thread::sleep(Duration::from_millis(10));
+            put(&queue);
+            // Only found once the taker wakes up at its cutoff.
+            assert_eq!(Fixed(-1), *taker.join().unwrap().unwrap());
+            assert!(Instant::now() >= cutoff);
+        }
+    }
+
+    #[test]
+    fn test_fail_persistence() {
+        let queue = DelayQueue::default();
+        for delay in 0..3 {
+            queue.try_put(Fixed(delay)).unwrap();
+        }
+        queue.set_failpoints(Failpoints::new().fail_persistence_after(Some(2)));
+        let mut dump = Vec::new();
+        assert!(queue
+            .snapshot_streaming(&mut dump, |w, item| writeln!(w, "{}", item.0))
+            .is_err());
+        assert_eq!(b"0\n1\n", &dump[..]);
+    }
+}
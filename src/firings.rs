@@ -0,0 +1,156 @@
+use std::{
+    collections::{BinaryHeap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
+
+use crate::{logging, DelayQueue, Delayed, PutError};
+
+/// The firings of one element scheduled by
+/// [`DelayQueue::put_at_times`].
+pub struct Firings<T: Delayed> {
+    queue: DelayQueue<T>,
+    seqs: HashSet<u64>,
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    /// Schedules `item` once at each of `times`, sharing one allocation,
+    /// e.g. for a reminder ladder at T-24h, T-1h and T-5m. Either every
+    /// firing is inserted or none is: fails without blocking if they do not
+    /// all fit or the queue is closed.
+    pub fn put_at_times(&self, item: T, times: &[Instant]) -> Result<Firings<T>, PutError<T>> {
+        let now = self.clock.now();
+        let mut guard = self.queue.lock();
+        if guard.closed {
+            return Err(PutError::Closed(item));
+        }
+        if times
+            .iter()
+            .any(|deadline| guard.beyond_horizon(now, *deadline))
+        {
+            return Err(PutError::DeadlineTooFar(item));
+        }
+        if !guard.has_room(times.len()) {
+            let capacity = guard.capacity;
+            drop(guard);
+            logging::overflow(self.name(), self.labels(), capacity);
+            return Err(PutError::Full(item));
+        }
+        let item = Arc::new(item);
+        let mut wake = false;
+        let mut seqs = HashSet::with_capacity(times.len());
+        for deadline in times {
+            let entry = guard.pinned_entry(*deadline, Arc::clone(&item));
+            // `push_entry_wakes` assigns the next sequence number.
+            seqs.insert(guard.next_seq);
+            wake |= guard.push_entry_wakes(entry);
+        }
+        if wake {
+            self.available.notify_one();
+        }
+        Ok(Firings {
+            queue: self.clone(),
+            seqs,
+        })
+    }
+}
+
+impl<T> Firings<T>
+where
+    T: Delayed + Send + Sync,
+{
+    /// Removes the firings that are still pending, including those buffered
+    /// by a frozen queue, and returns how many were.
+    pub fn cancel(self) -> usize {
+        let removed = {
+            let mut guard = self.queue.queue.lock();
+            let mut buffered = 0;
+            if let Some(frozen) = guard.frozen.as_mut() {
+                let before = frozen.buffered.len();
+                frozen
+                    .buffered
+                    .retain(|entry| !self.seqs.contains(&entry.seq));
+                buffered = before - frozen.buffered.len();
+            }
+            let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut guard.queue)
+                .into_vec()
+                .into_iter()
+                .partition(|entry| self.seqs.contains(&entry.0.seq));
+            guard.queue = BinaryHeap::from(kept);
+            if !removed.is_empty() {
+                guard.reindex();
+                guard.touch();
+            }
+            removed.len() + buffered
+        };
+        if removed > 0 {
+            self.queue.not_full.notify_all();
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{testing::Fixed, DeadlineMode, FreezePolicy};
+
+    #[test]
+    fn test_put_at_times() {
+        let queue = DelayQueue::builder()
+            .deadline_mode(DeadlineMode::Captured)
+            .capacity(3)
+            .build();
+        let now = Instant::now();
+        let hour = Duration::from_secs(3600);
+        let times = [now, now + hour, now + hour * 24];
+        let firings = queue.put_at_times(Fixed(0), &times).unwrap();
+        assert_eq!(3, queue.len());
+        assert_eq!(
+            Err(PutError::Full(Fixed(1))),
+            queue.put_at_times(Fixed(1), &times[..1]).map(|_| ())
+        );
+
+        let first = queue.take_or_closed().unwrap();
+        assert_eq!(Fixed(0), *first);
+        assert_eq!(2, firings.cancel());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_put_at_times_dynamic() {
+        let queue = DelayQueue::default();
+        let now = Instant::now();
+        let hour = Duration::from_secs(3600);
+        let firings = queue
+            .put_at_times(Fixed(3_600_000_000_000), &[now, now + hour * 2])
+            .unwrap();
+        assert_eq!(Fixed(3_600_000_000_000), *queue.try_take().unwrap());
+        assert!(queue.try_take().is_none());
+        assert_eq!(1, firings.cancel());
+    }
+
+    #[test]
+    fn test_put_at_times_frozen() {
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let now = Instant::now();
+        let times = [now, now + Duration::from_secs(3600)];
+        let rejecting = queue.freeze(FreezePolicy::Reject);
+        assert_eq!(
+            Err(PutError::Full(Fixed(0))),
+            queue.put_at_times(Fixed(0), &times).map(|_| ())
+        );
+        drop(rejecting);
+
+        let buffering = queue.freeze(FreezePolicy::Buffer);
+        let firings = queue.put_at_times(Fixed(1), &times).unwrap();
+        assert_eq!(2, firings.cancel());
+        drop(buffering);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Instant;
+
+use crate::{DelayQueue, Delayed, Delivery, Entry};
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    /// Moves every expired element into `dst`, due at the deadline
+    /// `reschedule` computes for it, and returns how many were moved. Chains
+    /// of queues forwarding into each other make multi-stage pipelines, e.g.
+    /// retry tiers of one minute, ten minutes and an hour.
+    ///
+    /// `dst` should use [`DeadlineMode::Captured`](crate::DeadlineMode::Captured),
+    /// otherwise it recomputes deadlines from `delayed`. Elements keep their
+    /// headers and are accepted even if `dst` is full. Once `dst` is closed
+    /// forwarding stops and the element at hand goes back into this queue.
+    /// Like [`run_pending`](Self::run_pending), elements expiring while
+    /// forwarding are left for the next call.
+    pub fn forward<F>(&self, dst: &DelayQueue<T>, mut reschedule: F) -> usize
+    where
+        F: FnMut(&Delivery<T>) -> Instant,
+    {
+        let started = self.clock.now();
+        let mut forwarded = 0;
+        while let Some(delivery) = self.take_expired() {
+            let late = delivery.deadline > started;
+            let deadline = reschedule(&delivery);
+            if let Err(delivery) = dst.insert_delivery(deadline, delivery) {
+                let deadline = delivery.deadline;
+                // Dropped if this queue was closed in the meantime too.
+                let _ = self.insert_delivery(deadline, delivery);
+                break;
+            }
+            forwarded += 1;
+            if late {
+                break;
+            }
+        }
+        forwarded
+    }
+
+    /// Inserts the element of `delivery` with its headers due at
+    /// `deadline`, ignoring the capacity; gives it back on a closed queue.
+    fn insert_delivery(&self, deadline: Instant, delivery: Delivery<T>) -> Result<(), Delivery<T>> {
+        let mut guard = self.queue.lock();
+        if guard.closed {
+            return Err(delivery);
+        }
+        let entry = Entry {
+            headers: delivery.headers,
+            ..guard.entry(deadline, false, delivery.item)
+        };
+        if guard.push_entry_wakes(entry) {
+            self.available.notify_one();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{testing::Fixed, DeadlineMode, Headers};
+
+    #[test]
+    fn test_forward() {
+        let first = DelayQueue::default();
+        let second = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        first.try_put(Fixed(-2)).unwrap();
+        let mut headers = Headers::new();
+        headers.insert("attempt", "1");
+        first.try_put_with_headers(Fixed(-1), headers).unwrap();
+        first.try_put(Fixed(3_600_000_000_000)).unwrap();
+
+        let retry_in = Duration::from_secs(600);
+        assert_eq!(
+            2,
+            first.forward(&second, |delivery| delivery.delivered_at + retry_in)
+        );
+        assert_eq!(1, first.len());
+        assert_eq!(2, second.len());
+        assert!(second.duration_until_next().unwrap() > Duration::from_secs(590));
+
+        second.close();
+        first.try_put(Fixed(-3)).unwrap();
+        assert_eq!(0, first.forward(&second, |delivery| delivery.deadline));
+        assert_eq!(2, first.len());
+        assert_eq!(
+            Some("1"),
+            second.close_and_drain_entries()[1]
+                .headers
+                .as_ref()
+                .and_then(|headers| headers.get("attempt"))
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{DelayQueue, Delayed, Entry};
+
+/// What happens to puts while a queue is frozen, see
+/// [`DelayQueue::freeze`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FreezePolicy {
+    /// Puts succeed but only take effect once the queue is unfrozen.
+    Buffer,
+    /// Puts are refused as if the queue were full: `try_put` fails with
+    /// [`PutError::Full`](crate::PutError::Full) and `put` blocks until the
+    /// queue is unfrozen. Inserts that ignore the capacity are buffered.
+    Reject,
+}
+
+pub(crate) struct Frozen<T> {
+    pub(crate) policy: FreezePolicy,
+    /// Live `FrozenGuard`s; the queue thaws when the last one is dropped.
+    guards: usize,
+    pub(crate) buffered: Vec<Entry<T>>,
+}
+
+impl<T> Clone for Frozen<T> {
+    fn clone(&self) -> Self {
+        Self {
+            policy: self.policy,
+            guards: self.guards,
+            buffered: self.buffered.clone(),
+        }
+    }
+}
+
+/// Keeps a queue frozen until dropped, see [`DelayQueue::freeze`].
+#[must_use = "the queue thaws as soon as the guard is dropped"]
+pub struct FrozenGuard<T: Delayed> {
+    queue: DelayQueue<T>,
+}
+
+impl<T: Delayed> DelayQueue<T> {
+    /// Stops elements from being added until the returned guard is dropped,
+    /// so a backup sees a stable set without holding the lock throughout.
+    /// Deliveries and removals go on. Puts are handled according to
+    /// `policy`; buffered ones are inserted in order when the queue thaws,
+    /// regardless of the capacity.
+    ///
+    /// Freezing a frozen queue keeps the first policy and thaws when every
+    /// guard is gone.
+    pub fn freeze(&self, policy: FreezePolicy) -> FrozenGuard<T> {
+        let mut guard = self.queue.lock();
+        match guard.frozen.as_mut() {
+            Some(frozen) => frozen.guards += 1,
+            None => {
+                guard.frozen = Some(Frozen {
+                    policy,
+                    guards: 1,
+                    buffered: Vec::new(),
+                })
+            }
+        }
+        FrozenGuard {
+            queue: self.clone(),
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.queue.lock().frozen.is_some()
+    }
+}
+
+impl<T: Delayed> Drop for FrozenGuard<T> {
+    fn drop(&mut self) {
+        let mut guard = self.queue.queue.lock();
+        let thawed = match guard.frozen.as_mut() {
+            Some(frozen) if frozen.guards > 1 => {
+                frozen.guards -= 1;
+                return;
+            }
+            _ => guard.frozen.take(),
+        };
+        for entry in thawed.into_iter().flat_map(|frozen| frozen.buffered) {
+            guard.insert(entry);
+        }
+        drop(guard);
+        self.queue.available.notify_all();
+        self.queue.not_full.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing::Fixed, PutError};
+
+    #[test]
+    fn test_freeze_buffer() {
+        let queue = DelayQueue::default();
+        queue.try_put(Fixed(-1)).unwrap();
+        let frozen = queue.freeze(FreezePolicy::Buffer);
+        let nested = queue.freeze(FreezePolicy::Reject);
+        queue.try_put(Fixed(-2)).unwrap();
+        assert_eq!(1, queue.snapshot().len());
+        assert_eq!(Fixed(-1), *queue.take_or_closed().unwrap());
+
+        drop(nested);
+        assert!(queue.is_frozen());
+        assert!(queue.is_empty());
+        drop(frozen);
+        assert!(!queue.is_frozen());
+        assert_eq!(Fixed(-2), *queue.take_or_closed().unwrap());
+    }
+
+    #[test]
+    fn test_freeze_reject() {
+        let queue = DelayQueue::default();
+        let frozen = queue.freeze(FreezePolicy::Reject);
+        assert_eq!(Err(PutError::Full(Fixed(-1))), queue.try_put(Fixed(-1)));
+
+        let thread = {
+            let mut queue = queue.clone();
+            std::thread::spawn(move || queue.put(Fixed(-2)))
+        };
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(queue.is_empty());
+        drop(frozen);
+        thread.join().unwrap();
+        assert_eq!(Fixed(-2), *queue.take_or_closed().unwrap());
+    }
+}
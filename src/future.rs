@@ -0,0 +1,284 @@
+use std::{
+    error::Error,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use parking_lot::Mutex;
+
+use crate::{delayed_until, DeadlineMode, DelayQueue, Delayed};
+
+/// Why a [`TimerFuture`] resolved without its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerError {
+    /// [`TimerFuture::cancel`] was called.
+    Cancelled,
+    /// The [`TimerService`] was closed or dropped before the deadline.
+    Closed,
+}
+
+impl fmt::Display for TimerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimerError::Cancelled => write!(f, "timer was cancelled"),
+            TimerError::Closed => write!(f, "timer service was closed"),
+        }
+    }
+}
+
+impl Error for TimerError {}
+
+/// Shared by a future and its entry in the core queue.
+struct Slot<T> {
+    /// Taken by whoever resolves the future.
+    value: Option<T>,
+    outcome: Option<Result<T, TimerError>>,
+    waker: Option<Waker>,
+}
+
+impl<T> Slot<T> {
+    /// Resolves the future with its value, or with `err`, unless it
+    /// already was. Returns whether it was resolved now.
+    fn resolve(&mut self, err: Option<TimerError>) -> bool {
+        let value = match self.value.take() {
+            Some(value) => value,
+            None => return false,
+        };
+        self.outcome = Some(match err {
+            None => Ok(value),
+            Some(err) => Err(err),
+        });
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+        true
+    }
+}
+
+/// A scheduled future, as stored in the core queue.
+struct Timed<T> {
+    deadline: Instant,
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+impl<T> Delayed for Timed<T> {
+    fn delayed(&self) -> i64 {
+        delayed_until(Instant::now(), self.deadline)
+    }
+}
+
+// Futures with equal deadlines resolve in scheduling order.
+unordered!(Timed<T>);
+
+/// Counts a cancelled future whose entry stays in the core queue, sweeping
+/// every such entry once they outnumber the pending futures.
+fn retire<T: Send>(queue: &DelayQueue<Timed<T>>, stale: &Mutex<usize>) {
+    let mut stale = stale.lock();
+    *stale += 1;
+    if *stale > queue.len().saturating_sub(*stale) {
+        let swept = queue.remove_where(|timed| timed.slot.lock().value.is_none());
+        *stale = (*stale).saturating_sub(swept.len());
+    }
+}
+
+/// A general-purpose timer for async code: each
+/// [`schedule_future`](Self::schedule_future) returns a future resolving with
+/// its value at the deadline, like `sleep_until` with a payload. One
+/// background thread drives every future, independent of any async runtime.
+///
+/// Cancelling a future leaves its entry in the core queue, where it is
+/// skipped when it comes due. Such entries are swept in one pass once they
+/// outnumber the pending futures, so cancelling costs amortized O(1).
+pub struct TimerService<T: Send + 'static> {
+    queue: DelayQueue<Timed<T>>,
+    /// Entries of cancelled futures still in the core queue.
+    stale: Arc<Mutex<usize>>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> Default for TimerService<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send + 'static> TimerService<T> {
+    pub fn new() -> Self {
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let stale = Arc::new(Mutex::new(0usize));
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let queue: DelayQueue<Timed<T>> = queue.clone();
+            let stale = Arc::clone(&stale);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while let Some(delivery) = queue.take_unless(&stop) {
+                    if !delivery.item.slot.lock().resolve(None) {
+                        let mut stale = stale.lock();
+                        *stale = (*stale).saturating_sub(1);
+                    }
+                }
+            })
+        };
+        Self {
+            queue,
+            stale,
+            stop,
+            worker: Some(worker),
+        }
+    }
+
+    /// Futures scheduled and not resolved yet.
+    pub fn len(&self) -> usize {
+        let stale = self.stale.lock();
+        self.queue.len().saturating_sub(*stale)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a future resolving with `value` at `at`, or with an error if
+    /// it is cancelled or the service is closed first. Deadlines in the past
+    /// resolve right away.
+    pub fn schedule_future(&self, at: Instant, value: T) -> TimerFuture<T> {
+        let slot = Arc::new(Mutex::new(Slot {
+            value: Some(value),
+            outcome: None,
+            waker: None,
+        }));
+        let timed = Timed {
+            deadline: at,
+            slot: Arc::clone(&slot),
+        };
+        if !self.queue.put_at(at, Arc::new(timed)) {
+            slot.lock().resolve(Some(TimerError::Closed));
+        }
+        TimerFuture {
+            slot,
+            queue: self.queue.clone(),
+            stale: Arc::clone(&self.stale),
+        }
+    }
+
+    /// Resolves every pending future with [`TimerError::Closed`] and stops
+    /// the background thread. Later futures resolve with it right away.
+    pub fn close(&mut self) {
+        for timed in self.queue.close_and_drain() {
+            timed.slot.lock().resolve(Some(TimerError::Closed));
+        }
+        *self.stale.lock() = 0;
+        self.stop.store(true, AtomicOrdering::SeqCst);
+        self.queue.wake_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<T: Send + 'static> Drop for TimerService<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Resolves with its value at the deadline, see
+/// [`TimerService::schedule_future`]. Dropping it cancels the timer.
+#[must_use = "futures do nothing unless polled"]
+pub struct TimerFuture<T: Send + 'static> {
+    slot: Arc<Mutex<Slot<T>>>,
+    queue: DelayQueue<Timed<T>>,
+    stale: Arc<Mutex<usize>>,
+}
+
+impl<T: Send + 'static> TimerFuture<T> {
+    /// Resolves the future with [`TimerError::Cancelled`] unless it already
+    /// resolved, and removes the timer.
+    pub fn cancel(&self) {
+        if self.slot.lock().resolve(Some(TimerError::Cancelled)) {
+            retire(&self.queue, &self.stale);
+        }
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.slot.lock().value.is_none()
+    }
+}
+
+impl<T: Send + 'static> Future for TimerFuture<T> {
+    type Output = Result<T, TimerError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock();
+        match slot.outcome.take() {
+            Some(outcome) => Poll::Ready(outcome),
+            None if slot.value.is_none() => panic!("TimerFuture polled after completion"),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T: Send + 'static> Drop for TimerFuture<T> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_schedule_future() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut timers = TimerService::new();
+        let now = Instant::now();
+        let later = timers.schedule_future(now + Duration::from_millis(20), "later");
+        let sooner = timers.schedule_future(now + Duration::from_millis(10), "sooner");
+        assert_eq!(Ok("sooner"), runtime.block_on(sooner));
+        assert!(Instant::now() >= now + Duration::from_millis(10));
+
+        let cancelled = timers.schedule_future(now + Duration::from_secs(3600), "never");
+        cancelled.cancel();
+        assert_eq!(Err(TimerError::Cancelled), runtime.block_on(cancelled));
+        assert_eq!(1, timers.len());
+
+        let pending = timers.schedule_future(now + Duration::from_secs(3600), "pending");
+        assert_eq!(Ok("later"), runtime.block_on(later));
+        timers.close();
+        assert_eq!(Err(TimerError::Closed), runtime.block_on(pending));
+        let late = timers.schedule_future(now, "late");
+        assert_eq!(Err(TimerError::Closed), runtime.block_on(late));
+    }
+
+    #[test]
+    fn test_cancel_sweeps_stale_entries() {
+        let timers = TimerService::new();
+        let later = Instant::now() + Duration::from_secs(3600);
+        let futures = (0..3)
+            .map(|value| timers.schedule_future(later, value))
+            .collect::<Vec<_>>();
+        futures[0].cancel();
+        assert_eq!((2, 3), (timers.len(), timers.queue.len()));
+        futures[1].cancel();
+        assert_eq!((1, 1), (timers.len(), timers.queue.len()));
+        drop(futures);
+        assert!(timers.is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+use std::{sync::Arc, time::Duration, time::Instant};
+
+use crate::{ConfigChange, DelayQueue, Delayed, Delivery, Headers, PutError};
+
+/// A handle that can only insert into a queue, see
+/// [`DelayQueue::producer`].
+pub struct ProducerHandle<T: Delayed> {
+    queue: DelayQueue<T>,
+}
+
+/// A handle that can only take from a queue, see [`DelayQueue::consumer`].
+pub struct ConsumerHandle<T: Delayed> {
+    queue: DelayQueue<T>,
+}
+
+/// A handle to the destructive operations of a queue, see
+/// [`DelayQueue::admin`].
+pub struct AdminHandle<T: Delayed> {
+    queue: DelayQueue<T>,
+    actor: Option<Arc<str>>,
+}
+
+impl<T: Delayed> Clone for ProducerHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<T: Delayed> Clone for ConsumerHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<T: Delayed> Clone for AdminHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+            actor: self.actor.clone(),
+        }
+    }
+}
+
+impl<T: Delayed> DelayQueue<T> {
+    /// Returns a handle limited to inserting, so a subsystem can be given
+    /// exactly the capability it needs.
+    pub fn producer(&self) -> ProducerHandle<T> {
+        ProducerHandle {
+            queue: self.clone(),
+        }
+    }
+
+    /// Returns a handle limited to taking.
+    pub fn consumer(&self) -> ConsumerHandle<T> {
+        ConsumerHandle {
+            queue: self.clone(),
+        }
+    }
+
+    /// Returns a handle limited to clearing, closing and shifting deadlines.
+    pub fn admin(&self) -> AdminHandle<T> {
+        AdminHandle {
+            queue: self.clone(),
+            actor: None,
+        }
+    }
+}
+
+impl<T: Delayed + Send + Sync> ProducerHandle<T> {
+    /// See [`DelayQueue::put`].
+    pub fn put(&mut self, t: T) {
+        self.queue.put(t);
+    }
+
+    /// See [`DelayQueue::put_shared`].
+    pub fn put_shared(&mut self, t: Arc<T>) {
+        self.queue.put_shared(t);
+    }
+
+    /// See [`DelayQueue::put_shared_at`].
+    pub fn put_shared_at(&mut self, deadline: Instant, t: Arc<T>) {
+        self.queue.put_shared_at(deadline, t);
+    }
+
+    /// See [`DelayQueue::try_put`].
+    pub fn try_put(&self, t: T) -> Result<(), PutError<T>> {
+        self.queue.try_put(t)
+    }
+
+    /// See [`DelayQueue::try_put_with_headers`].
+    pub fn try_put_with_headers(&self, t: T, headers: Headers) -> Result<(), PutError<T>> {
+        self.queue.try_put_with_headers(t, headers)
+    }
+
+    /// See [`DelayQueue::put_now`].
+    pub fn put_now(&self, t: T) {
+        self.queue.put_now(t);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.queue.is_closed()
+    }
+}
+
+impl<T: Delayed + Send + Sync> ConsumerHandle<T> {
+    /// See [`DelayQueue::take`].
+    pub fn take(&mut self) -> Arc<T> {
+        self.queue.take()
+    }
+
+    /// See [`DelayQueue::take_or_closed`].
+    pub fn take_or_closed(&self) -> Option<Arc<T>> {
+        self.queue.take_or_closed()
+    }
+
+    /// See [`DelayQueue::take_delivery`].
+    pub fn take_delivery(&self) -> Delivery<T> {
+        self.queue.take_delivery()
+    }
+
+    /// See [`DelayQueue::take_until`].
+    pub fn take_until(&self, cutoff: Instant) -> Option<Arc<T>> {
+        self.queue.take_until(cutoff)
+    }
+
+    /// See [`DelayQueue::take_delivery_until`].
+    pub fn take_delivery_until(&self, cutoff: Instant) -> Option<Delivery<T>> {
+        self.queue.take_delivery_until(cutoff)
+    }
+
+    /// See [`DelayQueue::take_timeout`].
+    pub fn take_timeout(&self, timeout: Duration) -> Option<Arc<T>> {
+        self.queue.take_timeout(timeout)
+    }
+
+    /// See [`DelayQueue::try_take`].
+    pub fn try_take(&self) -> Option<Arc<T>> {
+        self.queue.try_take()
+    }
+
+    /// See [`DelayQueue::run_pending`].
+    pub fn run_pending<F: FnMut(Arc<T>)>(&self, f: F) -> usize {
+        self.queue.run_pending(f)
+    }
+}
+
+impl<T: Delayed + Send + Sync> AdminHandle<T> {
+    /// Names who acts through this handle in the records of the
+    /// [audit sink](DelayQueue::set_audit_sink).
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into().into());
+        self
+    }
+
+    /// See [`DelayQueue::cancel_range`].
+    pub fn cancel_range(&self, from: Instant, to: Instant) -> Vec<Arc<T>> {
+        self.queue.cancel_range_as(from, to, self.actor.as_deref())
+    }
+
+    /// See [`DelayQueue::clear`].
+    pub fn clear(&self) -> usize {
+        self.queue.clear_as(self.actor.as_deref())
+    }
+
+    /// See [`DelayQueue::close`].
+    pub fn close(&self) {
+        self.queue.close();
+    }
+
+    /// See [`DelayQueue::close_and_drain`].
+    pub fn close_and_drain(&self) -> Vec<Arc<T>> {
+        self.queue.close_and_drain()
+    }
+
+    /// See [`DelayQueue::close_and_discard`].
+    pub fn close_and_discard(&self) -> usize {
+        self.queue.close_and_discard()
+    }
+
+    /// See [`DelayQueue::shift_deadlines_later`].
+    pub fn shift_deadlines_later(&self, by: Duration) {
+        self.queue.shift_deadlines(by, true, self.actor.as_deref());
+    }
+
+    /// See [`DelayQueue::shift_deadlines_earlier`].
+    pub fn shift_deadlines_earlier(&self, by: Duration) {
+        self.queue.shift_deadlines(by, false, self.actor.as_deref());
+    }
+
+    /// See [`DelayQueue::reconfigure`].
+    pub fn reconfigure(&self, change: ConfigChange) {
+        self.queue.reconfigure_as(change, self.actor.as_deref());
+    }
+
+    /// See [`DelayQueue::revalidate`].
+    pub fn revalidate(&self) {
+        self.queue.revalidate();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::Fixed;
+
+    #[test]
+    fn test_handles() {
+        let queue = DelayQueue::default();
+        let (producer, consumer, admin) = (queue.producer(), queue.consumer(), queue.admin());
+        producer.try_put(Fixed(-1)).unwrap();
+        producer.try_put(Fixed(3_600_000_000_000)).unwrap();
+        assert_eq!(Fixed(-1), *consumer.take_or_closed().unwrap());
+
+        assert_eq!(1, admin.clear());
+        admin.close();
+        assert!(producer.is_closed());
+        assert!(consumer.take_or_closed().is_none());
+    }
+}
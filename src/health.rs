@@ -0,0 +1,138 @@
+use std::time::{Duration, Instant};
+
+use crate::{DelayQueue, Delayed, Headers};
+
+/// How long an observed lateness keeps counting towards
+/// [`Health::max_lateness`].
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Maximum delivery lateness over the last one to two windows.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LatenessWindow {
+    started: Option<Instant>,
+    current: Duration,
+    previous: Duration,
+}
+
+impl LatenessWindow {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: None,
+            current: Duration::ZERO,
+            previous: Duration::ZERO,
+        }
+    }
+
+    fn rotate(&mut self, now: Instant) {
+        let elapsed = match self.started {
+            Some(started) => now.saturating_duration_since(started),
+            None => {
+                self.started = Some(now);
+                return;
+            }
+        };
+        if elapsed >= WINDOW * 2 {
+            self.previous = Duration::ZERO;
+            self.current = Duration::ZERO;
+            self.started = Some(now);
+        } else if elapsed >= WINDOW {
+            self.previous = self.current;
+            self.current = Duration::ZERO;
+            self.started = Some(now);
+        }
+    }
+
+    pub(crate) fn record(&mut self, now: Instant, lateness: Duration) {
+        self.rotate(now);
+        self.current = self.current.max(lateness);
+    }
+
+    fn max(&mut self, now: Instant) -> Duration {
+        self.rotate(now);
+        self.current.max(self.previous)
+    }
+}
+
+/// Whether the delivery loop of a queue is keeping up, see
+/// [`DelayQueue::health`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Health {
+    /// See [`DelayQueue::name`].
+    pub name: Option<String>,
+    /// See [`DelayQueue::labels`].
+    pub labels: Headers,
+    /// The largest delay between a deadline and its delivery over the last
+    /// one to two minutes.
+    pub max_lateness: Duration,
+    /// How long the current head has been expired without being taken.
+    pub head_overdue: Duration,
+    /// Whether a consumer is currently waiting for the head to expire.
+    pub has_leader: bool,
+    pub len: usize,
+    /// `None` for unbounded queues.
+    pub capacity: Option<usize>,
+    pub closed: bool,
+}
+
+impl Health {
+    /// Whether the queue is open, not full, and neither delivered nor left
+    /// an element more than `tolerance` past its deadline.
+    pub fn is_healthy(&self, tolerance: Duration) -> bool {
+        !self.closed
+            && self.max_lateness <= tolerance
+            && self.head_overdue <= tolerance
+            && self.capacity.is_none_or(|capacity| self.len < capacity)
+    }
+}
+
+impl<T: Delayed> DelayQueue<T> {
+    /// Reports whether consumers keep up with the queue, e.g. for a
+    /// readiness probe.
+    pub fn health(&self) -> Health {
+        let now = self.clock.now();
+        let mut guard = self.queue.lock();
+        let head_overdue = guard.peek().map_or(Duration::ZERO, |head| {
+            now.saturating_duration_since(head.deadline)
+        });
+        Health {
+            name: self.name().map(str::to_owned),
+            labels: self.labels().clone(),
+            max_lateness: guard.lateness.max(now),
+            head_overdue,
+            has_leader: guard.current_thread.is_some(),
+            len: guard.queue.len(),
+            capacity: guard.capacity,
+            closed: guard.closed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing::Fixed, DeadlineMode, SimClock};
+
+    #[test]
+    fn test_health() {
+        let clock = SimClock::new();
+        let queue = DelayQueue::with_clock(DeadlineMode::Captured, clock.clone());
+        let tolerance = Duration::from_secs(1);
+        assert!(queue.health().is_healthy(tolerance));
+
+        queue.try_put(Fixed(1_000_000_000)).unwrap();
+        clock.advance(Duration::from_secs(3));
+        let health = queue.health();
+        assert_eq!(Duration::from_secs(2), health.head_overdue);
+        assert!(!health.is_healthy(tolerance));
+
+        queue.take_or_closed().unwrap();
+        let health = queue.health();
+        assert_eq!(Duration::from_secs(2), health.max_lateness);
+        assert_eq!(Duration::ZERO, health.head_overdue);
+        assert!(!health.is_healthy(tolerance));
+
+        clock.advance(WINDOW * 2);
+        assert!(queue.health().is_healthy(tolerance));
+    }
+}
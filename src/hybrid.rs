@@ -0,0 +1,175 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{deadline_after, instant_after, worker::Periodic, DelayQueue, Delayed};
+
+/// Storage for elements that are due too far in the future to be kept in the
+/// in-memory heap, e.g. a database table.
+pub trait FarStore<T>: Send + 'static {
+    fn store(&mut self, item: T);
+
+    /// Removes and returns every stored element due within `horizon`.
+    fn load_due(&mut self, horizon: Duration) -> Vec<T>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// An in-memory [`FarStore`], ordered by the deadline captured at `store`.
+pub struct MemoryFarStore<T> {
+    items: BTreeMap<(Instant, u64), T>,
+    next_seq: u64,
+}
+
+impl<T> Default for MemoryFarStore<T> {
+    fn default() -> Self {
+        Self {
+            items: BTreeMap::new(),
+            next_seq: 0,
+        }
+    }
+}
+
+impl<T: Delayed + Send + 'static> FarStore<T> for MemoryFarStore<T> {
+    fn store(&mut self, item: T) {
+        let deadline = deadline_after(Instant::now(), item.delayed());
+        self.items.insert((deadline, self.next_seq), item);
+        self.next_seq += 1;
+    }
+
+    fn load_due(&mut self, horizon: Duration) -> Vec<T> {
+        let later = self
+            .items
+            .split_off(&(instant_after(Instant::now(), horizon), 0));
+        std::mem::replace(&mut self.items, later)
+            .into_values()
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+}
+
+/// Keeps only elements due within `horizon` in memory and pages later ones in
+/// from a [`FarStore`] every `interval`.
+///
+/// `interval` must be shorter than `horizon`, otherwise paged-in elements may
+/// be delivered late.
+pub struct HybridQueue<T: Delayed, S> {
+    queue: DelayQueue<T>,
+    far: Arc<Mutex<S>>,
+    horizon: Duration,
+    pager: Periodic,
+}
+
+impl<T, S> HybridQueue<T, S>
+where
+    T: Delayed + Send + Sync + 'static,
+    S: FarStore<T>,
+{
+    pub fn new(far: S, horizon: Duration, interval: Duration) -> Self {
+        let queue = DelayQueue::default();
+        let far = Arc::new(Mutex::new(far));
+        let pager = {
+            let mut queue = queue.clone();
+            let far = Arc::clone(&far);
+            Periodic::spawn(interval, move || {
+                let due = far.lock().load_due(horizon);
+                for item in due {
+                    queue.put(item);
+                }
+            })
+        };
+        Self {
+            queue,
+            far,
+            horizon,
+            pager,
+        }
+    }
+
+    pub fn put(&mut self, t: T) {
+        if t.delayed() > self.horizon.as_nanos() as i64 {
+            self.far.lock().store(t);
+        } else {
+            self.queue.put(t);
+        }
+    }
+
+    pub fn take(&mut self) -> Arc<T> {
+        self.queue.take()
+    }
+
+    /// Elements currently held in memory.
+    pub fn near_len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Elements currently held in the far store.
+    pub fn far_len(&self) -> usize {
+        self.far.lock().len()
+    }
+
+    /// Stops paging; elements left in the far store stay there.
+    pub fn stop(&mut self) {
+        self.pager.stop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{delayed_until, testing::Fixed};
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct At(Instant);
+
+    impl Delayed for At {
+        fn delayed(&self) -> i64 {
+            delayed_until(Instant::now(), self.0)
+        }
+    }
+
+    #[test]
+    fn test_far_store_paging() {
+        let mut store = MemoryFarStore::default();
+        store.store(Fixed(Duration::from_secs(3600).as_nanos() as i64));
+        store.store(Fixed(Duration::from_secs(60).as_nanos() as i64));
+        let due = store.load_due(Duration::from_secs(120));
+        assert_eq!(vec![Fixed(60_000_000_000)], due);
+        assert_eq!(1, store.len());
+        assert_eq!(1, store.load_due(Duration::MAX).len());
+    }
+
+    #[test]
+    fn test_paging_across_horizon() {
+        let mut queue = HybridQueue::new(
+            MemoryFarStore::default(),
+            Duration::from_millis(100),
+            Duration::from_millis(10),
+        );
+        let start = Instant::now();
+        let at = |millis| At(start + Duration::from_millis(millis));
+        for millis in &[300, 20, 200, 80] {
+            queue.put(at(*millis));
+        }
+        assert_eq!(2, queue.near_len());
+        assert_eq!(2, queue.far_len());
+
+        for millis in &[20, 80, 200, 300] {
+            assert_eq!(at(*millis), *queue.take());
+            assert!(Instant::now() >= at(*millis).0);
+        }
+        assert_eq!(0, queue.far_len());
+        queue.stop();
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{DelayQueue, Delayed, QueueEvent};
+
+/// How often a busy queue is checked for removals, which send no event.
+const BUSY_CHECK: Duration = Duration::from_millis(100);
+
+/// Background idle and busy notifications, see [`DelayQueue::on_idle`].
+pub struct IdleNotifier {
+    stopped: Arc<AtomicBool>,
+    wake: mpsc::SyncSender<QueueEvent>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl IdleNotifier {
+    pub fn stop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // A full buffer already wakes the thread.
+        let _ = self.wake.try_send(QueueEvent::Closed {
+            labels: Arc::default(),
+        });
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for IdleNotifier {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    /// Calls `on_idle` whenever the queue becomes idle, with nothing due
+    /// within `window`, and `on_busy` whenever it stops being idle, e.g. to
+    /// release and reacquire a wakelock. One of them is called right away
+    /// with the current state.
+    ///
+    /// The calls happen on a background thread. While idle it only wakes
+    /// for inserts and when the head comes within `window`; while busy it
+    /// also checks every 100ms, so removals are noticed late.
+    pub fn on_idle<F, G>(&self, window: Duration, on_idle: F, on_busy: G) -> IdleNotifier
+    where
+        F: Fn() + Send + 'static,
+        G: Fn() + Send + 'static,
+    {
+        let stopped = Arc::new(AtomicBool::new(false));
+        // One pending event is enough to wake the thread.
+        let (wake, events) = mpsc::sync_channel(1);
+        self.queue.lock().observers.push(wake.clone());
+        let worker = {
+            let queue = self.clone();
+            let stopped = Arc::clone(&stopped);
+            thread::spawn(move || {
+                let mut idle = None;
+                while !stopped.load(Ordering::SeqCst) {
+                    let now = queue.clock.now();
+                    let head = queue.queue.lock().peek().map(|entry| entry.deadline);
+                    let busy_at = head.map(|head| head.checked_sub(window).unwrap_or(now));
+                    let is_idle = busy_at.is_none_or(|busy_at| busy_at > now);
+                    if idle != Some(is_idle) {
+                        idle = Some(is_idle);
+                        if is_idle {
+                            on_idle();
+                        } else {
+                            on_busy();
+                        }
+                    }
+                    let result = match (is_idle, busy_at) {
+                        (true, None) => events.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                        (true, Some(busy_at)) => {
+                            events.recv_timeout(busy_at.saturating_duration_since(now))
+                        }
+                        (false, _) => events.recv_timeout(BUSY_CHECK),
+                    };
+                    match result {
+                        Ok(_) => while events.try_recv().is_ok() {},
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+            })
+        };
+        IdleNotifier {
+            stopped,
+            wake,
+            worker: Some(worker),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{testing::Fixed, DeadlineMode};
+
+    #[test]
+    fn test_on_idle() {
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let (sender, receiver) = mpsc::channel();
+        let busy = sender.clone();
+        let mut notifier = queue.on_idle(
+            Duration::from_millis(20),
+            move || sender.send(("idle", Instant::now())).unwrap(),
+            move || busy.send(("busy", Instant::now())).unwrap(),
+        );
+        let next = || receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!("idle", next().0);
+
+        let start = Instant::now();
+        queue.try_put(Fixed(60_000_000)).unwrap();
+        let (state, at) = next();
+        assert_eq!("busy", state);
+        assert!(at - start >= Duration::from_millis(40));
+
+        queue.take_or_closed().unwrap();
+        assert_eq!("idle", next().0);
+        notifier.stop();
+        assert!(receiver.try_recv().is_err());
+    }
+}
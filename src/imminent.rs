@@ -0,0 +1,154 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::RecvTimeoutError,
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{instant_after, DelayQueue, Delayed};
+
+/// How often the notifier thread checks whether it was stopped.
+const STOP_CHECK: Duration = Duration::from_millis(100);
+
+/// Background pre-expiry notifications, see [`DelayQueue::on_imminent`].
+pub struct ImminentNotifier {
+    stopped: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ImminentNotifier {
+    pub fn stop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for ImminentNotifier {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    /// Calls `f` with each element about `lead` before its deadline, e.g. to
+    /// warm caches right before the real work arrives.
+    ///
+    /// Best-effort: the call happens on a background thread, at most once
+    /// per insert, and elements inserted less than `lead` before their
+    /// deadline are announced right away. An element moved out of the lead
+    /// window by [`shift_deadlines_later`](Self::shift_deadlines_later) is
+    /// announced again when it comes back.
+    ///
+    /// Every change to the queue costs the thread O(k log n) for the k
+    /// elements within `lead` of their deadline; the others are not looked
+    /// at.
+    pub fn on_imminent<F>(&self, lead: Duration, f: F) -> ImminentNotifier
+    where
+        F: Fn(Arc<T>) + Send + 'static,
+    {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let events = self.subscribe();
+        let worker = {
+            let queue = self.clone();
+            let stopped = Arc::clone(&stopped);
+            thread::spawn(move || {
+                let mut announced = HashSet::new();
+                while !stopped.load(Ordering::SeqCst) {
+                    let now = queue.clock.now();
+                    let horizon = instant_after(now, lead);
+                    let (due, next) = {
+                        let mut guard = queue.queue.lock();
+                        // Only the entries due within `lead` come off the
+                        // heap; they all go back before the lock is released.
+                        let mut imminent = Vec::new();
+                        while let Some(head) = guard.queue.peek() {
+                            if !head.0.urgent && head.0.deadline > horizon {
+                                break;
+                            }
+                            imminent.push(guard.queue.pop().unwrap());
+                        }
+                        let next = guard
+                            .queue
+                            .peek()
+                            .map(|head| head.0.deadline.checked_sub(lead).unwrap_or(now));
+                        let mut due = Vec::new();
+                        let mut pending = HashSet::with_capacity(imminent.len());
+                        for entry in imminent.iter().map(|entry| &entry.0) {
+                            if !announced.contains(&entry.seq) {
+                                due.push(Arc::clone(&entry.item));
+                            }
+                            pending.insert(entry.seq);
+                        }
+                        if !imminent.is_empty() {
+                            // Pushing them back reshuffles the heap, which
+                            // views copied slice by slice must notice.
+                            guard.touch();
+                        }
+                        guard.queue.extend(imminent);
+                        announced = pending;
+                        (due, next)
+                    };
+                    due.into_iter().for_each(&f);
+                    let timeout = next.map_or(STOP_CHECK, |next| {
+                        next.saturating_duration_since(now).min(STOP_CHECK)
+                    });
+                    match events.recv_timeout(timeout) {
+                        Ok(_) => while events.try_recv().is_ok() {},
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+            })
+        };
+        ImminentNotifier {
+            stopped,
+            worker: Some(worker),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::mpsc, time::Instant};
+
+    use super::*;
+    use crate::{testing::Fixed, DeadlineMode};
+
+    #[test]
+    fn test_on_imminent() {
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let (sender, receiver) = mpsc::channel();
+        let mut notifier = queue.on_imminent(Duration::from_millis(20), move |item| {
+            let _ = sender.send((item, Instant::now()));
+        });
+        let start = Instant::now();
+        queue.try_put(Fixed(60_000_000)).unwrap();
+        queue.try_put(Fixed(3_600_000_000_000)).unwrap();
+
+        let (item, at) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(Fixed(60_000_000), *item);
+        assert!(at - start >= Duration::from_millis(40));
+        assert_eq!(Fixed(60_000_000), *queue.take_or_closed().unwrap());
+        notifier.stop();
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(1, queue.len());
+
+        let (sender, receiver) = mpsc::channel();
+        let mut notifier = queue.on_imminent(Duration::MAX, move |item| {
+            let _ = sender.send(item);
+        });
+        let item = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(Fixed(3_600_000_000_000), *item);
+        notifier.stop();
+        assert_eq!(1, queue.len());
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+    vec,
+};
+
+use crate::{instant_after, DelayQueue, Delayed};
+
+/// Inserts each element due `Duration` from now instead of after its
+/// `delayed`, blocking while the queue is full like [`DelayQueue::put`].
+/// The deadline stays fixed in every [`DeadlineMode`](crate::DeadlineMode);
+/// delays beyond the range of `Instant` saturate.
+impl<T> Extend<(Duration, T)> for DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    fn extend<I: IntoIterator<Item = (Duration, T)>>(&mut self, iter: I) {
+        for (delay, item) in iter {
+            self.put_blocking(instant_after(self.clock.now(), delay), true, Arc::new(item));
+        }
+    }
+}
+
+/// Inserts each element due at the `Instant`, blocking while the queue is
+/// full like [`DelayQueue::put`]. The deadline stays fixed in every
+/// [`DeadlineMode`](crate::DeadlineMode).
+impl<T> Extend<(Instant, T)> for DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    fn extend<I: IntoIterator<Item = (Instant, T)>>(&mut self, iter: I) {
+        for (deadline, item) in iter {
+            self.put_blocking(deadline, true, Arc::new(item));
+        }
+    }
+}
+
+/// Yields every element not delivered yet with its deadline, in delivery
+/// order, for final-drain code at teardown.
+///
+/// Only the last handle of the queue closes and drains it. While other
+/// handles exist the queue stays open and keeps its elements, and the
+/// iterator yields a copy of them.
+impl<T> IntoIterator for DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    type Item = (Instant, Arc<T>);
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        let entries = if Arc::strong_count(&self.queue) == 1 {
+            self.close_and_drain_entries()
+        } else {
+            self.sorted_entries().1
+        };
+        let entries = entries
+            .into_iter()
+            .map(|entry| (entry.deadline, entry.item))
+            .collect::<Vec<_>>();
+        IntoIter {
+            entries: entries.into_iter(),
+        }
+    }
+}
+
+/// The elements left in a [`DelayQueue`], see its `IntoIterator`
+/// implementation.
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    entries: vec::IntoIter<(Instant, Arc<T>)>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = (Instant, Arc<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing::Fixed, Clock, DeadlineMode, QueueEvent, SimClock};
+
+    #[test]
+    fn test_extend() {
+        let clock = SimClock::new();
+        let mut queue = DelayQueue::builder()
+            .deadline_mode(DeadlineMode::Captured)
+            .clock(clock.clone())
+            .build();
+        let now = clock.now();
+        queue.extend(vec![
+            (Duration::from_secs(2), Fixed(2)),
+            (Duration::from_secs(4), Fixed(4)),
+        ]);
+        queue.extend(vec![
+            (now + Duration::from_secs(3), Fixed(3)),
+            (now, Fixed(0)),
+        ]);
+        assert_eq!(4, queue.len());
+
+        clock.advance(Duration::from_secs(3));
+        for expected in &[0, 2, 3] {
+            assert_eq!(Fixed(*expected), *queue.take_or_closed().unwrap());
+        }
+        assert_eq!(1, queue.len());
+    }
+
+    #[test]
+    fn test_extend_dynamic() {
+        let mut queue = DelayQueue::default();
+        let hour = 3_600_000_000_000;
+        queue.extend(vec![(Duration::ZERO, Fixed(hour))]);
+        queue.extend(vec![(Instant::now(), Fixed(hour + 1))]);
+        assert_eq!(Fixed(hour), *queue.try_take().unwrap());
+        assert_eq!(Fixed(hour + 1), *queue.try_take().unwrap());
+
+        queue.extend(vec![(Duration::MAX, Fixed(0))]);
+        assert_eq!(1, queue.len());
+        assert!(queue.try_take().is_none());
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let mut queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let now = Instant::now();
+        queue.extend(vec![
+            (now + Duration::from_secs(2), Fixed(2)),
+            (now, Fixed(1)),
+        ]);
+        let handle = queue.clone();
+        assert_eq!(2, handle.into_iter().len());
+        assert!(!queue.is_closed());
+        assert_eq!(2, queue.len());
+
+        let events = queue.subscribe();
+        let drained = queue.into_iter().collect::<Vec<_>>();
+        assert_eq!(
+            vec![(now, Fixed(1)), (now + Duration::from_secs(2), Fixed(2))],
+            drained
+                .into_iter()
+                .map(|(deadline, item)| (deadline, Fixed(item.0)))
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(events.try_recv(), Ok(QueueEvent::Closed { .. })));
+    }
+}
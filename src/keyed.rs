@@ -0,0 +1,555 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{delayed_until, instant_after, DeadlineMode, DelayQueue, Delayed, PutError};
+
+/// A pending element of a [`KeyedDelayQueue`], as stored in the core queue;
+/// the element itself stays in the index.
+struct Keyed<K> {
+    key: K,
+    token: u64,
+    deadline: Instant,
+}
+
+impl<K> Delayed for Keyed<K> {
+    fn delayed(&self) -> i64 {
+        delayed_until(Instant::now(), self.deadline)
+    }
+}
+
+// Keyed elements with equal deadlines are delivered in insertion order.
+unordered!(Keyed<K>);
+
+struct Slot<T> {
+    token: u64,
+    deadline: Instant,
+    item: Arc<T>,
+}
+
+/// Which pending element currently owns each key. Whoever removes a key from
+/// here owns its element; core entries whose token no longer matches are
+/// stale and skipped.
+struct Index<K, T> {
+    slots: HashMap<K, Slot<T>>,
+    next_token: u64,
+    /// Stale core entries left behind by removals and replacements.
+    stale: usize,
+    /// Elements cancelled with `cancel_soft`, with when they are forgotten.
+    recycled: HashMap<K, (Slot<T>, Instant)>,
+    retention: Duration,
+}
+
+impl<K: Hash + Eq, T> Index<K, T> {
+    /// Forgets soft-cancelled elements whose retention is over.
+    fn purge(&mut self, now: Instant) {
+        self.recycled.retain(|_, (_, forget_at)| *forget_at > now);
+    }
+}
+
+/// How long soft-cancelled elements can be restored unless configured.
+const DEFAULT_RETENTION: Duration = Duration::from_secs(300);
+
+/// A delay queue holding at most one element per key, with explicit
+/// deadlines. Inserting under an existing key replaces its element.
+///
+/// Removing or replacing an element only updates the key index; its entry in
+/// the core queue is skipped when it comes due. Stale entries are swept in
+/// one pass once they outnumber the live ones, so removals cost amortized
+/// O(1).
+pub struct KeyedDelayQueue<K, T> {
+    queue: DelayQueue<Keyed<K>>,
+    index: Arc<Mutex<Index<K, T>>>,
+}
+
+impl<K, T> Default for KeyedDelayQueue<K, T> {
+    fn default() -> Self {
+        Self {
+            queue: DelayQueue::with_deadline_mode(DeadlineMode::Captured),
+            index: Arc::new(Mutex::new(Index {
+                slots: HashMap::new(),
+                next_token: 0,
+                stale: 0,
+                recycled: HashMap::new(),
+                retention: DEFAULT_RETENTION,
+            })),
+        }
+    }
+}
+
+impl<K, T> Clone for KeyedDelayQueue<K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+            index: Arc::clone(&self.index),
+        }
+    }
+}
+
+impl<K, T> KeyedDelayQueue<K, T>
+where
+    K: Hash + Eq + Clone + Send + Sync,
+    T: Send + Sync,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.lock().slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.index.lock().slots.contains_key(key)
+    }
+
+    /// The deadline scheduled for `key`, if any.
+    pub fn deadline(&self, key: &K) -> Option<Instant> {
+        self.index.lock().slots.get(key).map(|slot| slot.deadline)
+    }
+
+    /// Scheduled keys, earliest deadline first. Payloads are not exposed.
+    pub fn keys(&self) -> impl Iterator<Item = K> {
+        self.deadlines().map(|(key, _)| key)
+    }
+
+    /// Scheduled `(key, deadline)` pairs, earliest deadline first.
+    pub fn deadlines(&self) -> impl Iterator<Item = (K, Instant)> {
+        let mut deadlines = self
+            .index
+            .lock()
+            .slots
+            .iter()
+            .map(|(key, slot)| (key.clone(), slot.deadline))
+            .collect::<Vec<_>>();
+        deadlines.sort_by_key(|&(_, deadline)| deadline);
+        deadlines.into_iter()
+    }
+
+    /// Schedules `item` under `key` to expire after `timeout`, returning the
+    /// element it replaced. On a closed queue `item` is handed back and the
+    /// element already scheduled under `key`, if any, stays scheduled.
+    pub fn insert(
+        &self,
+        key: K,
+        item: T,
+        timeout: Duration,
+    ) -> Result<Option<Arc<T>>, PutError<T>> {
+        let deadline = instant_after(self.queue.clock.now(), timeout);
+        self.insert_at(key, item, deadline)
+    }
+
+    /// Like [`insert`](Self::insert), with an absolute deadline measured on
+    /// the queue's clock.
+    pub fn insert_at(
+        &self,
+        key: K,
+        item: T,
+        deadline: Instant,
+    ) -> Result<Option<Arc<T>>, PutError<T>> {
+        let mut index = self.index.lock();
+        let replaced = self.schedule(&mut index, key, item, deadline)?;
+        Ok(replaced.map(|slot| slot.item))
+    }
+
+    /// Like [`insert`](Self::insert), but when `key` is already scheduled the
+    /// new element is `merge(existing, item)`, e.g. the union of pending
+    /// changes, instead of `item` alone.
+    ///
+    /// `merge` runs while the key index is locked, and not at all on a
+    /// closed queue.
+    pub fn insert_with<F>(
+        &self,
+        key: K,
+        item: T,
+        timeout: Duration,
+        merge: F,
+    ) -> Result<Option<Arc<T>>, PutError<T>>
+    where
+        F: FnOnce(&T, T) -> T,
+    {
+        let deadline = instant_after(self.queue.clock.now(), timeout);
+        let mut index = self.index.lock();
+        let token = match self.enqueue(&mut index, key.clone(), deadline) {
+            Some(token) => token,
+            None => return Err(PutError::Closed(item)),
+        };
+        let item = match index.slots.get(&key) {
+            Some(slot) => merge(&slot.item, item),
+            None => item,
+        };
+        let replaced = self.install(&mut index, key, token, Arc::new(item), deadline);
+        Ok(replaced.map(|slot| slot.item))
+    }
+
+    /// Schedules `item` under `key` unless the key is already scheduled at
+    /// or before `deadline`, in which case `item` is dropped. Returns the
+    /// deadline the key ends up with.
+    ///
+    /// Useful to remind at the earliest of several triggers. Fails like
+    /// [`insert`](Self::insert) on a closed queue when `item` would have
+    /// been scheduled.
+    pub fn put_min(&self, key: K, deadline: Instant, item: T) -> Result<Instant, PutError<T>> {
+        let mut index = self.index.lock();
+        match index.slots.get(&key) {
+            Some(slot) if slot.deadline <= deadline => Ok(slot.deadline),
+            _ => {
+                self.schedule(&mut index, key, item, deadline)?;
+                Ok(deadline)
+            }
+        }
+    }
+
+    /// Like [`put_min`](Self::put_min), but always keeps a payload combined
+    /// by `merge(existing, item)` when the key is already scheduled.
+    ///
+    /// `merge` runs while the key index is locked, and not at all on a
+    /// closed queue.
+    pub fn put_min_with<F>(
+        &self,
+        key: K,
+        deadline: Instant,
+        item: T,
+        merge: F,
+    ) -> Result<Instant, PutError<T>>
+    where
+        F: FnOnce(&T, T) -> T,
+    {
+        let mut index = self.index.lock();
+        let deadline = match index.slots.get(&key) {
+            Some(slot) => slot.deadline.min(deadline),
+            None => deadline,
+        };
+        let token = match self.enqueue(&mut index, key.clone(), deadline) {
+            Some(token) => token,
+            None => return Err(PutError::Closed(item)),
+        };
+        let item = match index.slots.get(&key) {
+            Some(slot) => merge(&slot.item, item),
+            None => item,
+        };
+        self.install(&mut index, key, token, Arc::new(item), deadline);
+        Ok(deadline)
+    }
+
+    /// Makes `item` the element of `key`, returning the slot it replaced.
+    fn schedule(
+        &self,
+        index: &mut Index<K, T>,
+        key: K,
+        item: T,
+        deadline: Instant,
+    ) -> Result<Option<Slot<T>>, PutError<T>> {
+        match self.enqueue(index, key.clone(), deadline) {
+            Some(token) => Ok(self.install(index, key, token, Arc::new(item), deadline)),
+            None => Err(PutError::Closed(item)),
+        }
+    }
+
+    /// Puts the core entry of a new element of `key` into the queue and
+    /// returns its token, or `None` if the queue is closed. The entry stays
+    /// stale until `install` is called with the token.
+    fn enqueue(&self, index: &mut Index<K, T>, key: K, deadline: Instant) -> Option<u64> {
+        let token = index.next_token;
+        index.next_token += 1;
+        let keyed = Keyed {
+            key,
+            token,
+            deadline,
+        };
+        self.queue
+            .put_at(deadline, Arc::new(keyed))
+            .then_some(token)
+    }
+
+    /// Makes `item` the element of `key` under `token`, returning the slot
+    /// it replaced.
+    fn install(
+        &self,
+        index: &mut Index<K, T>,
+        key: K,
+        token: u64,
+        item: Arc<T>,
+        deadline: Instant,
+    ) -> Option<Slot<T>> {
+        let slot = Slot {
+            token,
+            deadline,
+            item,
+        };
+        let replaced = index.slots.insert(key, slot);
+        if replaced.is_some() {
+            self.retire(index);
+        }
+        replaced
+    }
+
+    /// Counts a core entry that has gone stale, sweeping them all once they
+    /// outnumber the live ones.
+    fn retire(&self, index: &mut Index<K, T>) {
+        index.stale += 1;
+        if index.stale > index.slots.len() {
+            self.sweep(index);
+        }
+    }
+
+    fn sweep(&self, index: &mut Index<K, T>) {
+        let slots = &index.slots;
+        self.queue.remove_where(|keyed| {
+            slots
+                .get(&keyed.key)
+                .is_none_or(|slot| slot.token != keyed.token)
+        });
+        index.stale = 0;
+    }
+
+    /// Cancels the element scheduled under `key` and returns it.
+    pub fn remove(&self, key: &K) -> Option<Arc<T>> {
+        let mut index = self.index.lock();
+        let slot = index.slots.remove(key)?;
+        self.retire(&mut index);
+        Some(slot.item)
+    }
+
+    /// Sets how long [`cancel_soft`](Self::cancel_soft) keeps elements
+    /// restorable, five minutes by default.
+    pub fn set_recycle_retention(&self, retention: Duration) {
+        self.index.lock().retention = retention;
+    }
+
+    /// Cancels the element scheduled under `key` like
+    /// [`remove`](Self::remove), but keeps it restorable with
+    /// [`restore`](Self::restore) for the retention window, e.g. to undo a
+    /// mistaken cancellation in admin tooling. Returns `false` if nothing
+    /// was scheduled under `key`.
+    pub fn cancel_soft(&self, key: &K) -> bool {
+        let now = self.queue.clock.now();
+        let mut index = self.index.lock();
+        index.purge(now);
+        let slot = match index.slots.remove(key) {
+            Some(slot) => slot,
+            None => return false,
+        };
+        self.retire(&mut index);
+        let forget_at = instant_after(now, index.retention);
+        index.recycled.insert(key.clone(), (slot, forget_at));
+        true
+    }
+
+    /// Schedules the element soft-cancelled under `key` again at its
+    /// original deadline; one already past is delivered right away. Returns
+    /// `false` if there is none within its retention, if `key` has been
+    /// scheduled again since, or if the queue is closed.
+    pub fn restore(&self, key: &K) -> bool {
+        let mut index = self.index.lock();
+        index.purge(self.queue.clock.now());
+        if index.slots.contains_key(key) {
+            return false;
+        }
+        let deadline = match index.recycled.get(key) {
+            Some((slot, _)) => slot.deadline,
+            None => return false,
+        };
+        let token = match self.enqueue(&mut index, key.clone(), deadline) {
+            Some(token) => token,
+            None => return false,
+        };
+        let (slot, _) = index.recycled.remove(key).unwrap();
+        self.install(&mut index, key.clone(), token, slot.item, deadline);
+        true
+    }
+
+    /// Blocks until an element expires and returns it with its key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the queue is closed and empty.
+    pub fn take(&self) -> (K, Arc<T>) {
+        self.take_or_closed()
+            .expect("take on a closed and empty KeyedDelayQueue")
+    }
+
+    /// Like [`take`](Self::take), but returns `None` once the queue is closed
+    /// and empty.
+    pub fn take_or_closed(&self) -> Option<(K, Arc<T>)> {
+        loop {
+            let keyed = self.queue.take_or_closed()?;
+            let mut index = self.index.lock();
+            if index
+                .slots
+                .get(&keyed.key)
+                .is_none_or(|slot| slot.token != keyed.token)
+            {
+                index.stale = index.stale.saturating_sub(1);
+                continue;
+            }
+            let slot = index.slots.remove(&keyed.key).unwrap();
+            // Don't leave a consumer of a closed queue waiting for entries
+            // that are all stale.
+            if index.slots.is_empty() && index.stale > 0 {
+                self.sweep(&mut index);
+            }
+            return Some((keyed.key.clone(), slot.item));
+        }
+    }
+
+    /// Stops accepting new elements, see [`DelayQueue::close`].
+    pub fn close(&self) {
+        self.queue.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keys_and_deadlines() {
+        let queue = KeyedDelayQueue::new();
+        let now = Instant::now();
+        let at = |ms| now + Duration::from_millis(ms);
+        queue.insert_at("a", 1, at(20)).unwrap();
+        queue.insert_at("b", 2, at(3_600_000)).unwrap();
+        queue.insert_at("c", 3, at(30)).unwrap();
+        assert_eq!(
+            Some(2),
+            queue.insert_at("b", 4, at(10)).unwrap().map(|item| *item)
+        );
+        assert_eq!(Some(3), queue.remove(&"c").map(|item| *item));
+
+        assert_eq!(vec!["b", "a"], queue.keys().collect::<Vec<_>>());
+        assert_eq!(
+            vec![("b", at(10)), ("a", at(20))],
+            queue.deadlines().collect::<Vec<_>>()
+        );
+        let (key, item) = queue.take();
+        assert_eq!(("b", 4), (key, *item));
+        let (key, item) = queue.take();
+        assert_eq!(("a", 1), (key, *item));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_put_min() {
+        let queue = KeyedDelayQueue::new();
+        let now = Instant::now();
+        let at = |ms| now + Duration::from_millis(ms);
+        assert_eq!(Ok(at(30)), queue.put_min("a", at(30), 1));
+        assert_eq!(Ok(at(30)), queue.put_min("a", at(40), 2));
+        assert_eq!(Ok(at(10)), queue.put_min("a", at(10), 3));
+        assert_eq!(
+            Ok(at(10)),
+            queue.put_min_with("a", at(20), 4, |old, new| old + new)
+        );
+
+        let (key, item) = queue.take();
+        assert_eq!(("a", 7), (key, *item));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_insert_with() {
+        let queue = KeyedDelayQueue::new();
+        let union = |old: &Vec<u32>, mut new: Vec<u32>| {
+            new.extend(old);
+            new.sort_unstable();
+            new
+        };
+        assert!(queue
+            .insert_with("doc", vec![2], Duration::from_secs(60), union)
+            .unwrap()
+            .is_none());
+        let replaced = queue
+            .insert_with("doc", vec![1, 3], Duration::ZERO, union)
+            .unwrap();
+        assert_eq!(Some(vec![2]), replaced.map(|item| item.to_vec()));
+
+        let (key, item) = queue.take();
+        assert_eq!(("doc", vec![1, 2, 3]), (key, item.to_vec()));
+
+        assert!(queue
+            .insert("far", vec![], Duration::MAX)
+            .unwrap()
+            .is_none());
+        assert!(queue
+            .insert_with("far", vec![1], Duration::MAX, union)
+            .unwrap()
+            .is_some());
+        assert_eq!(1, queue.len());
+    }
+
+    #[test]
+    fn test_cancel_soft() {
+        let queue = KeyedDelayQueue::new();
+        let now = Instant::now();
+        let hour = Duration::from_secs(3600);
+        queue.insert_at("a", 1, now + hour).unwrap();
+        queue.insert_at("b", 2, now + hour).unwrap();
+        assert!(queue.cancel_soft(&"a"));
+        assert!(!queue.cancel_soft(&"a"));
+        assert_eq!(1, queue.len());
+
+        assert!(queue.restore(&"a"));
+        assert!(!queue.restore(&"a"));
+        assert_eq!(Some(now + hour), queue.deadline(&"a"));
+
+        queue.set_recycle_retention(Duration::MAX);
+        assert!(queue.cancel_soft(&"a"));
+        assert!(queue.restore(&"a"));
+
+        queue.set_recycle_retention(Duration::ZERO);
+        assert!(queue.cancel_soft(&"b"));
+        assert!(!queue.restore(&"b"));
+        assert_eq!(1, queue.len());
+    }
+
+    #[test]
+    fn test_stale_entries() {
+        let queue = KeyedDelayQueue::new();
+        let later = Instant::now() + Duration::from_secs(3600);
+        for (key, item) in [("a", 1), ("b", 2), ("c", 3), ("a", 4), ("a", 5)] {
+            queue.insert_at(key, item, later).unwrap();
+        }
+        assert_eq!(3, queue.len());
+        assert_eq!(5, queue.queue.len());
+        queue.remove(&"b");
+        assert_eq!(2, queue.queue.len());
+
+        queue.remove(&"a");
+        assert!(queue.cancel_soft(&"c"));
+        assert_eq!(0, queue.queue.len());
+        queue.close();
+        assert!(queue.take_or_closed().is_none());
+    }
+
+    #[test]
+    fn test_insert_closed() {
+        let queue = KeyedDelayQueue::new();
+        let later = Instant::now() + Duration::from_secs(3600);
+        queue.insert_at("a", 1, later).unwrap();
+        assert!(queue.cancel_soft(&"a"));
+        queue.insert_at("b", 2, later).unwrap();
+        queue.close();
+
+        assert_eq!(Err(PutError::Closed(3)), queue.insert_at("b", 3, later));
+        assert_eq!(
+            Err(PutError::Closed(4)),
+            queue.put_min_with("b", later, 4, |old, new| old + new)
+        );
+        assert_eq!(Ok(later), queue.put_min("b", later, 5));
+        assert_eq!(Err(PutError::Closed(6)), queue.put_min("c", later, 6));
+        assert!(!queue.restore(&"a"));
+        assert_eq!(1, queue.len());
+        assert_eq!(Some(later), queue.deadline(&"b"));
+        assert_eq!(1, queue.queue.len());
+    }
+}
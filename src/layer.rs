@@ -0,0 +1,163 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// A shared handler run by an [`Executor`](crate::Executor) on every element.
+pub type Handler<T> = Arc<dyn Fn(&T) + Send + Sync>;
+
+/// Wraps an executor handler with a cross-cutting concern, see
+/// [`ExecutorBuilder::layer`](crate::ExecutorBuilder::layer).
+///
+/// Any `Fn(Handler<T>) -> Handler<T>` is a layer.
+pub trait ExecutorLayer<T>: Send + Sync + 'static {
+    fn layer(&self, inner: Handler<T>) -> Handler<T>;
+}
+
+impl<T, F> ExecutorLayer<T> for F
+where
+    F: Fn(Handler<T>) -> Handler<T> + Send + Sync + 'static,
+{
+    fn layer(&self, inner: Handler<T>) -> Handler<T> {
+        self(inner)
+    }
+}
+
+/// Reports how long every invocation took, including ones that panicked.
+pub struct Timing<F> {
+    report: Arc<F>,
+}
+
+impl<F> Timing<F> {
+    pub fn new(report: F) -> Self {
+        Self {
+            report: Arc::new(report),
+        }
+    }
+}
+
+impl<T, F> ExecutorLayer<T> for Timing<F>
+where
+    T: 'static,
+    F: Fn(&T, Duration) + Send + Sync + 'static,
+{
+    fn layer(&self, inner: Handler<T>) -> Handler<T> {
+        let report = Arc::clone(&self.report);
+        Arc::new(move |item: &T| {
+            let started = Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| inner(item)));
+            report(item, started.elapsed());
+            if let Err(payload) = result {
+                panic::resume_unwind(payload);
+            }
+        })
+    }
+}
+
+/// Runs a panicking handler again, up to `attempts` times in total. The last
+/// panic is passed on.
+pub struct Retry {
+    attempts: usize,
+}
+
+impl Retry {
+    pub fn new(attempts: usize) -> Self {
+        assert!(attempts > 0, "retrying needs at least one attempt");
+        Self { attempts }
+    }
+}
+
+impl<T: 'static> ExecutorLayer<T> for Retry {
+    fn layer(&self, inner: Handler<T>) -> Handler<T> {
+        let attempts = self.attempts;
+        Arc::new(move |item: &T| {
+            for _ in 1..attempts {
+                if panic::catch_unwind(AssertUnwindSafe(|| inner(item))).is_ok() {
+                    return;
+                }
+            }
+            inner(item)
+        })
+    }
+}
+
+/// Fails invocations that take longer than `timeout`: once the handler
+/// returns late, the layer panics, so outer layers such as [`Retry`] and the
+/// executor's failure hook treat it like any other failure. The handler is
+/// not interrupted, since threads cannot be aborted; see
+/// [`ExecutorBuilder::with_task_timeout`](crate::ExecutorBuilder::with_task_timeout)
+/// to hear about stuck invocations while they run.
+pub struct Timeout {
+    timeout: Duration,
+}
+
+impl Timeout {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl<T: 'static> ExecutorLayer<T> for Timeout {
+    fn layer(&self, inner: Handler<T>) -> Handler<T> {
+        let timeout = self.timeout;
+        Arc::new(move |item: &T| {
+            let started = Instant::now();
+            inner(item);
+            let elapsed = started.elapsed();
+            if elapsed > timeout {
+                panic!("handler timed out after {:?}", elapsed);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_layers_compose() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let timed = Arc::new(Mutex::new(Vec::new()));
+        let handler: Handler<u32> = {
+            let calls = Arc::clone(&calls);
+            Arc::new(move |item: &u32| {
+                if calls.fetch_add(1, Ordering::SeqCst) < *item as usize {
+                    panic!("flaky");
+                }
+            })
+        };
+        let timing = {
+            let timed = Arc::clone(&timed);
+            Timing::new(move |item: &u32, _: Duration| timed.lock().push(*item))
+        };
+        let handler = ExecutorLayer::layer(&timing, Retry::new(3).layer(handler));
+
+        handler(&2);
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+
+        calls.store(0, Ordering::SeqCst);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| handler(&3)));
+        assert!(result.is_err());
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+        assert_eq!(vec![2, 3], *timed.lock());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let handler: Handler<u64> = Arc::new(|millis: &u64| {
+            std::thread::sleep(Duration::from_millis(*millis));
+        });
+        let handler = Timeout::new(Duration::from_millis(20)).layer(handler);
+        handler(&0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| handler(&40)));
+        let payload = result.unwrap_err();
+        let message = payload.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("handler timed out after"));
+    }
+}
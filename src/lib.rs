@@ -1,15 +1,244 @@
-use std::{cmp::Reverse, collections::BinaryHeap, sync::Arc, thread::ThreadId, time};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering as AtomicOrdering},
+        mpsc, Arc,
+    },
+    thread::ThreadId,
+    time::{self, Instant},
+};
 
-use parking_lot::{Condvar, Mutex};
+use parking_lot::{Mutex, MutexGuard};
 
+use diagnostics::Wakeup;
+use signal::Signal;
+
+/// Implements an `Ord` under which all values are equal, for internal
+/// elements ordered by their cached deadline alone, so elements with equal
+/// deadlines keep their insertion order.
+macro_rules! unordered {
+    ($name:ident $(<$($param:ident),+>)?) => {
+        impl$(<$($param),+>)? ::std::cmp::Ord for $name$(<$($param),+>)? {
+            fn cmp(&self, _: &Self) -> ::std::cmp::Ordering {
+                ::std::cmp::Ordering::Equal
+            }
+        }
+
+        impl$(<$($param),+>)? ::std::cmp::PartialOrd for $name$(<$($param),+>)? {
+            fn partial_cmp(&self, other: &Self) -> Option<::std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl$(<$($param),+>)? ::std::cmp::PartialEq for $name$(<$($param),+>)? {
+            fn eq(&self, _: &Self) -> bool {
+                true
+            }
+        }
+
+        impl$(<$($param),+>)? ::std::cmp::Eq for $name$(<$($param),+>)? {}
+    };
+}
+
+#[cfg(feature = "tokio")]
+mod async_queue;
+mod audit;
+mod buckets;
+mod builder;
+mod chunks;
+mod clock;
+#[cfg(feature = "bytes")]
+mod codec;
+#[cfg(any(feature = "lz4", feature = "zstd"))]
+mod compress;
+mod config;
+mod delivery;
+mod diagnostics;
+mod drift;
+#[cfg(feature = "chacha20poly1305")]
+mod encrypt;
+mod error;
+mod escalation;
+mod executor;
+mod failpoints;
+mod firings;
+mod forward;
+mod freeze;
+mod future;
+mod handles;
+mod headers;
+mod health;
+mod hybrid;
+mod idle;
+mod imminent;
+mod iter;
+mod keyed;
+mod layer;
+mod load;
+mod logging;
+mod mirror;
+mod multi;
+#[cfg(feature = "otel")]
+mod otel;
+mod prepare;
+mod promote;
+mod registry;
+mod relay;
+mod scheduler;
+mod shared;
+mod signal;
+mod snapshot;
+mod spin;
+mod staged;
+mod stats;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod tiers;
+mod timeout;
+mod timer;
+#[cfg(feature = "tokio-util")]
+mod tokio_compat;
+mod transform;
+mod watchdog;
+mod window;
+#[cfg(feature = "prost")]
+mod wire;
+mod worker;
+
+#[cfg(feature = "tokio")]
+pub use async_queue::AsyncDelayQueue;
+pub use audit::{AdminAction, AuditRecord, AuditSink};
+pub use builder::DelayQueueBuilder;
+pub use chunks::{ViewIntoIter, ViewIter};
+#[cfg(feature = "tokio")]
+pub use clock::TokioClock;
+pub use clock::{AdvanceHook, Clock, SimClock, SystemClock};
+#[cfg(feature = "bincode")]
+pub use codec::BincodeCodec;
+#[cfg(feature = "serde_json")]
+pub use codec::JsonCodec;
+#[cfg(feature = "prost")]
+pub use codec::ProstCodec;
+#[cfg(feature = "bytes")]
+pub use codec::{ByteDelayQueue, Codec};
+#[cfg(any(feature = "lz4", feature = "zstd"))]
+pub use compress::{Compressed, CompressedError, Compression};
+pub use config::{ConfigChange, QueueConfig};
+pub use delivery::{Delivery, SequenceTracker};
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::DiagnosticsReport;
+pub use drift::DriftMonitor;
+#[cfg(feature = "chacha20poly1305")]
+pub use encrypt::{Encrypted, EncryptedError, KeyProvider};
+pub use error::PutError;
+pub use escalation::{Escalation, EscalationPolicy, EscalationQueue};
+pub use executor::{Executor, ExecutorBuilder, Failure, FailureReason, ShutdownReport};
+#[cfg(feature = "failpoints")]
+pub use failpoints::Failpoints;
+pub use firings::Firings;
+pub use freeze::{FreezePolicy, FrozenGuard};
+pub use future::{TimerError, TimerFuture, TimerService};
+pub use handles::{AdminHandle, ConsumerHandle, ProducerHandle};
+pub use headers::Headers;
+pub use health::Health;
+pub use hybrid::{FarStore, HybridQueue, MemoryFarStore};
+pub use idle::IdleNotifier;
+pub use imminent::ImminentNotifier;
+pub use iter::IntoIter;
+pub use keyed::KeyedDelayQueue;
+pub use layer::{ExecutorLayer, Handler, Retry, Timeout, Timing};
+pub use load::{Corrupted, LoadProgress};
+#[cfg(feature = "log")]
+pub use logging::{set_log_levels, LogLevels};
+pub use mirror::{Mirror, QueueEvent};
+pub use multi::MultiQueueTake;
+#[cfg(feature = "otel")]
+pub use otel::Traced;
+pub use prepare::{Prepared, Reservation};
+pub use promote::{Promoter, PromotionStats, PromotionTask};
+pub use registry::{IdleCollector, QueueRegistry, TeardownPolicy};
+pub use relay::{OutboxSource, Relay, Relayed};
+pub use scheduler::{DynDelayQueue, Scheduler};
+pub use shared::{LightQueue, SharedTimer};
+pub use snapshot::QueueView;
+pub use staged::StagedQueue;
+pub use stats::Stats;
+pub use tiers::{RetryAttempt, RetryTiers};
+pub use timeout::TimeoutMap;
+#[cfg(feature = "calibrate")]
+pub use timer::calibrate_timer;
+pub use transform::TransformingConsumer;
+pub use watchdog::Watchdog;
+#[cfg(feature = "prost")]
+pub use wire::DelayedEntry;
+
+/// An element that becomes available after a delay.
+///
+/// Both the ordering and the value returned by `delayed` must stay consistent
+/// while the element is queued. If a deadline is mutated in place (e.g. through
+/// interior mutability), call [`DelayQueue::revalidate`] afterwards, otherwise
+/// the heap ordering is silently corrupted.
 pub trait Delayed: Ord {
+    /// Remaining delay in nanoseconds; zero or negative means expired.
     fn delayed(&self) -> i64;
 }
 
-#[derive(Default)]
+/// How the queue obtains the deadline of an element.
+///
+/// Either way `delayed` is only ever called outside the queue lock, and the
+/// heap is ordered by the absolute deadline cached at insert.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DeadlineMode {
+    /// Re-check `delayed` before delivering an element whose cached deadline
+    /// has passed, and reschedule it if its deadline moved later.
+    ///
+    /// Deadlines that move earlier are only picked up by
+    /// [`DelayQueue::revalidate`].
+    #[default]
+    Dynamic,
+    /// Call `delayed` once in `put` and keep the resulting absolute deadline.
+    ///
+    /// Protects the queue from non-monotonic or expensive `delayed`
+    /// implementations, at the cost of ignoring later deadline changes.
+    Captured,
+}
+
+/// How elements with equal deadlines are ordered relative to each other.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OrderingMode {
+    /// Earliest deadline first; ties are broken by the elements' `Ord`, then
+    /// by insertion order.
+    #[default]
+    StrictDeadline,
+    /// Earliest deadline first; ties are delivered in insertion order.
+    DeadlineThenFifo,
+    /// Earliest deadline first; the order of ties is unspecified and may
+    /// change between releases. Never calls the elements' `Ord`.
+    Relaxed,
+}
+
 pub struct DelayQueue<T: Delayed> {
     queue: Arc<Mutex<DelayQueueInner<T>>>,
-    available: Arc<Condvar>,
+    available: Arc<Signal>,
+    not_full: Arc<Signal>,
+    mode: DeadlineMode,
+    clock: Arc<dyn Clock>,
+    version: Arc<AtomicU64>,
+    view: Arc<snapshot::ViewCache<T>>,
+    name: Option<Arc<str>>,
+    labels: Arc<Headers>,
+    diagnostics: Arc<diagnostics::Diagnostics>,
+}
+
+impl<T: Delayed> Default for DelayQueue<T> {
+    fn default() -> Self {
+        Self::with_deadline_mode(DeadlineMode::default())
+    }
 }
 
 impl<T: Delayed> Clone for DelayQueue<T> {
@@ -17,83 +246,1246 @@ impl<T: Delayed> Clone for DelayQueue<T> {
         Self {
             queue: Arc::clone(&self.queue),
             available: Arc::clone(&self.available),
+            not_full: Arc::clone(&self.not_full),
+            mode: self.mode,
+            clock: Arc::clone(&self.clock),
+            version: Arc::clone(&self.version),
+            view: Arc::clone(&self.view),
+            name: self.name.clone(),
+            labels: Arc::clone(&self.labels),
+            diagnostics: Arc::clone(&self.diagnostics),
+        }
+    }
+}
+
+type DiscardHook<T> = Arc<dyn Fn(Arc<T>) + Send + Sync>;
+
+struct Entry<T> {
+    deadline: Instant,
+    seq: u64,
+    /// Inserted by `put_now`; delivered before every other expired element.
+    urgent: bool,
+    /// Inserted at an explicit deadline, which is never re-derived from
+    /// `delayed`.
+    pinned: bool,
+    /// Nanoseconds added by `shift_deadlines_*`, applied on top of `delayed`.
+    shift: i64,
+    ordering: OrderingMode,
+    item: Arc<T>,
+    headers: Option<Arc<Headers>>,
+}
+
+impl<T> Clone for Entry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            deadline: self.deadline,
+            seq: self.seq,
+            urgent: self.urgent,
+            pinned: self.pinned,
+            shift: self.shift,
+            ordering: self.ordering,
+            item: Arc::clone(&self.item),
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+impl<T: Ord> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.urgent, other.urgent) {
+            (true, true) => self.seq.cmp(&other.seq),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => {
+                let by_deadline = self.deadline.cmp(&other.deadline);
+                match self.ordering {
+                    OrderingMode::StrictDeadline => by_deadline
+                        .then_with(|| self.item.cmp(&other.item))
+                        .then_with(|| self.seq.cmp(&other.seq)),
+                    OrderingMode::DeadlineThenFifo | OrderingMode::Relaxed => {
+                        by_deadline.then_with(|| self.seq.cmp(&other.seq))
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Ord> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord> Eq for Entry<T> {}
+
+/// The deadline `delayed` nanoseconds after `now`. Negative delays are in
+/// the past and saturate at the earliest representable instant; delays the
+/// platform cannot represent saturate at the latest one found.
+fn deadline_after(now: Instant, delayed: i64) -> Instant {
+    let mut by = time::Duration::from_nanos(delayed.unsigned_abs());
+    if delayed > 0 {
+        loop {
+            match now.checked_add(by) {
+                Some(deadline) => return deadline,
+                None => by /= 2,
+            }
+        }
+    } else {
+        now.checked_sub(by).unwrap_or(now)
+    }
+}
+
+/// `at + by`, saturating at the same limit as `deadline_after` instead of
+/// panicking on durations the platform cannot represent.
+fn instant_after(at: Instant, by: time::Duration) -> Instant {
+    deadline_after(at, by.as_nanos().min(i64::MAX as u128) as i64)
+}
+
+/// The inverse of `deadline_after`: nanoseconds from `now` until `deadline`.
+fn delayed_until(now: Instant, deadline: Instant) -> i64 {
+    match deadline.checked_duration_since(now) {
+        Some(remaining) => remaining.as_nanos().min(i64::MAX as u128) as i64,
+        None => {
+            -(now
+                .duration_since(deadline)
+                .as_nanos()
+                .min(i64::MAX as u128) as i64)
         }
     }
 }
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 struct DelayQueueInner<T: Delayed> {
-    queue: BinaryHeap<Reverse<Arc<T>>>,
+    queue: BinaryHeap<Reverse<Entry<T>>>,
     current_thread: Option<ThreadId>,
+    next_seq: u64,
+    next_delivery_seq: u64,
+    delivered: u64,
+    /// Leader wakeups that found nothing to deliver and woke another taker.
+    renotified: u64,
+    /// Undecodable entries set aside while loading.
+    quarantined: u64,
+    capacity: Option<usize>,
+    /// How far ahead elements may be scheduled; `None` for no limit.
+    max_horizon: Option<time::Duration>,
+    /// Slots held by `Prepared` elements that are not committed yet.
+    reserved: usize,
+    ordering: OrderingMode,
+    closed: bool,
+    on_discard: Option<DiscardHook<T>>,
+    audit: Option<Arc<dyn AuditSink>>,
+    min_spacing: Option<time::Duration>,
+    last_delivery: Option<Instant>,
+    /// Recent lateness of deliveries, for `health`.
+    lateness: health::LatenessWindow,
+    /// Nanoseconds elements are delivered ahead of their deadline to make up
+    /// for systematic wakeup lateness; `None` unless compensation is enabled.
+    pre_fire: Option<i64>,
+    notify_threshold: Option<time::Duration>,
+    /// Granularity the leader's wakeups for far deadlines are rounded up to.
+    coarse_timers: Option<time::Duration>,
+    /// When the waiting leader, if any, wakes up on its own.
+    leader_wakes_at: Option<Instant>,
+    observers: Vec<mpsc::SyncSender<QueueEvent>>,
+    /// The queue's labels, attached to every event.
+    labels: Arc<Headers>,
+    /// Monotonic and wall-clock time read together, for `clock_drift`.
+    epoch: (Instant, time::SystemTime),
+    /// Set while a `FrozenGuard` is alive.
+    frozen: Option<freeze::Frozen<T>>,
+    /// Deadlines by bucket, for range queries; `None` unless enabled.
+    buckets: Option<buckets::BucketIndex>,
+    /// The entries in delivery order, for views; `None` unless enabled.
+    chunks: Option<chunks::ChunkedEntries<T>>,
+    #[cfg(feature = "failpoints")]
+    failpoints: failpoints::Failpoints,
+    /// Bumped on every change to `queue`; readable without the lock.
+    version: Arc<AtomicU64>,
 }
 
 impl<T: Delayed> DelayQueueInner<T> {
-    fn peek(&self) -> Option<&T> {
+    fn peek(&self) -> Option<&Entry<T>> {
         let result = self.queue.peek()?;
         Some(&result.0)
     }
+
+    fn is_full(&self) -> bool {
+        !self.has_room(1)
+    }
+
+    /// Whether `n` more elements are admitted: the queue is not frozen with
+    /// `FreezePolicy::Reject` and has `n` slots left besides the reserved
+    /// ones.
+    fn has_room(&self, n: usize) -> bool {
+        self.frozen
+            .as_ref()
+            .is_none_or(|frozen| frozen.policy != FreezePolicy::Reject)
+            && self
+                .capacity
+                .is_none_or(|capacity| self.queue.len() + self.reserved + n <= capacity)
+    }
+
+    fn beyond_horizon(&self, now: Instant, deadline: Instant) -> bool {
+        self.max_horizon
+            .is_some_and(|horizon| deadline.saturating_duration_since(now) > horizon)
+    }
+
+    /// When the leader waiting for `deadline` wakes up: with coarse timers
+    /// and `deadline` further ahead than their granularity, the next
+    /// multiple of it after `deadline`, counted from the epoch.
+    fn wake_for(&self, now: Instant, deadline: Instant) -> Instant {
+        let granularity = match self.coarse_timers {
+            Some(granularity) if deadline > now + granularity => granularity.as_nanos().max(1),
+            _ => return deadline,
+        };
+        let offset = deadline.saturating_duration_since(self.epoch.0).as_nanos();
+        let rounded = offset.div_ceil(granularity) * granularity;
+        let rounded = time::Duration::from_nanos(rounded.min(u64::MAX as u128) as u64);
+        self.epoch.0.checked_add(rounded).unwrap_or(deadline)
+    }
+
+    fn entry(&self, deadline: Instant, urgent: bool, item: Arc<T>) -> Entry<T> {
+        Entry {
+            deadline,
+            seq: 0,
+            urgent,
+            pinned: false,
+            shift: 0,
+            ordering: self.ordering,
+            item,
+            headers: None,
+        }
+    }
+
+    /// An entry due at `deadline` whatever `delayed` says later on.
+    fn pinned_entry(&self, deadline: Instant, item: Arc<T>) -> Entry<T> {
+        Entry {
+            pinned: true,
+            ..self.entry(deadline, false, item)
+        }
+    }
+
+    fn push(&mut self, deadline: Instant, urgent: bool, item: Arc<T>) -> u64 {
+        self.push_entry(self.entry(deadline, urgent, item))
+    }
+
+    /// Like `push_entry`, but also tells whether blocked takers must be
+    /// woken: the element's deadline is strictly earlier than every queued
+    /// one and the leader would not wake up in time.
+    ///
+    /// Only deadlines are compared, never the elements, so ties with the
+    /// current head never cause a wakeup whatever `T: Ord` says.
+    fn push_entry_wakes(&mut self, entry: Entry<T>) -> bool {
+        let deadline = entry.deadline;
+        let earlier = self.peek().is_none_or(|head| deadline < head.deadline);
+        self.push_entry(entry);
+        if !earlier {
+            return false;
+        }
+        match (self.leader_wakes_at, self.notify_threshold) {
+            (Some(wakes_at), Some(threshold)) => {
+                wakes_at.saturating_duration_since(deadline) > threshold
+            }
+            _ => true,
+        }
+    }
+
+    /// Inserts `entry` under a fresh sequence number.
+    fn push_entry(&mut self, mut entry: Entry<T>) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        entry.seq = seq;
+        entry.ordering = self.ordering;
+        match self.frozen.as_mut() {
+            Some(frozen) => frozen.buffered.push(entry),
+            None => self.insert(entry),
+        }
+        seq
+    }
+
+    /// Adds an entry that already has its sequence number to the heap.
+    fn insert(&mut self, entry: Entry<T>) {
+        self.emit(QueueEvent::Inserted {
+            deadline: entry.deadline,
+            labels: Arc::clone(&self.labels),
+        });
+        if let Some(buckets) = self.buckets.as_mut() {
+            buckets.insert(entry.deadline);
+        }
+        if let Some(chunks) = self.chunks.as_mut() {
+            chunks.insert(entry.clone());
+        }
+        self.queue.push(Reverse(entry));
+        self.touch();
+    }
+
+    fn pop(&mut self) -> Option<Entry<T>> {
+        let entry = self.queue.pop()?.0;
+        if let Some(buckets) = self.buckets.as_mut() {
+            buckets.remove(entry.deadline);
+        }
+        if let Some(chunks) = self.chunks.as_mut() {
+            chunks.remove(&entry);
+        }
+        self.touch();
+        Some(entry)
+    }
+
+    fn drain(&mut self) -> Vec<Entry<T>> {
+        let entries = std::mem::take(&mut self.queue)
+            .into_vec()
+            .into_iter()
+            .map(|entry| entry.0)
+            .collect();
+        if let Some(buckets) = self.buckets.as_mut() {
+            buckets.clear();
+        }
+        if let Some(chunks) = self.chunks.as_mut() {
+            *chunks = chunks::ChunkedEntries::default();
+        }
+        self.touch();
+        entries
+    }
+
+    /// Brings the bucket index and the chunks back in line after `queue`
+    /// was rebuilt.
+    fn reindex(&mut self) {
+        if let Some(buckets) = self.buckets.as_mut() {
+            buckets.rebuild(self.queue.iter().map(|entry| entry.0.deadline));
+        }
+        if let Some(chunks) = self.chunks.as_mut() {
+            let mut entries = self
+                .queue
+                .iter()
+                .map(|entry| entry.0.clone())
+                .collect::<Vec<_>>();
+            entries.sort();
+            *chunks = chunks::ChunkedEntries::from_sorted(entries);
+        }
+    }
+
+    fn touch(&self) {
+        self.version.fetch_add(1, AtomicOrdering::Release);
+    }
+
+    /// How far ahead of its deadline an element is delivered.
+    fn pre_fire(&self) -> time::Duration {
+        time::Duration::from_nanos(self.pre_fire.unwrap_or(0) as u64)
+    }
+
+    /// Feeds the lateness of a delivery the leader slept for into the
+    /// compensation, which settles where deliveries are on time on average.
+    fn compensate(&mut self, deadline: Instant, delivered_at: Instant) {
+        if let Some(pre_fire) = self.pre_fire.as_mut() {
+            let lateness = delayed_until(deadline, delivered_at);
+            *pre_fire = pre_fire.saturating_add(lateness / 8).max(0);
+        }
+    }
+
+    /// Sends `event` to every subscriber, forgetting those that are gone.
+    /// Subscribers whose buffer is full miss the event.
+    fn emit(&mut self, event: QueueEvent) {
+        if !self.observers.is_empty() {
+            self.observers.retain(|observer| {
+                !matches!(
+                    observer.try_send(event.clone()),
+                    Err(mpsc::TrySendError::Disconnected(_))
+                )
+            });
+        }
+    }
+}
+
+struct Options {
+    mode: DeadlineMode,
+    ordering: OrderingMode,
+    capacity: Option<usize>,
+    clock: Arc<dyn Clock>,
+    min_spacing: Option<time::Duration>,
+    notify_threshold: Option<time::Duration>,
+    name: Option<Arc<str>>,
+    labels: Headers,
+    compensate_lateness: bool,
+    bucket_width: Option<time::Duration>,
+    max_horizon: Option<time::Duration>,
+    coarse_timers: Option<time::Duration>,
+    chunked_views: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            mode: DeadlineMode::default(),
+            ordering: OrderingMode::default(),
+            capacity: None,
+            clock: Arc::new(SystemClock),
+            min_spacing: None,
+            notify_threshold: None,
+            name: None,
+            labels: Headers::new(),
+            compensate_lateness: false,
+            bucket_width: None,
+            max_horizon: None,
+            coarse_timers: None,
+            chunked_views: false,
+        }
+    }
+}
+
+impl<T: Delayed> DelayQueue<T> {
+    pub fn with_deadline_mode(mode: DeadlineMode) -> Self {
+        Self::new(Options {
+            mode,
+            ..Options::default()
+        })
+    }
+
+    pub fn with_ordering(ordering: OrderingMode) -> Self {
+        Self::new(Options {
+            ordering,
+            ..Options::default()
+        })
+    }
+
+    /// Creates a queue holding at most `capacity` elements; `put` blocks
+    /// while it is full.
+    pub fn bounded(capacity: usize) -> Self {
+        Self::new(Options {
+            capacity: Some(capacity),
+            ..Options::default()
+        })
+    }
+
+    /// Creates a queue that delivers at most one element per `spacing`, even
+    /// when many expire at once, e.g. to pace outbound notifications.
+    pub fn with_min_spacing(spacing: time::Duration) -> Self {
+        Self::new(Options {
+            min_spacing: Some(spacing),
+            ..Options::default()
+        })
+    }
+
+    /// Creates a queue that skips waking consumers for a new head due less
+    /// than `threshold` before the waiting consumer wakes anyway. Under heavy
+    /// insert rates this trades up to `threshold` of latency for far fewer
+    /// wakeups.
+    pub fn with_notify_coalescing(threshold: time::Duration) -> Self {
+        Self::new(Options {
+            notify_threshold: Some(threshold),
+            ..Options::default()
+        })
+    }
+
+    /// Creates a queue whose telemetry is told apart from other queues' by
+    /// `name`.
+    pub fn with_name(name: impl Into<String>) -> Self {
+        Self::new(Options {
+            name: Some(name.into().into()),
+            ..Options::default()
+        })
+    }
+
+    fn new(options: Options) -> Self {
+        let Options {
+            mode,
+            ordering,
+            capacity,
+            clock,
+            min_spacing,
+            notify_threshold,
+            name,
+            labels,
+            compensate_lateness,
+            bucket_width,
+            max_horizon,
+            coarse_timers,
+            chunked_views,
+        } = options;
+        timer::calibrate_once();
+        let version = Arc::new(AtomicU64::new(0));
+        let labels = Arc::new(labels);
+        Self {
+            queue: Arc::new(Mutex::new(DelayQueueInner {
+                queue: BinaryHeap::new(),
+                current_thread: None,
+                next_seq: 0,
+                next_delivery_seq: 0,
+                delivered: 0,
+                renotified: 0,
+                quarantined: 0,
+                capacity,
+                max_horizon,
+                reserved: 0,
+                ordering,
+                closed: false,
+                on_discard: None,
+                audit: None,
+                min_spacing,
+                last_delivery: None,
+                lateness: health::LatenessWindow::new(),
+                pre_fire: compensate_lateness.then_some(0),
+                notify_threshold,
+                coarse_timers,
+                leader_wakes_at: None,
+                observers: Vec::new(),
+                labels: Arc::clone(&labels),
+                epoch: (clock.now(), time::SystemTime::now()),
+                frozen: None,
+                buckets: bucket_width.map(|width| buckets::BucketIndex::new(clock.now(), width)),
+                chunks: chunked_views.then(chunks::ChunkedEntries::default),
+                #[cfg(feature = "failpoints")]
+                failpoints: failpoints::Failpoints::default(),
+                version: Arc::clone(&version),
+            })),
+            available: Arc::default(),
+            not_full: Arc::default(),
+            mode,
+            clock,
+            version,
+            view: Arc::new(snapshot::ViewCache::new(chunked_views)),
+            name,
+            labels,
+            diagnostics: Arc::default(),
+        }
+    }
+
+    /// The name given with [`with_name`](Self::with_name) or the builder,
+    /// attached to the queue's stats, health and log records.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Labels set with [`DelayQueueBuilder::label`], attached to the queue's
+    /// stats, health, events and log records.
+    pub fn labels(&self) -> &Headers {
+        &self.labels
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.queue.lock().capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.lock().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Time until the head expires; zero if it already has, `None` if the
+    /// queue is empty.
+    pub fn duration_until_next(&self) -> Option<time::Duration> {
+        let now = self.clock.now();
+        let guard = self.queue.lock();
+        guard
+            .peek()
+            .map(|head| head.deadline.saturating_duration_since(now))
+    }
+
+    /// Calls `f` with every element the queue drops without delivering it:
+    /// elements put after [`close`](Self::close), and the ones removed by
+    /// [`close_and_discard`](Self::close_and_discard) or a registry teardown.
+    /// Releases resources embedded in payloads deterministically, as long as
+    /// no snapshot still holds the element.
+    pub fn on_discard<F>(&self, f: F)
+    where
+        F: Fn(Arc<T>) + Send + Sync + 'static,
+    {
+        self.queue.lock().on_discard = Some(Arc::new(f));
+    }
+
+    /// Passes dropped elements to the discard hook, if any. Must be called
+    /// without holding the lock.
+    fn discard(hook: Option<DiscardHook<T>>, items: Vec<Arc<T>>) {
+        if let Some(hook) = hook {
+            items.into_iter().for_each(|item| hook(item));
+        }
+    }
+
+    /// Stops accepting new elements. Elements already queued are still
+    /// delivered; once they are gone, consumers see the queue as closed.
+    pub fn close(&self) {
+        let pending = {
+            let mut guard = self.queue.lock();
+            guard.closed = true;
+            guard.emit(QueueEvent::Closed {
+                labels: Arc::clone(&self.labels),
+            });
+            guard.queue.len()
+        };
+        self.available.notify_all();
+        self.not_full.notify_all();
+        logging::closed(self.name(), self.labels(), pending);
+        #[cfg(feature = "diagnostics")]
+        logging::diagnostics(self.name(), self.labels(), &self.diagnostics());
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.queue.lock().closed
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Sync + Send + 'static,
+{
+    /// Creates a queue that reads time from `clock`, e.g. a [`SimClock`].
+    pub fn with_clock<C: Clock>(mode: DeadlineMode, clock: C) -> Self {
+        Self::builder().deadline_mode(mode).clock(clock).build()
+    }
+
+    /// Wakes waiting consumers whenever a virtual clock advances.
+    fn subscribe_clock(&self) {
+        if self.clock.is_virtual() {
+            let inner = Arc::downgrade(&self.queue);
+            let available = Arc::downgrade(&self.available);
+            self.clock.on_advance(Box::new(move || {
+                match (inner.upgrade(), available.upgrade()) {
+                    (Some(inner), Some(available)) => {
+                        // Taking the lock orders the wakeup after any taker that
+                        // already checked the time but has not started waiting.
+                        let _guard = inner.lock();
+                        available.notify_all();
+                        true
+                    }
+                    _ => false,
+                }
+            }));
+        }
+    }
 }
 
 impl<T> DelayQueue<T>
 where
     T: Delayed + Sync + Send,
 {
+    /// Inserts `t`, blocking while a bounded queue is full.
+    ///
+    /// Blocked takers are only woken if `t` expires strictly before every
+    /// element already queued; equal deadlines never wake anyone.
+    ///
+    /// On a closed queue, or if `t` is due beyond the
+    /// [maximum horizon](DelayQueueBuilder::max_horizon), the element is
+    /// dropped; use [`try_put`](Self::try_put) to get it back instead.
     pub fn put(&mut self, t: T) {
-        let queue = self.queue.clone();
-        let queue = &mut queue.lock().queue;
-        let t = Reverse(Arc::new(t));
-        queue.push(t.clone());
-        if queue.peek() == Some(&t) {
+        let deadline = deadline_after(self.clock.now(), t.delayed());
+        self.put_blocking(deadline, false, Arc::new(t));
+    }
+
+    /// Like [`put`](Self::put), but takes an element that is already shared,
+    /// so it is not copied into a new allocation.
+    pub fn put_shared(&mut self, t: Arc<T>) {
+        let deadline = deadline_after(self.clock.now(), t.delayed());
+        self.put_blocking(deadline, false, t);
+    }
+
+    /// Like [`put_shared`](Self::put_shared), but due at `deadline` rather
+    /// than after `delayed`. Scheduling clones of one `Arc` at several
+    /// deadlines fans out a large payload without copying it.
+    ///
+    /// The deadline stays fixed in every [`DeadlineMode`].
+    pub fn put_shared_at(&mut self, deadline: Instant, t: Arc<T>) {
+        self.put_blocking(deadline, true, t);
+    }
+
+    /// Inserts `t` due at `deadline` like [`put`](Self::put) does; a
+    /// `pinned` deadline is never re-derived from `delayed`.
+    fn put_blocking(&self, deadline: Instant, pinned: bool, t: Arc<T>) {
+        let now = self.clock.now();
+        let mut guard = self.diagnostics.lock(&self.queue);
+        while guard.is_full() && !guard.closed {
+            self.not_full.wait(&mut guard);
+        }
+        if guard.closed || guard.beyond_horizon(now, deadline) {
+            let hook = guard.on_discard.clone();
+            drop(guard);
+            Self::discard(hook, vec![t]);
+            return;
+        }
+        let entry = Entry {
+            pinned,
+            ..guard.entry(deadline, false, t)
+        };
+        self.push_notify(&mut guard, entry);
+    }
+
+    /// Inserts `entry` and wakes a taker if it must be woken, unless the
+    /// `drop_notification` failpoint swallows the wakeup.
+    fn push_notify(&self, guard: &mut DelayQueueInner<T>, entry: Entry<T>) {
+        if guard.push_entry_wakes(entry) && !guard.notification_dropped() {
+            self.available.notify_one();
+        }
+    }
+
+    /// Like [`put`](Self::put), but fails instead of blocking when the queue
+    /// is full, and returns the element when the queue is closed.
+    pub fn try_put(&self, t: T) -> Result<(), PutError<T>> {
+        self.try_put_entry(t, None)
+    }
+
+    /// Like [`try_put`](Self::try_put), attaching `headers` that are handed
+    /// out again with the [`Delivery`].
+    pub fn try_put_with_headers(&self, t: T, headers: Headers) -> Result<(), PutError<T>> {
+        self.try_put_entry(t, Some(Arc::new(headers)))
+    }
+
+    fn try_put_entry(&self, t: T, headers: Option<Arc<Headers>>) -> Result<(), PutError<T>> {
+        let now = self.clock.now();
+        let deadline = deadline_after(now, t.delayed());
+        let mut guard = self.diagnostics.lock(&self.queue);
+        if guard.closed {
+            return Err(PutError::Closed(t));
+        }
+        if guard.beyond_horizon(now, deadline) {
+            return Err(PutError::DeadlineTooFar(t));
+        }
+        if guard.is_full() {
+            let capacity = guard.capacity;
+            drop(guard);
+            logging::overflow(self.name(), self.labels(), capacity);
+            return Err(PutError::Full(t));
+        }
+        let entry = Entry {
+            headers,
+            ..guard.entry(deadline, false, Arc::new(t))
+        };
+        self.push_notify(&mut guard, entry);
+        Ok(())
+    }
+
+    /// Puts a delivered element back for its next firing, e.g. a recurring
+    /// job whose `delayed` now points at its next run.
+    ///
+    /// The element and its headers are reused rather than reallocated, and
+    /// heap slots are kept inline, so a recurring schedule costs no
+    /// allocation per firing. Fails like [`try_put`](Self::try_put).
+    pub fn reschedule(&self, delivery: Delivery<T>) -> Result<(), PutError<Delivery<T>>> {
+        let now = self.clock.now();
+        let deadline = deadline_after(now, delivery.item.delayed());
+        let mut guard = self.diagnostics.lock(&self.queue);
+        if guard.closed {
+            return Err(PutError::Closed(delivery));
+        }
+        if guard.beyond_horizon(now, deadline) {
+            return Err(PutError::DeadlineTooFar(delivery));
+        }
+        if guard.is_full() {
+            let capacity = guard.capacity;
+            drop(guard);
+            logging::overflow(self.name(), self.labels(), capacity);
+            return Err(PutError::Full(delivery));
+        }
+        let entry = Entry {
+            headers: delivery.headers,
+            ..guard.entry(deadline, false, delivery.item)
+        };
+        self.push_notify(&mut guard, entry);
+        Ok(())
+    }
+
+    /// Schedules `t` for immediate delivery, ahead of every element that has
+    /// already expired. Urgent elements are delivered in insertion order.
+    pub fn put_now(&self, t: T) {
+        let mut guard = self.diagnostics.lock(&self.queue);
+        while guard.is_full() && !guard.closed {
+            self.not_full.wait(&mut guard);
+        }
+        if guard.closed {
+            let hook = guard.on_discard.clone();
+            drop(guard);
+            Self::discard(hook, vec![Arc::new(t)]);
+            return;
+        }
+        guard.push(self.clock.now(), true, Arc::new(t));
+        if !guard.notification_dropped() {
             self.available.notify_one();
         }
     }
 
+    /// Closes the queue and returns every element not delivered yet, in
+    /// delivery order.
+    pub fn close_and_drain(&self) -> Vec<Arc<T>> {
+        self.close_and_drain_entries()
+            .into_iter()
+            .map(|entry| entry.item)
+            .collect()
+    }
+
+    fn close_and_drain_entries(&self) -> Vec<Entry<T>> {
+        let mut entries = {
+            let mut guard = self.queue.lock();
+            guard.closed = true;
+            guard.emit(QueueEvent::Closed {
+                labels: Arc::clone(&self.labels),
+            });
+            guard.drain()
+        };
+        self.available.notify_all();
+        self.not_full.notify_all();
+        logging::closed(self.name(), self.labels(), entries.len());
+        entries.sort();
+        entries
+    }
+
+    /// Closes the queue and drops every element not delivered yet, returning
+    /// how many were dropped.
+    pub fn close_and_discard(&self) -> usize {
+        let hook = self.queue.lock().on_discard.clone();
+        let items = self.close_and_drain();
+        let discarded = items.len();
+        Self::discard(hook, items);
+        discarded
+    }
+
+    /// Drops every element not delivered yet without closing the queue,
+    /// passing them to the [`on_discard`](Self::on_discard) hook, and returns
+    /// how many were dropped.
+    pub fn clear(&self) -> usize {
+        self.clear_as(None)
+    }
+
+    pub(crate) fn clear_as(&self, actor: Option<&str>) -> usize {
+        let (hook, entries) = {
+            let mut guard = self.queue.lock();
+            (guard.on_discard.clone(), guard.drain())
+        };
+        self.not_full.notify_all();
+        let cleared = entries.len();
+        Self::discard(hook, entries.into_iter().map(|entry| entry.item).collect());
+        self.audit(actor, AdminAction::Clear { removed: cleared });
+        cleared
+    }
+
+    /// Recomputes every cached deadline from `delayed` and rebuilds the heap.
+    ///
+    /// Required after mutating the deadline of an element that is already in
+    /// the queue. Waiting consumers are woken to recompute their timeouts.
+    pub fn revalidate(&self) {
+        let items = {
+            let guard = self.queue.lock();
+            guard
+                .queue
+                .iter()
+                .filter(|entry| !entry.0.urgent)
+                .map(|entry| (entry.0.seq, entry.0.shift, Arc::clone(&entry.0.item)))
+                .collect::<Vec<_>>()
+        };
+        let now = self.clock.now();
+        let deadlines = items
+            .into_iter()
+            .map(|(seq, shift, item)| {
+                let delayed = item.delayed().saturating_add(shift);
+                (seq, deadline_after(now, delayed))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut guard = self.queue.lock();
+        let mut entries = std::mem::take(&mut guard.queue).into_vec();
+        for entry in entries.iter_mut() {
+            if let Some(deadline) = deadlines.get(&entry.0.seq) {
+                entry.0.deadline = *deadline;
+            }
+        }
+        guard.queue = BinaryHeap::from(entries);
+        guard.reindex();
+        guard.touch();
+        self.available.notify_all();
+    }
+
+    /// Moves the deadline of every pending element `by` later, atomically.
+    ///
+    /// Elements inserted with [`put_now`](Self::put_now) are not affected.
+    pub fn shift_deadlines_later(&self, by: time::Duration) {
+        self.shift_deadlines(by, true, None);
+    }
+
+    /// Moves the deadline of every pending element `by` earlier, atomically.
+    ///
+    /// Elements inserted with [`put_now`](Self::put_now) are not affected.
+    pub fn shift_deadlines_earlier(&self, by: time::Duration) {
+        self.shift_deadlines(by, false, None);
+    }
+
+    pub(crate) fn shift_deadlines(&self, by: time::Duration, later: bool, actor: Option<&str>) {
+        let nanos = by.as_nanos().min(i64::MAX as u128) as i64;
+        let mut guard = self.queue.lock();
+        // Nothing below can panic, so the heap is always put back.
+        let mut entries = std::mem::take(&mut guard.queue).into_vec();
+        for Reverse(entry) in entries.iter_mut().filter(|entry| !entry.0.urgent) {
+            if later {
+                entry.deadline = instant_after(entry.deadline, by);
+                entry.shift = entry.shift.saturating_add(nanos);
+            } else {
+                entry.deadline = entry.deadline.checked_sub(by).unwrap_or(entry.deadline);
+                entry.shift = entry.shift.saturating_sub(nanos);
+            }
+        }
+        guard.queue = BinaryHeap::from(entries);
+        guard.reindex();
+        guard.touch();
+        drop(guard);
+        self.available.notify_all();
+        let action = if later {
+            AdminAction::ShiftLater { by }
+        } else {
+            AdminAction::ShiftEarlier { by }
+        };
+        self.audit(actor, action);
+    }
+
+    /// Inserts `t` with an explicit deadline instead of one derived from
+    /// `delayed`, ignoring the capacity. Returns `false` on a closed queue.
+    pub(crate) fn put_at(&self, deadline: Instant, t: Arc<T>) -> bool {
+        let mut guard = self.queue.lock();
+        if guard.closed {
+            return false;
+        }
+        let entry = guard.pinned_entry(deadline, t);
+        if guard.push_entry_wakes(entry) {
+            self.available.notify_one();
+        }
+        true
+    }
+
+    /// Removes every pending element matching `pred`, in O(n).
+    pub(crate) fn remove_where<F: FnMut(&T) -> bool>(&self, mut pred: F) -> Vec<Arc<T>> {
+        let mut guard = self.queue.lock();
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut guard.queue)
+            .into_vec()
+            .into_iter()
+            .partition(|entry| pred(&entry.0.item));
+        guard.queue = BinaryHeap::from(kept);
+        if removed.is_empty() {
+            return Vec::new();
+        }
+        guard.reindex();
+        guard.touch();
+        self.not_full.notify_all();
+        removed.into_iter().map(|entry| entry.0.item).collect()
+    }
+
+    /// Sequence number the next delivery will carry.
+    pub fn next_delivery_seq(&self) -> u64 {
+        self.queue.lock().next_delivery_seq
+    }
+
+    /// Continues delivery numbering at `next`, e.g. after crash recovery.
+    pub fn resume_delivery_seq(&self, next: u64) {
+        self.queue.lock().next_delivery_seq = next;
+    }
+
+    /// Blocks until an element expires and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the queue is closed and empty; use
+    /// [`take_or_closed`](Self::take_or_closed) where that can happen.
     pub fn take(&mut self) -> Arc<T> {
-        let queue = self.queue.clone();
-        let avaliable = self.available.clone();
-        let mut guard = queue.lock();
+        self.take_delivery().into_item()
+    }
+
+    /// Like [`take`](Self::take), but returns `None` once the queue is closed
+    /// and every remaining element has been delivered.
+    pub fn take_or_closed(&self) -> Option<Arc<T>> {
+        self.take_before(None, None).map(Delivery::into_item)
+    }
+
+    /// Like [`take`](Self::take), but also returns the delivery metadata.
+    pub fn take_delivery(&self) -> Delivery<T> {
+        self.take_before(None, None)
+            .expect("take on a closed and empty DelayQueue")
+    }
+
+    /// Waits for an expired element until `cutoff`, measured on the queue's
+    /// clock. Returns `None` once `cutoff` has passed, or the queue is closed
+    /// and empty.
+    pub fn take_until(&self, cutoff: Instant) -> Option<Arc<T>> {
+        self.take_delivery_until(cutoff).map(Delivery::into_item)
+    }
+
+    /// Like [`take_until`](Self::take_until), but also returns the delivery
+    /// metadata.
+    pub fn take_delivery_until(&self, cutoff: Instant) -> Option<Delivery<T>> {
+        self.take_before(Some(cutoff), None)
+    }
+
+    /// Waits at most `timeout` for an expired element. Returns `None` once
+    /// the timeout has passed, or the queue is closed and empty.
+    pub fn take_timeout(&self, timeout: time::Duration) -> Option<Arc<T>> {
+        let cutoff = self.clock.now().checked_add(timeout);
+        self.take_before(cutoff, None).map(Delivery::into_item)
+    }
+
+    /// Returns an element if one has already expired, without blocking, e.g.
+    /// to poll the queue from an event loop.
+    pub fn try_take(&self) -> Option<Arc<T>> {
+        self.try_take_delivery().map(Delivery::into_item)
+    }
+
+    /// Like [`try_take`](Self::try_take), but also returns the delivery
+    /// metadata.
+    pub fn try_take_delivery(&self) -> Option<Delivery<T>> {
+        self.take_expired()
+    }
+
+    /// Passes every element that has already expired to `f` without
+    /// blocking, for callers that cannot spare a thread to wait in
+    /// [`take`](Self::take). Returns how many elements were processed.
+    ///
+    /// Elements expiring while `f` runs are left for the next call.
+    pub fn run_pending<F: FnMut(Arc<T>)>(&self, mut f: F) -> usize {
+        let started = self.clock.now();
+        let mut processed = 0;
+        while let Some(delivery) = self.take_expired() {
+            let late = delivery.deadline > started;
+            f(delivery.item);
+            processed += 1;
+            if late {
+                break;
+            }
+        }
+        processed
+    }
+
+    /// Like [`take_or_closed`](Self::take_or_closed), but also gives up once
+    /// `stop` is set. Whoever sets it must call [`wake_all`](Self::wake_all).
+    pub(crate) fn take_unless(&self, stop: &AtomicBool) -> Option<Delivery<T>> {
+        self.take_before(None, Some(stop))
+    }
+
+    /// Wakes every blocked taker so that it rechecks its stop condition.
+    pub(crate) fn wake_all(&self) {
+        drop(self.queue.lock());
+        self.available.notify_all();
+    }
+
+    fn take_before(
+        &self,
+        cutoff: Option<Instant>,
+        stop: Option<&AtomicBool>,
+    ) -> Option<Delivery<T>> {
+        let mut guard = self.diagnostics.lock(&self.queue);
+        let mut timed_out = false;
+        let mut woken = None;
         loop {
-            match guard.peek() {
-                None => {
-                    avaliable.wait(&mut guard);
+            if stop.is_some_and(|stop| stop.load(AtomicOrdering::SeqCst)) {
+                if guard.current_thread.is_none() && !guard.queue.is_empty() {
+                    self.available.notify_one();
                 }
-                Some(first) => {
-                    let delayed = first.delayed();
-                    if delayed <= 0 {
-                        let result = guard.queue.pop().unwrap();
-                        if guard.current_thread.is_none() && guard.peek().is_some() {
-                            avaliable.notify_one();
-                        }
-                        return result.0;
+                return None;
+            }
+            let deadline = match self.poll_head(&mut guard) {
+                Head::Ready(delivery) => {
+                    if timed_out {
+                        guard.compensate(delivery.deadline, delivery.delivered_at);
                     }
-                    let _ = first;
-                    match guard.current_thread {
-                        Some(_) => {
-                            avaliable.wait(&mut guard);
-                        }
-                        None => {
-                            let thread_id = std::thread::current().id();
-                            guard.current_thread = Some(thread_id);
-                            avaliable
-                                .wait_for(&mut guard, time::Duration::from_nanos(delayed as u64));
-                            if guard.current_thread == Some(thread_id) {
-                                guard.current_thread = None
-                            }
+                    if let Some(delay) = self.hand_out(guard, &delivery) {
+                        std::thread::sleep(delay);
+                    }
+                    return Some(delivery);
+                }
+                Head::Empty if guard.closed => return None,
+                Head::Empty => None,
+                Head::Pending(deadline) => Some(deadline),
+            };
+            self.diagnostics.woke_for_nothing(woken.take());
+            if std::mem::take(&mut timed_out) && deadline.is_some() {
+                // The leader's timer fired but the head is not deliverable;
+                // don't rely on this thread alone to watch it.
+                guard.renotified += 1;
+                self.available.notify_one();
+            }
+            if cutoff.is_some_and(|cutoff| cutoff <= self.clock.now()) {
+                // Followers wait without a timeout, so hand leadership on.
+                if guard.current_thread.is_none() && deadline.is_some() {
+                    self.available.notify_one();
+                }
+                return None;
+            }
+            match deadline {
+                Some(deadline) if guard.current_thread.is_none() => {
+                    let thread_id = std::thread::current().id();
+                    guard.current_thread = Some(thread_id);
+                    let wake_at = guard.wake_for(self.clock.now(), deadline);
+                    let wake_at = cutoff.map_or(wake_at, |cutoff| cutoff.min(wake_at));
+                    guard.leader_wakes_at = Some(wake_at);
+                    timed_out = self.wait(&mut guard, Some(wake_at));
+                    woken = Some(Wakeup::Leader);
+                    if guard.current_thread == Some(thread_id) {
+                        guard.current_thread = None;
+                        guard.leader_wakes_at = None;
+                    }
+                }
+                _ => {
+                    if !self.wait(&mut guard, cutoff) {
+                        woken = Some(Wakeup::Follower);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns whether the wait ended because `until` passed.
+    ///
+    /// With a calibrated timer the wait ends early by the measured resolution
+    /// and spins, without the lock, for the rest.
+    fn wait(&self, guard: &mut MutexGuard<'_, DelayQueueInner<T>>, until: Option<Instant>) -> bool {
+        match until {
+            Some(until) if !self.clock.is_virtual() => {
+                let margin = timer::resolution().unwrap_or_default();
+                let early = until.checked_sub(margin).unwrap_or(until);
+                let timed_out = self.available.wait_until(guard, early).timed_out();
+                if timed_out && early < until {
+                    MutexGuard::unlocked(guard, || {
+                        while self.clock.now() < until {
+                            std::hint::spin_loop();
                         }
+                    });
+                }
+                timed_out
+            }
+            _ => {
+                self.available.wait(guard);
+                false
+            }
+        }
+    }
+
+    /// Delivers the head if it has expired, without blocking.
+    pub(crate) fn take_expired(&self) -> Option<Delivery<T>> {
+        self.poll_expired().ok()
+    }
+
+    /// Like [`take_expired`](Self::take_expired), but tells when the head
+    /// is due if it has not expired yet; `None` if the queue is empty.
+    pub(crate) fn poll_expired(&self) -> Result<Delivery<T>, Option<Instant>> {
+        let (delivery, delay) = self.poll_delivery()?;
+        if let Some(delay) = delay {
+            std::thread::sleep(delay);
+        }
+        Ok(delivery)
+    }
+
+    /// Like [`poll_expired`](Self::poll_expired), but leaves holding on to
+    /// the delivery for the returned delay to the caller, e.g. to wait for it
+    /// without blocking a thread.
+    pub(crate) fn poll_delivery(
+        &self,
+    ) -> Result<(Delivery<T>, Option<time::Duration>), Option<Instant>> {
+        let mut guard = self.diagnostics.lock(&self.queue);
+        match self.poll_head(&mut guard) {
+            Head::Ready(delivery) => {
+                let delay = self.hand_out(guard, &delivery);
+                Ok((delivery, delay))
+            }
+            Head::Empty => Err(None),
+            Head::Pending(due) => Err(Some(due)),
+        }
+    }
+
+    /// Finishes a delivery made by `poll_head` once the lock is released.
+    /// Returns how long the taker holds on to it before returning it, as
+    /// injected by the `delay_delivery` failpoint.
+    fn hand_out(
+        &self,
+        guard: MutexGuard<'_, DelayQueueInner<T>>,
+        delivery: &Delivery<T>,
+    ) -> Option<time::Duration> {
+        let delay = guard.delivery_delay();
+        drop(guard);
+        self.drop_stale_view();
+        logging::delivered(self.name(), self.labels(), delivery);
+        delay
+    }
+
+    fn poll_head(&self, guard: &mut MutexGuard<'_, DelayQueueInner<T>>) -> Head<T> {
+        loop {
+            let first = match guard.peek() {
+                None => return Head::Empty,
+                Some(first) => first,
+            };
+            let now = self.clock.now();
+            let spaced = match (guard.last_delivery, guard.min_spacing) {
+                (Some(last), Some(spacing)) => last + spacing,
+                _ => now,
+            };
+            let pre_fire = guard.pre_fire();
+            if first.deadline > now + pre_fire || spaced > now {
+                let due = first.deadline.checked_sub(pre_fire).unwrap_or(now);
+                return Head::Pending(due.max(spaced));
+            }
+            if self.mode == DeadlineMode::Dynamic && !first.urgent && !first.pinned {
+                let (seq, shift, item) = (first.seq, first.shift, Arc::clone(&first.item));
+                let delayed = MutexGuard::unlocked(guard, || item.delayed().saturating_add(shift));
+                if guard.peek().map(|head| head.seq) != Some(seq) {
+                    continue;
+                }
+                if delayed > pre_fire.as_nanos() as i64 {
+                    let entry = guard.pop().unwrap();
+                    let entry = Entry {
+                        deadline: deadline_after(self.clock.now(), delayed),
+                        ..entry
+                    };
+                    if let Some(buckets) = guard.buckets.as_mut() {
+                        buckets.insert(entry.deadline);
+                    }
+                    if let Some(chunks) = guard.chunks.as_mut() {
+                        chunks.insert(entry.clone());
                     }
+                    guard.queue.push(Reverse(entry));
+                    continue;
                 }
             }
+            let result = guard.pop().unwrap();
+            let seq = guard.next_delivery_seq;
+            guard.next_delivery_seq += 1;
+            guard.delivered += 1;
+            let delivered_at = self.clock.now();
+            guard.emit(QueueEvent::Delivered {
+                seq,
+                deadline: result.deadline,
+                labels: Arc::clone(&self.labels),
+            });
+            guard.last_delivery = Some(delivered_at);
+            let lateness = delivered_at.saturating_duration_since(result.deadline);
+            guard.lateness.record(delivered_at, lateness);
+            self.not_full.notify_all();
+            if guard.current_thread.is_none() && guard.peek().is_some() {
+                self.available.notify_one();
+            }
+            return Head::Ready(Delivery {
+                item: result.item,
+                seq,
+                deadline: result.deadline,
+                delivered_at,
+                headers: result.headers,
+            });
         }
     }
 }
 
+enum Head<T> {
+    Empty,
+    Pending(Instant),
+    Ready(Delivery<T>),
+}
+
 #[cfg(test)]
 mod test {
-    use std::collections::HashMap;
+    use std::{collections::HashMap, io::Write, thread};
 
     use chrono::{DateTime, Duration, Local};
 
     use super::*;
+    use crate::testing::Fixed;
     #[test]
     fn test() {
         #[derive(Default, Debug, PartialEq, Eq)]
@@ -112,7 +1504,7 @@ mod test {
 
         impl Delayed for Task {
             fn delayed(&self) -> i64 {
-                self.deadline - chrono::Local::now().timestamp_nanos()
+                self.deadline - chrono::Local::now().timestamp_nanos_opt().unwrap()
             }
         }
 
@@ -124,7 +1516,7 @@ mod test {
 
         impl PartialOrd for Task {
             fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-                Some(self.deadline.cmp(&other.deadline))
+                Some(self.cmp(other))
             }
         }
 
@@ -138,7 +1530,9 @@ mod test {
                 for index in 0..TOTAL_COUNT {
                     let v = rand::random::<u64>() % 10000;
                     queue.put(Task::new(
-                        after(Duration::milliseconds(v as i64)).timestamp_nanos(),
+                        after(Duration::milliseconds(v as i64))
+                            .timestamp_nanos_opt()
+                            .unwrap(),
                         format!("index: {}. delay for {}ms", index, v),
                     ));
                 }
@@ -153,7 +1547,7 @@ mod test {
                     for _i in 0..TOTAL_COUNT / THREAD_COUNT {
                         let task = queue.take();
                         let now = chrono::Local::now();
-                        let diff = (now.timestamp_nanos() - task.deadline) / 1000;
+                        let diff = (now.timestamp_nanos_opt().unwrap() - task.deadline) / 1000;
                         if diff <= 100 {
                             *map.entry(100).or_default() += 1;
                         } else if diff <= 200 {
@@ -205,6 +1599,531 @@ mod test {
         assert_eq!(1000, result_count);
     }
 
+    #[test]
+    fn test_revalidate() {
+        use std::sync::atomic::{AtomicI64, Ordering};
+
+        #[derive(Debug)]
+        struct Mutable(AtomicI64);
+
+        impl Delayed for Mutable {
+            fn delayed(&self) -> i64 {
+                self.0.load(Ordering::SeqCst) - Local::now().timestamp_nanos_opt().unwrap()
+            }
+        }
+
+        impl PartialEq for Mutable {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == std::cmp::Ordering::Equal
+            }
+        }
+
+        impl Eq for Mutable {}
+
+        impl Ord for Mutable {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.0
+                    .load(Ordering::SeqCst)
+                    .cmp(&other.0.load(Ordering::SeqCst))
+            }
+        }
+
+        impl PartialOrd for Mutable {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        let deadline = |du: Duration| after(du).timestamp_nanos_opt().unwrap();
+        let mut queue = DelayQueue::<Mutable>::default();
+        queue.put(Mutable(AtomicI64::new(deadline(Duration::hours(1)))));
+        queue.put(Mutable(AtomicI64::new(deadline(Duration::hours(2)))));
+
+        let last = {
+            let guard = queue.queue.lock();
+            let last = guard.queue.iter().max_by(|a, b| a.0.cmp(&b.0)).unwrap();
+            Arc::clone(&last.0.item)
+        };
+        last.0
+            .store(deadline(Duration::hours(-1)), Ordering::SeqCst);
+        queue.revalidate();
+
+        let taken = queue.take();
+        assert!(Arc::ptr_eq(&taken, &last));
+    }
+
+    #[test]
+    fn test_captured_deadline() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        // Always reports the same remaining delay, so it never expires unless
+        // the deadline is captured at insert.
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Stubborn(i64);
+
+        impl Delayed for Stubborn {
+            fn delayed(&self) -> i64 {
+                CALLS.fetch_add(1, Ordering::SeqCst);
+                self.0
+            }
+        }
+
+        let mut queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        queue.put(Stubborn(1_000_000));
+        assert_eq!(Stubborn(1_000_000), *queue.take());
+        assert_eq!(1, CALLS.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_put_now() {
+        let mut queue = DelayQueue::default();
+        queue.put(Fixed(-2));
+        queue.put(Fixed(-1));
+        queue.put_now(Fixed(10));
+        queue.put_now(Fixed(5));
+        let taken = (0..4).map(|_| queue.take().0).collect::<Vec<_>>();
+        assert_eq!(vec![10, 5, -2, -1], taken);
+    }
+
+    #[test]
+    fn test_prepare_commit() {
+        let mut queue = DelayQueue::bounded(2);
+        let prepared = queue.prepare(Fixed(-1)).unwrap();
+        queue.put(Fixed(-2));
+        assert_eq!(Err(PutError::Full(Fixed(-3))), queue.try_put(Fixed(-3)));
+        assert_eq!(1, queue.len());
+
+        assert_eq!(Fixed(-2), *queue.take());
+        let aborted = queue.prepare(Fixed(-4)).unwrap();
+        assert_eq!(Fixed(-4), queue.abort(aborted));
+        drop(queue.prepare(Fixed(-5)).unwrap());
+
+        queue.commit(prepared);
+        assert_eq!(Fixed(-1), *queue.take());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_streaming() {
+        let mut queue = DelayQueue::default();
+        for delay in [3_000_000_000, 1_000_000_000, 2_000_000_000] {
+            queue.put(Fixed(delay));
+        }
+        let mut out = Vec::new();
+        let written = queue
+            .snapshot_streaming(&mut out, |w, item| writeln!(w, "{}", item.0))
+            .unwrap();
+        assert_eq!(3, written);
+        assert_eq!(
+            "1000000000\n2000000000\n3000000000\n",
+            String::from_utf8(out).unwrap()
+        );
+        assert_eq!(3, queue.snapshot().len());
+
+        let view = queue.view();
+        assert_eq!(view.version(), queue.view().version());
+        queue.put(Fixed(0));
+        assert_ne!(view.version(), queue.view().version());
+        assert_eq!(3, view.len());
+        assert_eq!(
+            Some(&Fixed(0)),
+            queue.view().iter().next().map(|item| &**item)
+        );
+
+        let mut queue = DelayQueue::default();
+        let payload = Arc::new(Fixed(-1));
+        queue.put_shared(Arc::clone(&payload));
+        drop(queue.view());
+        assert_eq!(3, Arc::strong_count(&payload));
+        drop(queue.take());
+        assert_eq!(1, Arc::strong_count(&payload));
+    }
+
+    #[test]
+    fn test_snapshot_stream() {
+        let queue = DelayQueue::default();
+        for i in 0..3000 {
+            queue
+                .try_put(Fixed(3_600_000_000_000 + i * 1_000_000_000))
+                .unwrap();
+        }
+        let producer = {
+            let queue = queue.clone();
+            thread::spawn(move || {
+                for i in 0..3000 {
+                    queue
+                        .try_put(Fixed(3_600_000_000_000 - (i + 1) * 1_000_000_000))
+                        .unwrap();
+                }
+            })
+        };
+        while !producer.is_finished() {
+            let stream = queue.snapshot_stream();
+            let len = stream.len();
+            let items = stream.map(|item| item.0).collect::<Vec<_>>();
+            assert_eq!(len, items.len());
+            assert!(items.len() >= 3000);
+            // A slice copied after a concurrent insert would break the order
+            // or repeat elements.
+            assert!(items.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+        producer.join().unwrap();
+        assert_eq!(6000, queue.snapshot_stream().count());
+    }
+
+    #[test]
+    fn test_chunked_views() {
+        let mut queue = DelayQueue::builder()
+            .chunked_views(true)
+            .bucket_index(time::Duration::from_secs(1))
+            .build();
+        for i in 0..300 {
+            queue.put(Fixed(if i % 3 == 0 { -i } else { 1_000_000_000 * i }));
+        }
+        let before = queue.view();
+        assert_eq!(300, before.len());
+        assert!(before.iter().eq(queue.snapshot().iter()));
+
+        let taken = std::iter::from_fn(|| queue.take_expired()).count();
+        assert_eq!(100, taken);
+        queue.shift_deadlines_later(time::Duration::from_secs(1));
+        let after = queue.view();
+        assert_eq!(200, after.len());
+        assert!(after.iter().eq(queue.snapshot().iter()));
+        assert_ne!(before.version(), after.version());
+        // The earlier view is unaffected by the changes.
+        assert_eq!(300, before.iter().count());
+        assert_eq!(Some(&Fixed(0)), before.iter().next().map(|item| &**item));
+    }
+
+    #[test]
+    fn test_shift_deadlines() {
+        let mut queue = DelayQueue::default();
+        queue.put(Fixed(3_600_000_000_000));
+        queue.put(Fixed(-1));
+        queue.shift_deadlines_later(time::Duration::from_secs(3600));
+        queue.shift_deadlines_earlier(time::Duration::from_secs(7200));
+        assert_eq!(Fixed(-1), *queue.take());
+        assert_eq!(Fixed(3_600_000_000_000), *queue.take());
+    }
+
+    #[test]
+    fn test_shift_deadlines_saturates() {
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        queue.try_put(Fixed(1_000_000_000)).unwrap();
+        queue.put_now(Fixed(0));
+        queue.shift_deadlines_later(time::Duration::MAX);
+        queue.shift_deadlines_later(time::Duration::MAX);
+        assert_eq!(2, queue.len());
+        assert_eq!(Some(Fixed(0)), queue.try_take().map(|item| Fixed(item.0)));
+        assert_eq!(None, queue.try_take());
+        queue.shift_deadlines_earlier(time::Duration::MAX);
+        assert_eq!(1, queue.len());
+    }
+
+    #[test]
+    fn test_sim_clock() {
+        let clock = SimClock::new();
+        let mut queue = DelayQueue::with_clock(DeadlineMode::Captured, clock.clone());
+        queue.put(Fixed(1_000_000_000));
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let taker = {
+            let mut queue = queue.clone();
+            std::thread::spawn(move || tx.send(queue.take()).unwrap())
+        };
+        let wait = time::Duration::from_millis(50);
+        assert!(rx.recv_timeout(wait).is_err());
+        clock.advance(time::Duration::from_millis(999));
+        assert!(rx.recv_timeout(wait).is_err());
+        clock.advance(time::Duration::from_millis(1));
+        assert_eq!(Fixed(1_000_000_000), *rx.recv().unwrap());
+        taker.join().unwrap();
+    }
+
+    #[test]
+    fn test_ordering_modes() {
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Tied(u32);
+
+        impl Delayed for Tied {
+            fn delayed(&self) -> i64 {
+                0
+            }
+        }
+
+        // A frozen clock gives every element the same deadline, so only the
+        // ordering mode decides between them.
+        let deliveries = |ordering: OrderingMode| {
+            let mut queue = DelayQueue::new(Options {
+                ordering,
+                clock: Arc::new(SimClock::new()),
+                ..Options::default()
+            });
+            for key in [3, 1, 2] {
+                queue.put(Tied(key));
+            }
+            (0..3).map(|_| queue.take().0).collect::<Vec<_>>()
+        };
+        assert_eq!(vec![1, 2, 3], deliveries(OrderingMode::StrictDeadline));
+        assert_eq!(vec![3, 1, 2], deliveries(OrderingMode::DeadlineThenFifo));
+        let mut relaxed = deliveries(OrderingMode::Relaxed);
+        relaxed.sort_unstable();
+        assert_eq!(vec![1, 2, 3], relaxed);
+    }
+
+    #[test]
+    fn test_take_until() {
+        let queue = DelayQueue::default();
+        queue.put_now(Fixed(0));
+        queue.try_put(Fixed(3_600_000_000_000)).unwrap();
+        let cutoff = Instant::now() + time::Duration::from_millis(20);
+        assert_eq!(
+            Some(Fixed(0)),
+            queue.take_until(cutoff).map(|item| Fixed(item.0))
+        );
+        assert_eq!(None, queue.take_until(cutoff));
+        assert!(Instant::now() >= cutoff);
+    }
+
+    #[test]
+    fn test_run_pending() {
+        let queue = DelayQueue::default();
+        queue.try_put(Fixed(-2_000_000_000)).unwrap();
+        queue.try_put(Fixed(3_600_000_000_000)).unwrap();
+        queue.try_put(Fixed(-1_000_000_000)).unwrap();
+        let mut taken = Vec::new();
+        assert_eq!(2, queue.run_pending(|item| taken.push(item.0)));
+        assert_eq!(vec![-2_000_000_000, -1_000_000_000], taken);
+        assert_eq!(0, queue.run_pending(|_| unreachable!()));
+        assert_eq!(1, queue.len());
+    }
+
+    #[test]
+    fn test_try_take() {
+        let queue = DelayQueue::default();
+        assert_eq!(None, queue.try_take());
+        queue.try_put(Fixed(3_600_000_000_000)).unwrap();
+        queue.try_put(Fixed(-1)).unwrap();
+        assert_eq!(Some(Fixed(-1)), queue.try_take().map(|item| Fixed(item.0)));
+        assert_eq!(None, queue.try_take());
+        assert_eq!(1, queue.len());
+    }
+
+    #[test]
+    fn test_take_timeout() {
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let started = Instant::now();
+        assert_eq!(None, queue.take_timeout(time::Duration::from_millis(10)));
+        assert!(started.elapsed() >= time::Duration::from_millis(10));
+
+        queue.try_put(Fixed(10_000_000)).unwrap();
+        queue.try_put(Fixed(3_600_000_000_000)).unwrap();
+        let taken = queue.take_timeout(time::Duration::from_secs(5));
+        assert_eq!(Some(10_000_000), taken.map(|item| item.0));
+        assert_eq!(None, queue.take_timeout(time::Duration::ZERO));
+    }
+
+    #[test]
+    fn test_min_spacing() {
+        let mut queue = DelayQueue::with_min_spacing(time::Duration::from_millis(30));
+        let started = Instant::now();
+        for _ in 0..3 {
+            queue.put(Fixed(-1));
+        }
+        assert_eq!(1, queue.run_pending(drop));
+        queue.take();
+        queue.take();
+        assert!(started.elapsed() >= time::Duration::from_millis(60));
+    }
+
+    #[test]
+    fn test_notify_coalescing() {
+        let mut queue = DelayQueue::with_notify_coalescing(time::Duration::from_millis(500));
+        queue.put(Fixed(200_000_000));
+        let taker = {
+            let mut queue = queue.clone();
+            std::thread::spawn(move || {
+                let started = Instant::now();
+                (queue.take().0, started.elapsed())
+            })
+        };
+        std::thread::sleep(time::Duration::from_millis(20));
+        // Due well within the threshold of the waiting head: no wakeup, so it
+        // is only seen once the taker wakes for the old head.
+        queue.put(Fixed(0));
+        let (taken, waited) = taker.join().unwrap();
+        assert_eq!(0, taken);
+        assert!(waited >= time::Duration::from_millis(150));
+    }
+
+    #[test]
+    fn test_renotify_on_spurious_expiry() {
+        // Never expires on recheck, so every timed wait of the leader ends
+        // without a delivery.
+        let queue = DelayQueue::default();
+        queue.try_put(Fixed(10_000_000)).unwrap();
+        let cutoff = Instant::now() + time::Duration::from_millis(50);
+        assert!(queue.take_until(cutoff).is_none());
+        assert!(queue.stats().renotified >= 1);
+    }
+
+    #[test]
+    fn test_head_check_by_deadline() {
+        let queue = DelayQueue::<Fixed>::default();
+        let mut guard = queue.queue.lock();
+        let now = Instant::now();
+        let later = now + time::Duration::from_secs(1);
+        let mut push = |deadline, item| {
+            let entry = guard.entry(deadline, false, Arc::new(Fixed(item)));
+            guard.push_entry_wakes(entry)
+        };
+        assert!(push(later, 1));
+        // Equal elements and equal deadlines are no new head.
+        assert!(!push(later, 1));
+        assert!(!push(later, 0));
+        assert!(push(now, 1));
+    }
+
+    #[test]
+    fn test_lateness_compensation() {
+        let queue = DelayQueue::builder()
+            .deadline_mode(DeadlineMode::Captured)
+            .lateness_compensation(true)
+            .build();
+        let now = Instant::now();
+        queue
+            .queue
+            .lock()
+            .compensate(now, now + time::Duration::from_millis(80));
+        assert_eq!(time::Duration::from_millis(10), queue.stats().pre_fire);
+
+        queue.try_put(Fixed(5_000_000)).unwrap();
+        assert!(queue.take_expired().is_some());
+        queue.queue.lock().compensate(now, now);
+        assert_eq!(time::Duration::from_millis(10), queue.stats().pre_fire);
+        queue
+            .queue
+            .lock()
+            .compensate(now + time::Duration::from_secs(1), now);
+        assert_eq!(time::Duration::ZERO, queue.stats().pre_fire);
+    }
+
+    #[test]
+    fn test_reserve_slots() {
+        let mut queue = DelayQueue::bounded(3);
+        queue.put(Fixed(-1));
+        let mut reservation = queue.reserve_slots(2);
+        assert_eq!(Err(PutError::Full(Fixed(-2))), queue.try_put(Fixed(-2)));
+        reservation.put(Fixed(-3)).unwrap();
+        assert_eq!(1, reservation.remaining());
+        drop(reservation);
+        queue.wait_for_capacity();
+        queue.try_put(Fixed(-4)).unwrap();
+        assert_eq!(3, queue.len());
+    }
+
+    #[test]
+    fn test_close_and_drain() {
+        let mut queue = DelayQueue::default();
+        queue.put(Fixed(-1_000_000_000));
+        queue.put(Fixed(3_600_000_000_000));
+        queue.put(Fixed(-2_000_000_000));
+
+        let consumer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || {
+                let mut taken = Vec::new();
+                while let Some(item) = queue.take_or_closed() {
+                    taken.push(item.0);
+                }
+                taken
+            })
+        };
+        while queue.len() > 1 {
+            std::thread::yield_now();
+        }
+        let drained = queue.close_and_drain();
+        assert_eq!(
+            vec![-2_000_000_000, -1_000_000_000],
+            consumer.join().unwrap()
+        );
+        assert_eq!(
+            vec![3_600_000_000_000],
+            drained.iter().map(|item| item.0).collect::<Vec<_>>()
+        );
+        assert!(queue.is_closed());
+        assert_eq!(Err(PutError::Closed(Fixed(0))), queue.try_put(Fixed(0)));
+    }
+
+    #[test]
+    fn test_headers() {
+        let queue = DelayQueue::default();
+        let headers: Headers = vec![("trace-id", "abc"), ("tenant", "acme")]
+            .into_iter()
+            .collect();
+        queue.try_put_with_headers(Fixed(-1), headers).unwrap();
+        queue.try_put(Fixed(0)).unwrap();
+
+        let delivery = queue.take_delivery();
+        let headers = delivery.headers.unwrap();
+        assert_eq!(Some("abc"), headers.get("trace-id"));
+        assert_eq!(2, headers.len());
+        assert!(queue.take_delivery().headers.is_none());
+    }
+
+    #[test]
+    fn test_reschedule() {
+        let queue = DelayQueue::default();
+        queue
+            .try_put_with_headers(Fixed(-1), std::iter::once(("job", "report")).collect())
+            .unwrap();
+        let first = queue.take_delivery();
+        let item = Arc::clone(&first.item);
+        queue.reschedule(first).unwrap();
+
+        let second = queue.take_delivery();
+        assert!(Arc::ptr_eq(&item, &second.item));
+        assert_eq!(Some("report"), second.headers.as_ref().unwrap().get("job"));
+        queue.close();
+        assert!(matches!(queue.reschedule(second), Err(PutError::Closed(_))));
+    }
+
+    #[test]
+    fn test_put_shared() {
+        let mut queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let payload = Arc::new(Fixed(-1));
+        let now = Instant::now();
+        queue.put_shared(Arc::clone(&payload));
+        queue.put_shared_at(now + time::Duration::from_secs(3600), Arc::clone(&payload));
+        assert_eq!(3, Arc::strong_count(&payload));
+        assert!(Arc::ptr_eq(&payload, &queue.take()));
+        assert_eq!(1, queue.len());
+
+        let mut queue = DelayQueue::default();
+        let payload = Arc::new(Fixed(3_600_000_000_000));
+        queue.put_shared_at(Instant::now(), Arc::clone(&payload));
+        assert!(Arc::ptr_eq(&payload, &queue.try_take().unwrap()));
+    }
+
+    #[test]
+    fn test_on_discard() {
+        let mut queue = DelayQueue::default();
+        let discarded = Arc::new(Mutex::new(Vec::new()));
+        {
+            let discarded = Arc::clone(&discarded);
+            queue.on_discard(move |item: Arc<Fixed>| discarded.lock().push(item.0));
+        }
+        queue.put(Fixed(1));
+        queue.put(Fixed(2));
+        assert_eq!(2, queue.close_and_discard());
+        queue.put(Fixed(3));
+        assert_eq!(Err(PutError::Closed(Fixed(4))), queue.try_put(Fixed(4)));
+        assert_eq!(vec![1, 2, 3], *discarded.lock());
+    }
+
     fn after(du: Duration) -> DateTime<Local> {
         chrono::Local::now() + du
     }
@@ -0,0 +1,283 @@
+use std::{
+    io::{self, Write},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{deadline_after, DelayQueue, Delayed};
+
+/// How many elements are loaded between two progress reports.
+const REPORT_EVERY: usize = 1024;
+
+/// An entry that was read but could not be decoded, e.g. a truncated or
+/// bit-flipped record of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corrupted {
+    /// The raw record, as far as it could be read.
+    pub bytes: Vec<u8>,
+    /// Why decoding failed.
+    pub reason: String,
+}
+
+impl Corrupted {
+    pub fn new(bytes: impl Into<Vec<u8>>, reason: impl ToString) -> Self {
+        Self {
+            bytes: bytes.into(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// How far [`DelayQueue::load_with_progress`] has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LoadProgress {
+    pub loaded: usize,
+    /// Entries set aside by
+    /// [`load_quarantining`](DelayQueue::load_quarantining) so far.
+    pub quarantined: usize,
+    /// The number of elements the caller expects to load, if known.
+    pub expected: Option<usize>,
+    pub elapsed: Duration,
+}
+
+impl LoadProgress {
+    /// The time left at the rate so far; `None` until something is loaded
+    /// or without an expected count.
+    pub fn eta(&self) -> Option<Duration> {
+        let expected = self.expected?;
+        if self.loaded + self.quarantined == 0 {
+            return None;
+        }
+        let read = self.loaded + self.quarantined;
+        let remaining = expected.saturating_sub(read) as u32;
+        Some(self.elapsed / read as u32 * remaining)
+    }
+
+    /// The fraction loaded, from 0 to 1, if the expected count is known.
+    pub fn fraction(&self) -> Option<f64> {
+        let expected = self.expected?;
+        Some(match expected {
+            0 => 1.0,
+            _ => ((self.loaded + self.quarantined) as f64 / expected as f64).min(1.0),
+        })
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    /// Reads elements back with `read` until it returns `None`, e.g. those
+    /// written by [`snapshot_streaming`](Self::snapshot_streaming), and
+    /// inserts them regardless of the capacity.
+    ///
+    /// `progress` is called every thousand or so elements and once at the
+    /// end, e.g. to report readiness; returning `false` stops loading.
+    /// Elements loaded so far stay queued. Returns the number loaded, which
+    /// stops short on a closed queue.
+    pub fn load_with_progress<R, F, P>(
+        &self,
+        reader: &mut R,
+        expected: Option<usize>,
+        mut read: F,
+        progress: P,
+    ) -> io::Result<usize>
+    where
+        F: FnMut(&mut R) -> io::Result<Option<T>>,
+        P: FnMut(&LoadProgress) -> bool,
+    {
+        self.load(
+            reader,
+            expected,
+            |reader| Ok(read(reader)?.map(Ok)),
+            |_, _| unreachable!(),
+            progress,
+        )
+    }
+
+    /// Like [`load_with_progress`](Self::load_with_progress), but `read`
+    /// may report an entry as [`Corrupted`] instead of aborting the load.
+    /// Such entries are written to `quarantine` for later inspection, each
+    /// as a line giving its position in the stream, its length and the
+    /// reason, followed by the raw bytes and a newline, and are counted in
+    /// [`Stats::quarantined`](crate::Stats::quarantined).
+    ///
+    /// Errors of `read` and of `quarantine` still abort the load.
+    pub fn load_quarantining<R, F, W, P>(
+        &self,
+        reader: &mut R,
+        expected: Option<usize>,
+        read: F,
+        quarantine: &mut W,
+        progress: P,
+    ) -> io::Result<usize>
+    where
+        F: FnMut(&mut R) -> io::Result<Option<Result<T, Corrupted>>>,
+        W: Write,
+        P: FnMut(&LoadProgress) -> bool,
+    {
+        self.load(
+            reader,
+            expected,
+            read,
+            |index, corrupted| {
+                writeln!(
+                    quarantine,
+                    "entry {} ({} bytes): {}",
+                    index,
+                    corrupted.bytes.len(),
+                    corrupted.reason
+                )?;
+                quarantine.write_all(&corrupted.bytes)?;
+                quarantine.write_all(b"\n")
+            },
+            progress,
+        )
+    }
+
+    fn load<R, F, Q, P>(
+        &self,
+        reader: &mut R,
+        expected: Option<usize>,
+        mut read: F,
+        mut quarantine: Q,
+        mut progress: P,
+    ) -> io::Result<usize>
+    where
+        F: FnMut(&mut R) -> io::Result<Option<Result<T, Corrupted>>>,
+        Q: FnMut(usize, &Corrupted) -> io::Result<()>,
+        P: FnMut(&LoadProgress) -> bool,
+    {
+        let started = Instant::now();
+        let report = |loaded, quarantined| LoadProgress {
+            loaded,
+            quarantined,
+            expected,
+            elapsed: started.elapsed(),
+        };
+        let (mut loaded, mut quarantined) = (0, 0);
+        while let Some(entry) = read(reader)? {
+            match entry {
+                Ok(item) => {
+                    let deadline = deadline_after(self.clock.now(), item.delayed());
+                    if !self.put_at(deadline, Arc::new(item)) {
+                        break;
+                    }
+                    loaded += 1;
+                }
+                Err(corrupted) => {
+                    quarantine(loaded + quarantined, &corrupted)?;
+                    self.queue.lock().quarantined += 1;
+                    quarantined += 1;
+                }
+            }
+            if (loaded + quarantined) % REPORT_EVERY == 0 && !progress(&report(loaded, quarantined))
+            {
+                return Ok(loaded);
+            }
+        }
+        progress(&report(loaded, quarantined));
+        Ok(loaded)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{BufRead, Write};
+
+    use super::*;
+    use crate::testing::Fixed;
+
+    fn read_line(reader: &mut &[u8]) -> io::Result<Option<Fixed>> {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let delay = line
+            .trim()
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(Some(Fixed(delay)))
+    }
+
+    #[test]
+    fn test_load_with_progress() {
+        let source = DelayQueue::default();
+        for delay in 0..3000 {
+            source.try_put(Fixed(delay)).unwrap();
+        }
+        let mut dump = Vec::new();
+        source
+            .snapshot_streaming(&mut dump, |w, item| writeln!(w, "{}", item.0))
+            .unwrap();
+
+        let queue = DelayQueue::default();
+        let mut reports = Vec::new();
+        let loaded = queue
+            .load_with_progress(&mut &dump[..], Some(3000), read_line, |progress| {
+                reports.push(progress.loaded);
+                true
+            })
+            .unwrap();
+        assert_eq!(3000, loaded);
+        assert_eq!(vec![1024, 2048, 3000], reports);
+        assert_eq!(3000, queue.len());
+
+        let cancelled = DelayQueue::default();
+        let loaded = cancelled
+            .load_with_progress(&mut &dump[..], None, read_line, |progress| {
+                progress.loaded < 1024
+            })
+            .unwrap();
+        assert_eq!(1024, loaded);
+        assert_eq!(1024, cancelled.len());
+    }
+
+    #[test]
+    fn test_eta() {
+        let progress = LoadProgress {
+            loaded: 200,
+            quarantined: 50,
+            expected: Some(1000),
+            elapsed: Duration::from_secs(1),
+        };
+        assert_eq!(Some(Duration::from_secs(3)), progress.eta());
+        assert_eq!(Some(0.25), progress.fraction());
+    }
+
+    #[test]
+    fn test_load_quarantining() {
+        let dump = b"-1\n-2\nx\n-3\n";
+        let read = |reader: &mut &[u8]| -> io::Result<Option<Result<Fixed, Corrupted>>> {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end();
+            Ok(Some(
+                line.parse()
+                    .map(Fixed)
+                    .map_err(|err| Corrupted::new(line, err)),
+            ))
+        };
+        let queue = DelayQueue::default();
+        let mut quarantine = Vec::new();
+        let mut last = None;
+        let loaded = queue
+            .load_quarantining(&mut &dump[..], Some(4), read, &mut quarantine, |progress| {
+                last = Some(*progress);
+                true
+            })
+            .unwrap();
+        assert_eq!(3, loaded);
+        assert_eq!(1, last.unwrap().quarantined);
+        assert_eq!(Some(1.0), last.unwrap().fraction());
+        assert_eq!(
+            "entry 2 (1 bytes): invalid digit found in string\nx\n",
+            String::from_utf8(quarantine).unwrap()
+        );
+        assert_eq!(1, queue.stats().quarantined);
+        assert_eq!(3, queue.len());
+    }
+}
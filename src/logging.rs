@@ -0,0 +1,138 @@
+//! Lifecycle events emitted as `log` records with target `delayqueue` when
+//! the `log` feature is enabled; no-ops otherwise. Records name the queue,
+//! or `-` for unnamed ones, followed by its labels in braces if it has any,
+//! e.g. `reminders{tenant=acme}`.
+
+#[cfg(feature = "log")]
+use std::{fmt, time::Duration};
+
+#[cfg(feature = "log")]
+use log::Level;
+#[cfg(feature = "log")]
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{Delivery, FailureReason, Headers};
+
+/// The level each class of lifecycle event is logged at; `None` disables
+/// the class.
+#[cfg(feature = "log")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogLevels {
+    /// A queue closed, with the number of elements still pending.
+    pub close: Option<Level>,
+    /// A put refused because the queue is full.
+    pub overflow: Option<Level>,
+    /// A handler failure reported by an [`Executor`](crate::Executor).
+    pub dead_letter: Option<Level>,
+    /// A delivery later than [`lateness`](Self::lateness) after its deadline.
+    pub late_delivery: Option<Level>,
+    /// How late a delivery may be before it is logged.
+    pub lateness: Duration,
+}
+
+#[cfg(feature = "log")]
+impl Default for LogLevels {
+    fn default() -> Self {
+        Self {
+            close: Some(Level::Info),
+            overflow: Some(Level::Warn),
+            dead_letter: Some(Level::Error),
+            late_delivery: Some(Level::Warn),
+            lateness: Duration::from_secs(1),
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+static LEVELS: RwLock<Option<LogLevels>> = const_rwlock(None);
+
+/// Replaces the levels used by every queue in the process.
+#[cfg(feature = "log")]
+pub fn set_log_levels(levels: LogLevels) {
+    *LEVELS.write() = Some(levels);
+}
+
+#[cfg(feature = "log")]
+fn levels() -> LogLevels {
+    LEVELS.read().unwrap_or_default()
+}
+
+/// A queue as it appears in log records.
+#[cfg(feature = "log")]
+struct Queue<'a>(Option<&'a str>, &'a Headers);
+
+#[cfg(feature = "log")]
+impl fmt::Display for Queue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.unwrap_or("-"))?;
+        if !self.1.is_empty() {
+            f.write_str("{")?;
+            for (i, (name, value)) in self.1.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(",")?;
+                }
+                write!(f, "{}={}", name, value)?;
+            }
+            f.write_str("}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn closed(queue: Option<&str>, labels: &Headers, pending: usize) {
+    #[cfg(feature = "log")]
+    if let Some(level) = levels().close {
+        log::log!(target: "delayqueue", level, "queue {} closed with {} pending", Queue(queue, labels), pending);
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn diagnostics(
+    queue: Option<&str>,
+    labels: &Headers,
+    report: &crate::DiagnosticsReport,
+) {
+    #[cfg(feature = "log")]
+    if let Some(level) = levels().close {
+        log::log!(target: "delayqueue", level, "queue {} diagnostics: {}", Queue(queue, labels), report);
+    }
+}
+
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn overflow(queue: Option<&str>, labels: &Headers, capacity: Option<usize>) {
+    #[cfg(feature = "log")]
+    if let Some(level) = levels().overflow {
+        log::log!(target: "delayqueue", level, "queue {} full at capacity {:?}", Queue(queue, labels), capacity);
+    }
+}
+
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn dead_letter(queue: Option<&str>, labels: &Headers, reason: &FailureReason) {
+    #[cfg(feature = "log")]
+    if let Some(level) = levels().dead_letter {
+        log::log!(target: "delayqueue", level, "handler of queue {} failed: {:?}", Queue(queue, labels), reason);
+    }
+}
+
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn delivered<T>(queue: Option<&str>, labels: &Headers, delivery: &Delivery<T>) {
+    #[cfg(feature = "log")]
+    {
+        let levels = levels();
+        let late = delivery
+            .delivered_at
+            .saturating_duration_since(delivery.deadline);
+        if let (Some(level), true) = (levels.late_delivery, late > levels.lateness) {
+            log::log!(
+                target: "delayqueue",
+                level,
+                "delivery {} of queue {} is {:?} late",
+                delivery.seq,
+                Queue(queue, labels),
+                late
+            );
+        }
+    }
+}
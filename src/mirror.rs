@@ -0,0 +1,170 @@
+use std::{
+    sync::{mpsc, Arc},
+    time::Instant,
+};
+
+use crate::{DelayQueue, Delayed, Headers, Health, QueueView, Stats};
+
+/// A change to a queue, as seen by [`DelayQueue::subscribe`].
+///
+/// Every event carries the [`labels`](DelayQueue::labels) of its queue, so
+/// a subscriber following several queues can tell them apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum QueueEvent {
+    Inserted {
+        deadline: Instant,
+        labels: Arc<Headers>,
+    },
+    Delivered {
+        seq: u64,
+        deadline: Instant,
+        labels: Arc<Headers>,
+    },
+    Closed {
+        labels: Arc<Headers>,
+    },
+    /// Settings changed through [`DelayQueue::reconfigure`].
+    Reconfigured {
+        labels: Arc<Headers>,
+    },
+    /// Wall-clock drift beyond the threshold given to
+    /// [`DelayQueue::check_clock_drift`], in nanoseconds.
+    ClockDrift {
+        drift: i64,
+        labels: Arc<Headers>,
+    },
+}
+
+impl QueueEvent {
+    /// The labels of the queue the event happened on.
+    pub fn labels(&self) -> &Headers {
+        match self {
+            QueueEvent::Inserted { labels, .. }
+            | QueueEvent::Delivered { labels, .. }
+            | QueueEvent::Closed { labels }
+            | QueueEvent::Reconfigured { labels }
+            | QueueEvent::ClockDrift { labels, .. } => labels,
+        }
+    }
+}
+
+/// Events buffered for each subscriber, see [`DelayQueue::subscribe`].
+const EVENT_BUFFER: usize = 1024;
+
+/// A read-only handle to a queue for monitoring components: it can look at
+/// the contents and follow events, but never put, take or cancel.
+pub struct Mirror<T: Delayed> {
+    queue: DelayQueue<T>,
+}
+
+impl<T: Delayed> Clone for Mirror<T> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<T: Delayed> DelayQueue<T> {
+    /// Returns a read-only handle to this queue.
+    pub fn mirror(&self) -> Mirror<T> {
+        Mirror {
+            queue: self.clone(),
+        }
+    }
+
+    /// Returns a receiver of every later change to the queue; drop it to
+    /// unsubscribe.
+    ///
+    /// Up to 1024 events are buffered until received. While the buffer is
+    /// full newer events are dropped, so a subscriber that falls behind
+    /// misses events instead of growing memory without bound.
+    pub fn subscribe(&self) -> mpsc::Receiver<QueueEvent> {
+        let (sender, receiver) = mpsc::sync_channel(EVENT_BUFFER);
+        self.queue.lock().observers.push(sender);
+        receiver
+    }
+}
+
+impl<T: Delayed> Mirror<T> {
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.queue.capacity()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.queue.is_closed()
+    }
+
+    /// The element that is delivered next, if nothing else is inserted.
+    pub fn peek(&self) -> Option<Arc<T>> {
+        self.queue
+            .queue
+            .lock()
+            .peek()
+            .map(|head| Arc::clone(&head.item))
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.queue.stats()
+    }
+
+    pub fn health(&self) -> Health {
+        self.queue.health()
+    }
+
+    pub fn subscribe(&self) -> mpsc::Receiver<QueueEvent> {
+        self.queue.subscribe()
+    }
+}
+
+impl<T: Delayed + Send + Sync> Mirror<T> {
+    /// See [`DelayQueue::view`].
+    pub fn view(&self) -> QueueView<T> {
+        self.queue.view()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::Fixed;
+
+    #[test]
+    fn test_mirror() {
+        let queue = DelayQueue::default();
+        let mirror = queue.mirror();
+        let events = mirror.subscribe();
+        queue.try_put(Fixed(3_600_000_000_000)).unwrap();
+        queue.try_put(Fixed(-1)).unwrap();
+        assert_eq!(Some(&Fixed(-1)), mirror.peek().as_deref());
+        assert_eq!(2, mirror.view().len());
+
+        queue.take_or_closed().unwrap();
+        queue.close();
+        let events = events.try_iter().collect::<Vec<_>>();
+        assert_eq!(4, events.len());
+        assert!(matches!(events[2], QueueEvent::Delivered { seq: 0, .. }));
+        assert!(matches!(events[3], QueueEvent::Closed { .. }));
+    }
+
+    #[test]
+    fn test_subscriber_lag() {
+        let queue = DelayQueue::default();
+        let events = queue.subscribe();
+        for _ in 0..EVENT_BUFFER + 10 {
+            queue.try_put(Fixed(3_600_000_000_000)).unwrap();
+        }
+        assert_eq!(EVENT_BUFFER, events.try_iter().count());
+        queue.close();
+        assert!(matches!(events.try_recv(), Ok(QueueEvent::Closed { .. })));
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    sync::{mpsc, Arc},
+    time::Instant,
+};
+
+use crate::{DelayQueue, Delayed, QueueEvent};
+
+/// Takes from several queues at once, e.g. one per tenant, always handing
+/// out the element with the earliest deadline among the expired ones, so a
+/// single consumer thread can serve all of them.
+pub struct MultiQueueTake<K, T: Delayed> {
+    queues: Vec<(K, DelayQueue<T>)>,
+    /// Inserts and closes of every queue, to wake up a waiting taker; one
+    /// pending event is enough for that.
+    events: mpsc::Receiver<QueueEvent>,
+    sender: mpsc::SyncSender<QueueEvent>,
+}
+
+impl<K, T: Delayed> Default for MultiQueueTake<K, T> {
+    fn default() -> Self {
+        let (sender, events) = mpsc::sync_channel(1);
+        Self {
+            queues: Vec::new(),
+            events,
+            sender,
+        }
+    }
+}
+
+impl<K, T> MultiQueueTake<K, T>
+where
+    K: Clone,
+    T: Delayed + Send + Sync,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `queue` under `id`, which is handed out with its elements.
+    /// Elements with equal deadlines are taken from the queue added first.
+    pub fn add(&mut self, id: K, queue: &DelayQueue<T>) {
+        queue.queue.lock().observers.push(self.sender.clone());
+        self.queues.push((id, queue.clone()));
+    }
+
+    /// Elements pending in all queues.
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(|(_, queue)| queue.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Blocks until an element of any queue expires and returns it with
+    /// the id of its queue; `None` once every queue is closed and empty.
+    pub fn take_or_closed(&self) -> Option<(K, Arc<T>)> {
+        self.take_before(None)
+    }
+
+    /// Like [`take_or_closed`](Self::take_or_closed), but gives up at
+    /// `cutoff`.
+    pub fn take_until(&self, cutoff: Instant) -> Option<(K, Arc<T>)> {
+        self.take_before(Some(cutoff))
+    }
+
+    fn take_before(&self, cutoff: Option<Instant>) -> Option<(K, Arc<T>)> {
+        loop {
+            // Events that arrive from here on wake the wait below.
+            while self.events.try_recv().is_ok() {}
+            let mut heads: Vec<_> = self
+                .queues
+                .iter()
+                .enumerate()
+                .filter_map(|(index, (_, queue))| {
+                    let head = queue.queue.lock().peek().map(|entry| entry.deadline);
+                    head.map(|deadline| (deadline, index))
+                })
+                .collect();
+            heads.sort();
+            let mut wake_at = cutoff;
+            for (_, index) in heads {
+                let (id, queue) = &self.queues[index];
+                match queue.poll_expired() {
+                    Ok(delivery) => return Some((id.clone(), delivery.item)),
+                    Err(Some(due)) => wake_at = Some(wake_at.map_or(due, |at| at.min(due))),
+                    Err(None) => {}
+                }
+            }
+            let all_closed = self
+                .queues
+                .iter()
+                .all(|(_, queue)| queue.is_closed() && queue.is_empty());
+            if all_closed || cutoff.is_some_and(|cutoff| cutoff <= Instant::now()) {
+                return None;
+            }
+            // Never disconnected, since `self` holds a sender.
+            let _ = match wake_at {
+                Some(wake_at) => self
+                    .events
+                    .recv_timeout(wake_at.saturating_duration_since(Instant::now()))
+                    .ok(),
+                None => self.events.recv().ok(),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{thread, time::Duration};
+
+    use super::*;
+    use crate::{testing::Fixed, DeadlineMode};
+
+    #[test]
+    fn test_multi_queue_take() {
+        let acme = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let globex = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let mut multi = MultiQueueTake::new();
+        multi.add("acme", &acme);
+        multi.add("globex", &globex);
+        acme.try_put(Fixed(-1_000_000)).unwrap();
+        globex.try_put(Fixed(-2_000_000)).unwrap();
+        assert_eq!(2, multi.len());
+
+        assert_eq!(Some(("globex", Fixed(-2_000_000))), take(&multi));
+        assert_eq!(Some(("acme", Fixed(-1_000_000))), take(&multi));
+        assert_eq!(
+            None,
+            multi.take_until(Instant::now() + Duration::from_millis(10))
+        );
+
+        let producer = {
+            let acme = acme.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                acme.try_put(Fixed(10_000_000)).unwrap();
+            })
+        };
+        assert_eq!(Some(("acme", Fixed(10_000_000))), take(&multi));
+        producer.join().unwrap();
+
+        acme.close();
+        globex.close();
+        assert_eq!(None, take(&multi));
+    }
+
+    fn take(multi: &MultiQueueTake<&'static str, Fixed>) -> Option<(&'static str, Fixed)> {
+        multi
+            .take_or_closed()
+            .map(|(id, item)| (id, Arc::try_unwrap(item).unwrap()))
+    }
+}
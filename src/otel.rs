@@ -0,0 +1,153 @@
+use std::{
+    borrow::Cow,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use opentelemetry::{
+    global,
+    propagation::{Extractor, Injector},
+    trace::{Status, TraceContextExt, Tracer},
+    Context,
+};
+
+use crate::{DelayQueue, Delayed, Delivery, ExecutorLayer, Handler, Headers, PutError};
+
+impl Injector for Headers {
+    fn set(&mut self, key: &str, value: String) {
+        self.insert(key, value);
+    }
+}
+
+impl Extractor for Headers {
+    fn get(&self, key: &str) -> Option<&str> {
+        Headers::get(self, key)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.iter().map(|(key, _)| key).collect()
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Sync + Send,
+{
+    /// Like [`try_put`](Self::try_put), storing the current trace context in
+    /// the entry headers with the global propagator, so the delayed work
+    /// shows up in the trace that scheduled it.
+    pub fn try_put_traced(&self, t: T) -> Result<(), PutError<T>> {
+        let mut headers = Headers::new();
+        global::get_text_map_propagator(|propagator| propagator.inject(&mut headers));
+        self.try_put_with_headers(t, headers)
+    }
+}
+
+impl<T> Delivery<T> {
+    /// The trace context stored by
+    /// [`DelayQueue::try_put_traced`], or the current one if there is none.
+    /// Executor workers attach it while running the handler.
+    pub fn trace_context(&self) -> Context {
+        match &self.headers {
+            Some(headers) => {
+                global::get_text_map_propagator(|propagator| propagator.extract(&**headers))
+            }
+            None => Context::current(),
+        }
+    }
+}
+
+/// An executor layer running every invocation in a span of the global
+/// tracer, a child of the trace context the worker attached. Invocations
+/// that panic get an error status.
+pub struct Traced {
+    name: Cow<'static, str>,
+}
+
+impl Traced {
+    /// Spans are called `name`.
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl<T: 'static> ExecutorLayer<T> for Traced {
+    fn layer(&self, inner: Handler<T>) -> Handler<T> {
+        let name = self.name.clone();
+        Arc::new(move |item: &T| {
+            let tracer = global::tracer("delayqueue");
+            let result = tracer.in_span(name.clone(), |cx| {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| inner(item)));
+                if result.is_err() {
+                    cx.span().set_status(Status::error("handler panicked"));
+                }
+                result
+            });
+            if let Err(payload) = result {
+                panic::resume_unwind(payload);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use opentelemetry::propagation::{text_map_propagator::FieldIter, TextMapPropagator};
+
+    use super::*;
+    use crate::testing::Fixed;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Tag(String);
+
+    #[derive(Debug)]
+    struct TagPropagator(Vec<String>);
+
+    impl TextMapPropagator for TagPropagator {
+        fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+            if let Some(tag) = cx.get::<Tag>() {
+                injector.set("x-tag", tag.0.clone());
+            }
+        }
+
+        fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+            match extractor.get("x-tag") {
+                Some(tag) => cx.with_value(Tag(tag.to_string())),
+                None => cx.clone(),
+            }
+        }
+
+        fn fields(&self) -> FieldIter<'_> {
+            FieldIter::new(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_context_round_trip() {
+        global::set_text_map_propagator(TagPropagator(vec!["x-tag".into()]));
+        let queue = DelayQueue::default();
+        {
+            let _context = Context::current_with_value(Tag("scheduled".into())).attach();
+            queue.try_put_traced(Fixed(0)).unwrap();
+        }
+        let delivery = queue.take_delivery();
+        assert_eq!(
+            Some("scheduled"),
+            delivery.headers.as_ref().unwrap().get("x-tag")
+        );
+        let context = delivery.trace_context();
+        assert_eq!(Some(&Tag("scheduled".into())), context.get::<Tag>());
+    }
+
+    #[test]
+    fn test_traced_layer() {
+        let handler: Handler<bool> = Arc::new(|fail: &bool| {
+            assert!(Context::current().has_active_span());
+            assert!(!*fail, "failed");
+        });
+        let handler = Traced::new("job").layer(handler);
+        handler(&false);
+        assert!(!Context::current().has_active_span());
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| handler(&true))).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use std::{sync::Arc, time::Instant};
+
+use crate::{deadline_after, logging, DelayQueue, DelayQueueInner, Delayed, PutError};
+
+/// An element that holds a reserved slot but is not visible to consumers yet.
+///
+/// Created by [`DelayQueue::prepare`]; dropping it without calling
+/// [`DelayQueue::commit`] releases the slot like [`DelayQueue::abort`].
+pub struct Prepared<T: Delayed> {
+    queue: DelayQueue<T>,
+    item: Option<T>,
+    deadline: Instant,
+}
+
+impl<T: Delayed> Prepared<T> {
+    pub fn item(&self) -> &T {
+        self.item.as_ref().unwrap()
+    }
+
+    fn release(&mut self) -> Option<T> {
+        let item = self.item.take()?;
+        self.queue.queue.lock().reserved -= 1;
+        self.queue.not_full.notify_all();
+        Some(item)
+    }
+}
+
+impl<T: Delayed> Drop for Prepared<T> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Free slots held for later insertion, see [`DelayQueue::reserve_slots`].
+///
+/// Unused slots are released when the reservation is dropped.
+pub struct Reservation<T: Delayed> {
+    queue: DelayQueue<T>,
+    remaining: usize,
+}
+
+impl<T: Delayed> Reservation<T> {
+    /// Slots that can still be used.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<T> Reservation<T>
+where
+    T: Delayed + Sync + Send,
+{
+    /// Inserts `t` into one of the reserved slots; never blocks. Fails with
+    /// [`PutError::Full`] once every slot is used.
+    pub fn put(&mut self, t: T) -> Result<(), PutError<T>> {
+        if self.remaining == 0 {
+            return Err(PutError::Full(t));
+        }
+        let now = self.queue.clock.now();
+        let deadline = deadline_after(now, t.delayed());
+        let mut guard = self.queue.queue.lock();
+        if guard.closed {
+            return Err(PutError::Closed(t));
+        }
+        if guard.beyond_horizon(now, deadline) {
+            return Err(PutError::DeadlineTooFar(t));
+        }
+        guard.reserved -= 1;
+        self.remaining -= 1;
+        let entry = guard.entry(deadline, false, Arc::new(t));
+        self.queue.push_notify(&mut guard, entry);
+        Ok(())
+    }
+}
+
+impl<T: Delayed> Drop for Reservation<T> {
+    fn drop(&mut self) {
+        if self.remaining > 0 {
+            self.queue.queue.lock().reserved -= self.remaining;
+            self.queue.not_full.notify_all();
+        }
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Sync + Send,
+{
+    /// Blocks until the queue has at least one free slot or is closed.
+    /// Returns immediately for unbounded queues.
+    pub fn wait_for_capacity(&self) {
+        let mut guard = self.queue.lock();
+        while guard.is_full() && !guard.closed {
+            self.not_full.wait(&mut guard);
+        }
+    }
+
+    /// Blocks until `n` slots are free and holds them, so that an expensive
+    /// element can be built before inserting it without risking a full queue.
+    ///
+    /// On a closed queue this returns at once and every
+    /// [`Reservation::put`] fails with [`PutError::Closed`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` exceeds the capacity of a bounded queue.
+    pub fn reserve_slots(&self, n: usize) -> Reservation<T> {
+        let mut guard = self.queue.lock();
+        loop {
+            if let Some(reservation) = self.reserve_locked(&mut guard, n) {
+                return reservation;
+            }
+            self.not_full.wait(&mut guard);
+        }
+    }
+
+    /// Like [`reserve_slots`](Self::reserve_slots), but returns `None`
+    /// instead of blocking.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn try_reserve_slots(&self, n: usize) -> Option<Reservation<T>> {
+        self.reserve_locked(&mut self.queue.lock(), n)
+    }
+
+    fn reserve_locked(&self, guard: &mut DelayQueueInner<T>, n: usize) -> Option<Reservation<T>> {
+        if let Some(capacity) = guard.capacity {
+            assert!(n <= capacity, "reserving {} slots of {}", n, capacity);
+            if guard.queue.len() + guard.reserved + n > capacity && !guard.closed {
+                return None;
+            }
+        }
+        if guard.closed {
+            return Some(Reservation {
+                queue: self.clone(),
+                remaining: 0,
+            });
+        }
+        guard.reserved += n;
+        Some(Reservation {
+            queue: self.clone(),
+            remaining: n,
+        })
+    }
+
+    /// Validates `t` and reserves a slot for it without making it visible.
+    ///
+    /// The deadline is computed here, so a later [`commit`](Self::commit)
+    /// never blocks and never fails.
+    pub fn prepare(&self, t: T) -> Result<Prepared<T>, PutError<T>> {
+        let now = self.clock.now();
+        let deadline = deadline_after(now, t.delayed());
+        let mut guard = self.queue.lock();
+        if guard.closed {
+            return Err(PutError::Closed(t));
+        }
+        if guard.beyond_horizon(now, deadline) {
+            return Err(PutError::DeadlineTooFar(t));
+        }
+        if guard.is_full() {
+            let capacity = guard.capacity;
+            drop(guard);
+            logging::overflow(self.name(), self.labels(), capacity);
+            return Err(PutError::Full(t));
+        }
+        guard.reserved += 1;
+        Ok(Prepared {
+            queue: self.clone(),
+            item: Some(t),
+            deadline,
+        })
+    }
+
+    /// Makes a prepared element visible to consumers. If the queue was
+    /// closed in the meantime the element is discarded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prepared` was created by a different queue.
+    pub fn commit(&self, mut prepared: Prepared<T>) {
+        assert!(
+            Arc::ptr_eq(&self.queue, &prepared.queue.queue),
+            "committing an element prepared by another queue"
+        );
+        let item = prepared.item.take().unwrap();
+        let mut guard = self.queue.lock();
+        guard.reserved -= 1;
+        if guard.closed {
+            let hook = guard.on_discard.clone();
+            drop(guard);
+            Self::discard(hook, vec![Arc::new(item)]);
+            return;
+        }
+        let entry = guard.entry(prepared.deadline, false, Arc::new(item));
+        self.push_notify(&mut guard, entry);
+    }
+
+    /// Releases the slot reserved by `prepared` and returns its element.
+    pub fn abort(&self, mut prepared: Prepared<T>) -> T {
+        prepared.release().unwrap()
+    }
+}
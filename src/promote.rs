@@ -0,0 +1,134 @@
+use std::{sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+
+use crate::{deadline_after, worker::Periodic, DelayQueue, Delayed};
+
+/// A coarse, usually persistent, store that feeds elements into the
+/// in-memory heap shortly before they are due.
+pub trait Promoter<T>: Send + 'static {
+    /// Removes and returns every element due within `horizon`.
+    fn due_within(&mut self, horizon: Duration) -> Vec<T>;
+}
+
+/// Counters of a [`PromotionTask`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PromotionStats {
+    /// Promotion passes run so far.
+    pub runs: u64,
+    /// Elements moved into the queue so far.
+    pub promoted: u64,
+    /// Elements moved by the latest pass.
+    pub last_batch: usize,
+}
+
+/// Background promotion into a queue, see [`DelayQueue::spawn_promoter`].
+pub struct PromotionTask {
+    stats: Arc<Mutex<PromotionStats>>,
+    worker: Periodic,
+}
+
+impl PromotionTask {
+    pub fn stats(&self) -> PromotionStats {
+        self.stats.lock().clone()
+    }
+
+    /// Stops promoting; elements left in the store stay there.
+    pub fn stop(&mut self) {
+        self.worker.stop();
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    /// Moves every element `promoter` reports due within `horizon` into the
+    /// queue and returns how many were moved.
+    ///
+    /// Promoted elements are accepted even if a bounded queue is full, and
+    /// dropped if it is closed.
+    pub fn promote_due_within<P: Promoter<T>>(&self, promoter: &mut P, horizon: Duration) -> usize {
+        let due = promoter.due_within(horizon);
+        let promoted = due.len();
+        for item in due {
+            let deadline = deadline_after(self.clock.now(), item.delayed());
+            self.put_at(deadline, Arc::new(item));
+        }
+        promoted
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    /// Runs [`promote_due_within`](Self::promote_due_within) every
+    /// `interval` on a background thread until the returned task is stopped
+    /// or dropped.
+    ///
+    /// `interval` must be shorter than `horizon`, otherwise promoted elements
+    /// may be delivered late.
+    pub fn spawn_promoter<P: Promoter<T>>(
+        &self,
+        mut promoter: P,
+        horizon: Duration,
+        interval: Duration,
+    ) -> PromotionTask {
+        let stats = Arc::new(Mutex::new(PromotionStats::default()));
+        let worker = {
+            let queue = self.clone();
+            let stats = Arc::clone(&stats);
+            Periodic::spawn(interval, move || {
+                let promoted = queue.promote_due_within(&mut promoter, horizon);
+                let mut stats = stats.lock();
+                stats.runs += 1;
+                stats.promoted += promoted as u64;
+                stats.last_batch = promoted;
+            })
+        };
+        PromotionTask { stats, worker }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::Fixed;
+
+    struct Backlog(Vec<Fixed>);
+
+    impl Promoter<Fixed> for Backlog {
+        fn due_within(&mut self, horizon: Duration) -> Vec<Fixed> {
+            let horizon = horizon.as_nanos() as i64;
+            let (due, later) = std::mem::take(&mut self.0)
+                .into_iter()
+                .partition(|item| item.0 <= horizon);
+            self.0 = later;
+            due
+        }
+    }
+
+    #[test]
+    fn test_promote() {
+        let queue = DelayQueue::default();
+        let mut backlog = Backlog(vec![Fixed(-1), Fixed(3_600_000_000_000)]);
+        assert_eq!(
+            1,
+            queue.promote_due_within(&mut backlog, Duration::from_secs(60))
+        );
+        assert_eq!(Fixed(-1), *queue.take_or_closed().unwrap());
+
+        let mut task = queue.spawn_promoter(
+            Backlog(vec![Fixed(-2)]),
+            Duration::from_secs(60),
+            Duration::from_millis(5),
+        );
+        assert_eq!(Fixed(-2), *queue.take_or_closed().unwrap());
+        task.stop();
+        let stats = task.stats();
+        assert_eq!(1, stats.promoted);
+        assert!(stats.runs >= 1);
+    }
+}
@@ -0,0 +1,366 @@
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::{Mutex, RwLock};
+
+use crate::{worker::Periodic, DelayQueue, Delayed, Stats};
+
+trait Registered: Send + Sync {
+    fn stats(&self) -> Stats;
+    fn as_any(&self) -> &dyn Any;
+    /// Handles to the queue that exist outside the registry.
+    fn outside_handles(&self) -> usize;
+    fn clear(&self);
+    /// Moves every element `target` accepts into it, keeping the others, like
+    /// [`DelayQueue::try_put`] would. `false` unless every element moved.
+    fn migrate_into(&self, target: &dyn Registered) -> bool;
+}
+
+impl<T> Registered for DelayQueue<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    fn stats(&self) -> Stats {
+        DelayQueue::stats(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn outside_handles(&self) -> usize {
+        Arc::strong_count(&self.queue) - 1
+    }
+
+    fn clear(&self) {
+        DelayQueue::clear(self);
+    }
+
+    fn migrate_into(&self, target: &dyn Registered) -> bool {
+        let target = match target.as_any().downcast_ref::<DelayQueue<T>>() {
+            Some(target) => target,
+            None => return false,
+        };
+        let entries = self.queue.lock().drain();
+        let mut rejected = Vec::new();
+        {
+            let now = target.clock.now();
+            let mut guard = target.queue.lock();
+            for entry in entries {
+                if guard.closed || guard.is_full() || guard.beyond_horizon(now, entry.deadline) {
+                    rejected.push(entry);
+                } else {
+                    guard.push_entry(entry);
+                }
+            }
+        }
+        target.available.notify_all();
+        self.not_full.notify_all();
+        if rejected.is_empty() {
+            return true;
+        }
+        let mut guard = self.queue.lock();
+        for entry in rejected {
+            guard.push_entry(entry);
+        }
+        drop(guard);
+        self.available.notify_all();
+        false
+    }
+}
+
+struct Slot {
+    queue: Box<dyn Registered>,
+    last_used: Mutex<Instant>,
+}
+
+impl Slot {
+    fn new(queue: Box<dyn Registered>) -> Self {
+        Self {
+            queue,
+            last_used: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn get<T>(&self) -> Option<DelayQueue<T>>
+    where
+        T: Delayed + Send + Sync + 'static,
+    {
+        let queue = self.queue.as_any().downcast_ref::<DelayQueue<T>>()?;
+        *self.last_used.lock() = Instant::now();
+        Some(queue.clone())
+    }
+}
+
+/// What [`QueueRegistry::collect_idle`] does with idle queues that still hold
+/// elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TeardownPolicy {
+    /// Keep them registered; only empty queues are collected.
+    Keep,
+    /// Collect them and drop their remaining elements.
+    Drop,
+    /// Move their remaining elements into the queue registered under the
+    /// given name, then collect them. Queues whose elements cannot all be
+    /// moved there, because the element type differs or the target is
+    /// closed, full or beyond its horizon, are kept with the elements that
+    /// did not move.
+    MigrateTo(String),
+}
+
+/// A set of named queues, possibly of different element types, that can be
+/// looked up and inspected together, e.g. from a single metrics endpoint.
+#[derive(Clone, Default)]
+pub struct QueueRegistry {
+    queues: Arc<RwLock<BTreeMap<String, Slot>>>,
+}
+
+impl QueueRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `queue` under `name`, replacing any queue registered before.
+    ///
+    /// The registry keeps a handle to the queue until it is unregistered.
+    pub fn register<T, S>(&self, name: S, queue: &DelayQueue<T>)
+    where
+        T: Delayed + Send + Sync + 'static,
+        S: Into<String>,
+    {
+        self.queues
+            .write()
+            .insert(name.into(), Slot::new(Box::new(queue.clone())));
+    }
+
+    /// Returns `true` if a queue was registered under `name`.
+    pub fn unregister(&self, name: &str) -> bool {
+        self.queues.write().remove(name).is_some()
+    }
+
+    /// Looks up the queue registered under `name`; `None` if there is none or
+    /// its element type is not `T`.
+    pub fn get<T>(&self, name: &str) -> Option<DelayQueue<T>>
+    where
+        T: Delayed + Send + Sync + 'static,
+    {
+        self.queues.read().get(name)?.get()
+    }
+
+    /// Looks up the queue registered under `name`, registering the one built
+    /// by `create` if there is none. `None` if the registered queue has a
+    /// different element type.
+    pub fn get_or_create<T, F>(&self, name: &str, create: F) -> Option<DelayQueue<T>>
+    where
+        T: Delayed + Send + Sync + 'static,
+        F: FnOnce() -> DelayQueue<T>,
+    {
+        if let Some(slot) = self.queues.read().get(name) {
+            return slot.get();
+        }
+        self.queues
+            .write()
+            .entry(name.to_string())
+            .or_insert_with(|| Slot::new(Box::new(create())))
+            .get()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.queues.read().keys().cloned().collect()
+    }
+
+    /// Stats of every registered queue, ordered by name.
+    pub fn stats(&self) -> Vec<(String, Stats)> {
+        self.queues
+            .read()
+            .iter()
+            .map(|(name, slot)| (name.clone(), slot.queue.stats()))
+            .collect()
+    }
+
+    /// Stats of all registered queues merged together.
+    pub fn aggregate(&self) -> Stats {
+        self.stats()
+            .into_iter()
+            .map(|(_, stats)| stats)
+            .reduce(|mut total, stats| {
+                total.merge(&stats);
+                total
+            })
+            .unwrap_or_default()
+    }
+
+    /// Unregisters queues that were not looked up for `idle` and have no
+    /// handles outside the registry, so no producer can race the teardown.
+    /// Returns the names of the collected queues.
+    pub fn collect_idle(&self, idle: Duration, policy: &TeardownPolicy) -> Vec<String> {
+        let mut queues = self.queues.write();
+        let now = Instant::now();
+        let idle_names = queues
+            .iter()
+            .filter(|(_, slot)| {
+                now.duration_since(*slot.last_used.lock()) >= idle
+                    && slot.queue.outside_handles() == 0
+            })
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        let mut collected = Vec::new();
+        let mut to_clear = Vec::new();
+        for name in idle_names {
+            let slot = &queues[&name];
+            let stats = slot.queue.stats();
+            let empty = stats.len == 0 && stats.reserved == 0;
+            let teardown = empty
+                || match policy {
+                    TeardownPolicy::Keep => false,
+                    TeardownPolicy::Drop => true,
+                    TeardownPolicy::MigrateTo(target) if *target != name => queues
+                        .get(target)
+                        .is_some_and(|target| slot.queue.migrate_into(&*target.queue)),
+                    TeardownPolicy::MigrateTo(_) => false,
+                };
+            if teardown {
+                let slot = queues.remove(&name).unwrap();
+                if !empty {
+                    to_clear.push(slot);
+                }
+                collected.push(name);
+            }
+        }
+        // Clearing runs the discard hooks and audit sinks, which must not
+        // be called with the registry locked.
+        drop(queues);
+        for slot in to_clear {
+            slot.queue.clear();
+        }
+        collected
+    }
+
+    /// Runs [`collect_idle`](Self::collect_idle) every `interval` on a
+    /// background thread until the returned collector is stopped or dropped.
+    pub fn spawn_collector(
+        &self,
+        interval: Duration,
+        idle: Duration,
+        policy: TeardownPolicy,
+    ) -> IdleCollector {
+        let registry = self.clone();
+        IdleCollector {
+            worker: Periodic::spawn(interval, move || {
+                registry.collect_idle(idle, &policy);
+            }),
+        }
+    }
+}
+
+/// Background garbage collection of idle queues, see
+/// [`QueueRegistry::spawn_collector`].
+pub struct IdleCollector {
+    worker: Periodic,
+}
+
+impl IdleCollector {
+    pub fn stop(&mut self) {
+        self.worker.stop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::Fixed;
+
+    #[test]
+    fn test_registry() {
+        let registry = QueueRegistry::new();
+        let mut a = DelayQueue::<Fixed>::bounded(4);
+        let b = DelayQueue::<Fixed>::bounded(2);
+        registry.register("a", &a);
+        registry.register("b", &b);
+        a.put(Fixed(1_000_000_000));
+
+        assert!(registry.get::<Fixed>("a").is_some());
+        assert!(registry.get::<Fixed>("c").is_none());
+        let total = registry.aggregate();
+        assert_eq!(1, total.len);
+        assert_eq!(Some(6), total.capacity);
+
+        assert!(registry.unregister("a"));
+        assert_eq!(vec!["b".to_string()], registry.names());
+    }
+
+    #[test]
+    fn test_collect_idle() {
+        let registry = QueueRegistry::new();
+        let archive = registry
+            .get_or_create("archive", DelayQueue::<Fixed>::default)
+            .unwrap();
+        registry
+            .get_or_create("tenant-1", DelayQueue::<Fixed>::default)
+            .unwrap()
+            .put(Fixed(1_000_000_000));
+        drop(registry.get_or_create("tenant-2", DelayQueue::<Fixed>::default));
+
+        let held = registry.get::<Fixed>("tenant-2").unwrap();
+        assert!(registry
+            .collect_idle(Duration::ZERO, &TeardownPolicy::Keep)
+            .is_empty());
+        drop(held);
+
+        assert_eq!(
+            vec!["tenant-2".to_string()],
+            registry.collect_idle(Duration::ZERO, &TeardownPolicy::Keep)
+        );
+        assert_eq!(
+            vec!["tenant-1".to_string()],
+            registry.collect_idle(
+                Duration::ZERO,
+                &TeardownPolicy::MigrateTo("archive".to_string())
+            )
+        );
+        assert_eq!(1, archive.len());
+
+        let mut full = DelayQueue::<Fixed>::bounded(2);
+        full.put(Fixed(0));
+        registry.register("full", &full);
+        let mut tenant = DelayQueue::<Fixed>::default();
+        tenant.put(Fixed(-1));
+        tenant.put(Fixed(-2));
+        registry.register("tenant-4", &tenant);
+        drop(tenant);
+        let policy = TeardownPolicy::MigrateTo("full".to_string());
+        assert!(registry.collect_idle(Duration::ZERO, &policy).is_empty());
+        assert_eq!(1, registry.get::<Fixed>("tenant-4").unwrap().len());
+        assert_eq!(2, full.len());
+        full.take();
+        full.take();
+        assert_eq!(
+            vec!["tenant-4".to_string()],
+            registry.collect_idle(Duration::ZERO, &policy)
+        );
+        assert_eq!(1, full.len());
+        assert!(registry.unregister("full"));
+
+        // The hook looks at the registry, which deadlocks if it still holds
+        // its lock.
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut dropped = DelayQueue::<Fixed>::default();
+        {
+            let (registry, seen) = (registry.clone(), Arc::clone(&seen));
+            dropped.on_discard(move |_| *seen.lock() = registry.names());
+        }
+        dropped.put(Fixed(1_000_000_000));
+        registry.register("tenant-3", &dropped);
+        drop(dropped);
+        assert_eq!(
+            vec!["tenant-3".to_string()],
+            registry.collect_idle(Duration::ZERO, &TeardownPolicy::Drop)
+        );
+        assert_eq!(vec!["archive".to_string()], *seen.lock());
+    }
+}
@@ -0,0 +1,147 @@
+use std::{cmp::Ordering, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+
+use crate::{worker::Periodic, DelayQueue, Delayed};
+
+/// A user store that scheduled records are relayed from, e.g. an outbox table.
+pub trait OutboxSource: Send + 'static {
+    type Id: Send + Sync + Clone + 'static;
+    type Item: Delayed + Send + Sync + 'static;
+
+    /// Returns records due within `horizon` that were not returned before.
+    fn poll_due(&mut self, horizon: Duration) -> Vec<(Self::Id, Self::Item)>;
+
+    /// Called once the record identified by `id` has been delivered.
+    fn ack(&mut self, id: Self::Id);
+}
+
+/// A record relayed from an [`OutboxSource`].
+#[derive(Debug)]
+pub struct Relayed<I, T> {
+    id: I,
+    item: T,
+}
+
+impl<I, T> Relayed<I, T> {
+    pub fn id(&self) -> &I {
+        &self.id
+    }
+
+    pub fn item(&self) -> &T {
+        &self.item
+    }
+}
+
+impl<I, T: Delayed> Delayed for Relayed<I, T> {
+    fn delayed(&self) -> i64 {
+        self.item.delayed()
+    }
+}
+
+impl<I, T: Ord> Ord for Relayed<I, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.item.cmp(&other.item)
+    }
+}
+
+impl<I, T: Ord> PartialOrd for Relayed<I, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<I, T: Ord> PartialEq for Relayed<I, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<I, T: Ord> Eq for Relayed<I, T> {}
+
+/// Periodically moves near-due records from an [`OutboxSource`] into an
+/// in-memory queue and acknowledges them back once they are taken.
+pub struct Relay<S: OutboxSource> {
+    queue: DelayQueue<Relayed<S::Id, S::Item>>,
+    source: Arc<Mutex<S>>,
+    poller: Periodic,
+}
+
+impl<S: OutboxSource> Relay<S> {
+    /// Polls `source` every `interval` for records due within `horizon`.
+    pub fn spawn(source: S, interval: Duration, horizon: Duration) -> Self {
+        let queue = DelayQueue::default();
+        let source = Arc::new(Mutex::new(source));
+        let poller = {
+            let mut queue = queue.clone();
+            let source = Arc::clone(&source);
+            Periodic::spawn(interval, move || {
+                let records = source.lock().poll_due(horizon);
+                for (id, item) in records {
+                    queue.put(Relayed { id, item });
+                }
+            })
+        };
+        Self {
+            queue,
+            source,
+            poller,
+        }
+    }
+
+    /// Takes the next expired record and acknowledges it to the source.
+    pub fn take(&mut self) -> Arc<Relayed<S::Id, S::Item>> {
+        let relayed = self.queue.take();
+        self.source.lock().ack(relayed.id.clone());
+        relayed
+    }
+
+    pub fn queue(&self) -> &DelayQueue<Relayed<S::Id, S::Item>> {
+        &self.queue
+    }
+
+    /// Stops polling; records already relayed stay in the queue.
+    pub fn stop(&mut self) {
+        self.poller.stop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::Fixed;
+
+    struct VecSource {
+        pending: Vec<(u32, Fixed)>,
+        acked: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl OutboxSource for VecSource {
+        type Id = u32;
+        type Item = Fixed;
+
+        fn poll_due(&mut self, _horizon: Duration) -> Vec<(u32, Fixed)> {
+            std::mem::take(&mut self.pending)
+        }
+
+        fn ack(&mut self, id: u32) {
+            self.acked.lock().push(id);
+        }
+    }
+
+    #[test]
+    fn test_relay() {
+        let acked = Arc::new(Mutex::new(Vec::new()));
+        let source = VecSource {
+            pending: vec![(1, Fixed(-1)), (2, Fixed(-2))],
+            acked: Arc::clone(&acked),
+        };
+        let mut relay = Relay::spawn(source, Duration::from_millis(10), Duration::from_secs(60));
+        let mut taken = vec![*relay.take().id(), *relay.take().id()];
+        relay.stop();
+        taken.sort_unstable();
+        acked.lock().sort_unstable();
+        assert_eq!(vec![1, 2], taken);
+        assert_eq!(taken, *acked.lock());
+    }
+}
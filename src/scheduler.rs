@@ -0,0 +1,176 @@
+use std::{sync::Arc, time::Instant};
+
+use crate::{DelayQueue, Delayed, PutError, Stats};
+
+/// The operations application code needs from a delay queue, so it can be
+/// written against any backend and tested against the in-memory
+/// [`DelayQueue`]. Methods are named after their [`DelayQueue`]
+/// counterparts.
+///
+/// The trait is object safe: [`DynDelayQueue`] stores any backend behind
+/// it, e.g. in a heterogeneous registry, and test doubles can stand in for
+/// a real queue.
+pub trait Scheduler<T> {
+    /// Inserts `item` due after its `delayed`, failing instead of blocking
+    /// when the backend is full.
+    fn try_put(&self, item: T) -> Result<(), PutError<T>>;
+
+    /// Blocks until an element expires and returns it; `None` once the
+    /// backend is closed and empty.
+    fn take_or_closed(&self) -> Option<Arc<T>>;
+
+    /// Like [`take_or_closed`](Self::take_or_closed), but gives up at
+    /// `cutoff`.
+    fn take_until(&self, cutoff: Instant) -> Option<Arc<T>>;
+
+    /// Removes every pending element matching `pred` and returns them.
+    fn cancel(&self, pred: &mut dyn FnMut(&T) -> bool) -> Vec<Arc<T>>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Refuses further puts and lets takers drain what is left.
+    fn close(&self);
+
+    fn is_closed(&self) -> bool;
+
+    fn stats(&self) -> Stats;
+}
+
+/// A type-erased delay queue, see [`DelayQueue::into_dyn`].
+pub type DynDelayQueue<T> = Arc<dyn Scheduler<T> + Send + Sync>;
+
+impl<T, S> Scheduler<T> for Arc<S>
+where
+    S: Scheduler<T> + ?Sized,
+{
+    fn try_put(&self, item: T) -> Result<(), PutError<T>> {
+        (**self).try_put(item)
+    }
+
+    fn take_or_closed(&self) -> Option<Arc<T>> {
+        (**self).take_or_closed()
+    }
+
+    fn take_until(&self, cutoff: Instant) -> Option<Arc<T>> {
+        (**self).take_until(cutoff)
+    }
+
+    fn cancel(&self, pred: &mut dyn FnMut(&T) -> bool) -> Vec<Arc<T>> {
+        (**self).cancel(pred)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn close(&self) {
+        (**self).close()
+    }
+
+    fn is_closed(&self) -> bool {
+        (**self).is_closed()
+    }
+
+    fn stats(&self) -> Stats {
+        (**self).stats()
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    /// Erases the queue type, so queues and other [`Scheduler`]s can be
+    /// stored and used interchangeably.
+    pub fn into_dyn(self) -> DynDelayQueue<T> {
+        Arc::new(self)
+    }
+}
+
+impl<T> Scheduler<T> for DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    fn try_put(&self, item: T) -> Result<(), PutError<T>> {
+        DelayQueue::try_put(self, item)
+    }
+
+    fn take_or_closed(&self) -> Option<Arc<T>> {
+        DelayQueue::take_or_closed(self)
+    }
+
+    fn take_until(&self, cutoff: Instant) -> Option<Arc<T>> {
+        DelayQueue::take_until(self, cutoff)
+    }
+
+    fn cancel(&self, pred: &mut dyn FnMut(&T) -> bool) -> Vec<Arc<T>> {
+        self.remove_where(pred)
+    }
+
+    fn len(&self) -> usize {
+        DelayQueue::len(self)
+    }
+
+    fn close(&self) {
+        DelayQueue::close(self)
+    }
+
+    fn is_closed(&self) -> bool {
+        DelayQueue::is_closed(self)
+    }
+
+    fn stats(&self) -> Stats {
+        DelayQueue::stats(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::Fixed;
+
+    fn drain(scheduler: &dyn Scheduler<Fixed>) -> Vec<i64> {
+        scheduler.cancel(&mut |item| item.0 > 0);
+        let mut taken = Vec::new();
+        while scheduler.stats().len > 0 {
+            taken.push(scheduler.take_or_closed().unwrap().0);
+        }
+        taken
+    }
+
+    #[test]
+    fn test_scheduler() {
+        let queue = DelayQueue::default();
+        for delay in &[-1_000_000, 3_600_000_000_000, -2_000_000] {
+            queue.try_put(Fixed(*delay)).unwrap();
+        }
+        assert_eq!(vec![-2_000_000, -1_000_000], drain(&queue));
+        assert_eq!(2, queue.stats().delivered);
+    }
+
+    #[test]
+    fn test_dyn_delay_queue() {
+        let queues: Vec<DynDelayQueue<Fixed>> = vec![
+            DelayQueue::default().into_dyn(),
+            DelayQueue::bounded(1).into_dyn(),
+        ];
+        for queue in &queues {
+            queue.try_put(Fixed(3_600_000_000_000)).unwrap();
+            assert_eq!(None, queue.take_until(Instant::now()));
+            queue.close();
+            assert!(queue.is_closed());
+        }
+        assert_eq!(
+            vec![1, 1],
+            queues.iter().map(|queue| queue.len()).collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            queues[1].try_put(Fixed(0)),
+            Err(PutError::Closed(_))
+        ));
+    }
+}
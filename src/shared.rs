@@ -0,0 +1,279 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc, Weak,
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use parking_lot::Mutex;
+
+use crate::{deadline_after, delayed_until, DeadlineMode, DelayQueue, Delayed};
+
+/// A lightweight queue as seen by the driver thread.
+trait Expire: Send + Sync {
+    /// Hands out every expired element and registers the new head.
+    fn expire(&self);
+}
+
+/// The head deadline of a lightweight queue, as registered with the timer.
+struct HeadDue {
+    deadline: Instant,
+    queue: Weak<dyn Expire>,
+}
+
+impl Delayed for HeadDue {
+    fn delayed(&self) -> i64 {
+        delayed_until(Instant::now(), self.deadline)
+    }
+}
+
+// Heads with equal deadlines expire in registration order.
+unordered!(HeadDue);
+
+/// One driver thread serving any number of [`LightQueue`]s, e.g. one per
+/// connection or session, instead of a thread per queue. Each queue keeps
+/// its own elements and registers only its head deadline with the timer.
+pub struct SharedTimer {
+    heads: DelayQueue<HeadDue>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Default for SharedTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SharedTimer {
+    pub fn new() -> Self {
+        let heads = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let heads: DelayQueue<HeadDue> = heads.clone();
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while let Some(delivery) = heads.take_unless(&stop) {
+                    if let Some(queue) = delivery.item.queue.upgrade() {
+                        queue.expire();
+                    }
+                }
+            })
+        };
+        Self {
+            heads,
+            stop,
+            worker: Some(worker),
+        }
+    }
+
+    /// Creates a queue driven by this timer. `on_expired` is called with
+    /// each element once it expires, on the driver thread, so it should
+    /// hand the element off rather than process it.
+    pub fn queue<T, F>(&self, on_expired: F) -> LightQueue<T>
+    where
+        T: Delayed + Send + Sync + 'static,
+        F: Fn(Arc<T>) + Send + Sync + 'static,
+    {
+        LightQueue {
+            inner: Arc::new_cyclic(|this| Light {
+                state: Mutex::new(LightState {
+                    heap: BinaryHeap::new(),
+                    next_seq: 0,
+                    registered: None,
+                }),
+                on_expired: Box::new(on_expired),
+                heads: self.heads.clone(),
+                this: this.clone(),
+            }),
+        }
+    }
+
+    /// Head deadlines registered and not expired yet; at most one per queue
+    /// plus stale ones left behind by removals.
+    pub fn registered(&self) -> usize {
+        self.heads.len()
+    }
+
+    /// Stops the driver thread; pending elements never expire.
+    pub fn stop(&mut self) {
+        self.stop.store(true, AtomicOrdering::SeqCst);
+        self.heads.wake_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for SharedTimer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// An element of a [`LightQueue`]: deadline, insertion order, element.
+type Pending<T> = Reverse<(Instant, u64, Unordered<T>)>;
+
+/// Makes elements of any type storable in the heap, which orders by the
+/// deadline and sequence number only.
+struct Unordered<T>(Arc<T>);
+
+unordered!(Unordered<T>);
+
+struct LightState<T> {
+    heap: BinaryHeap<Pending<T>>,
+    next_seq: u64,
+    /// The earliest head deadline registered with the timer and not expired.
+    registered: Option<Instant>,
+}
+
+struct Light<T> {
+    state: Mutex<LightState<T>>,
+    on_expired: Box<dyn Fn(Arc<T>) + Send + Sync>,
+    heads: DelayQueue<HeadDue>,
+    /// The queue itself, for registering with the timer.
+    this: Weak<Light<T>>,
+}
+
+impl<T: Send + Sync + 'static> Light<T> {
+    /// Registers the head with the timer unless an earlier deadline already
+    /// is; must be called with the state locked.
+    fn register_head(&self, state: &mut LightState<T>) {
+        let head = match state.heap.peek() {
+            Some(Reverse((deadline, _, _))) => *deadline,
+            None => return,
+        };
+        if state
+            .registered
+            .is_some_and(|registered| registered <= head)
+        {
+            return;
+        }
+        let queue: Weak<dyn Expire> = self.this.clone();
+        state.registered = Some(head);
+        self.heads.put_at(
+            head,
+            Arc::new(HeadDue {
+                deadline: head,
+                queue,
+            }),
+        );
+    }
+}
+
+impl<T: Send + Sync + 'static> Expire for Light<T> {
+    fn expire(&self) {
+        let now = self.heads.clock.now();
+        let mut expired = Vec::new();
+        {
+            let mut state = self.state.lock();
+            while let Some(Reverse((deadline, _, _))) = state.heap.peek() {
+                if *deadline > now {
+                    break;
+                }
+                if let Some(Reverse((_, _, item))) = state.heap.pop() {
+                    expired.push(item.0);
+                }
+            }
+            state.registered = None;
+            self.register_head(&mut state);
+        }
+        for item in expired {
+            (self.on_expired)(item);
+        }
+    }
+}
+
+/// A queue without a thread of its own, see [`SharedTimer::queue`]. Dropping
+/// it drops its pending elements.
+pub struct LightQueue<T> {
+    inner: Arc<Light<T>>,
+}
+
+impl<T> Clone for LightQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: Delayed + Send + Sync + 'static> LightQueue<T> {
+    /// Inserts `t`, due after its `delayed`.
+    pub fn put(&self, t: T) {
+        let deadline = deadline_after(self.inner.heads.clock.now(), t.delayed());
+        let mut state = self.inner.state.lock();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state
+            .heap
+            .push(Reverse((deadline, seq, Unordered(Arc::new(t)))));
+        self.inner.register_head(&mut state);
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.state.lock().heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every pending element and returns how many there were.
+    pub fn clear(&self) -> usize {
+        let mut state = self.inner.state.lock();
+        let cleared = state.heap.len();
+        state.heap.clear();
+        cleared
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::mpsc, time::Duration};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Session(u32, i64);
+
+    impl Delayed for Session {
+        fn delayed(&self) -> i64 {
+            self.1
+        }
+    }
+
+    #[test]
+    fn test_shared_timer() {
+        let timer = SharedTimer::new();
+        let (sender, expired) = mpsc::channel();
+        let queues: Vec<_> = (0..100)
+            .map(|_| {
+                let sender = Mutex::new(sender.clone());
+                timer.queue(move |session: Arc<Session>| {
+                    sender.lock().send(session.0).unwrap();
+                })
+            })
+            .collect();
+        for (id, queue) in queues.iter().enumerate() {
+            let id = id as u32;
+            queue.put(Session(id, (100 - id as i64) * 2_000_000));
+            queue.put(Session(id, 3_600_000_000_000));
+        }
+        assert_eq!(100, timer.registered());
+
+        let order: Vec<_> = (0..100)
+            .map(|_| expired.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        assert_eq!((0..100).rev().collect::<Vec<_>>(), order);
+        assert!(queues.iter().all(|queue| queue.len() == 1));
+        assert_eq!(100, timer.registered());
+
+        drop(queues);
+        assert!(expired.recv_timeout(Duration::from_millis(10)).is_err());
+    }
+}
@@ -0,0 +1,49 @@
+//! The condition variables of a queue. With the `tokio` feature they also
+//! wake the tasks of an [`AsyncDelayQueue`](crate::AsyncDelayQueue) waiting
+//! on the same queue.
+
+use std::time::Instant;
+
+use parking_lot::{Condvar, MutexGuard, WaitTimeoutResult};
+
+#[derive(Default)]
+pub(crate) struct Signal {
+    condvar: Condvar,
+    #[cfg(feature = "tokio")]
+    tasks: tokio::sync::Notify,
+}
+
+impl Signal {
+    /// Wakes one blocked thread. Every waiting task is woken, since tasks
+    /// do not take turns like threads do.
+    pub(crate) fn notify_one(&self) {
+        self.condvar.notify_one();
+        #[cfg(feature = "tokio")]
+        self.tasks.notify_waiters();
+    }
+
+    pub(crate) fn notify_all(&self) {
+        self.condvar.notify_all();
+        #[cfg(feature = "tokio")]
+        self.tasks.notify_waiters();
+    }
+
+    pub(crate) fn wait<T>(&self, guard: &mut MutexGuard<'_, T>) {
+        self.condvar.wait(guard);
+    }
+
+    pub(crate) fn wait_until<T>(
+        &self,
+        guard: &mut MutexGuard<'_, T>,
+        until: Instant,
+    ) -> WaitTimeoutResult {
+        self.condvar.wait_until(guard, until)
+    }
+
+    /// Resolves on the next notification; enable it before checking the
+    /// queue so a notification in between is not missed.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn notified(&self) -> tokio::sync::futures::Notified<'_> {
+        self.tasks.notified()
+    }
+}
@@ -0,0 +1,263 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    chunks::{ChunkedEntries, Chunks, ViewIntoIter, ViewIter},
+    failpoints, DelayQueue, Delayed, Entry,
+};
+
+/// Entries copied per lock hold when a view is built from the heap.
+const COPY_SLICE: usize = 1024;
+
+/// Times copying a view from the heap starts over because the queue changed
+/// before it copies the rest under one lock hold.
+const COPY_RESTARTS: usize = 3;
+
+/// An immutable, shareable copy of the queue contents in delivery order.
+///
+/// Views are made of chunks of up to 128 elements. By default they are
+/// cached per queue version, so repeated readers share one copy and only the
+/// first reader after a change touches the queue lock. Queues built with
+/// [`chunked_views`](crate::DelayQueueBuilder::chunked_views) instead keep
+/// the chunks up to date as elements come and go, so taking a view costs
+/// O(1) and consecutive views share every chunk that did not change.
+pub struct QueueView<T> {
+    version: u64,
+    len: usize,
+    chunks: Chunks<T>,
+}
+
+impl<T> Clone for QueueView<T> {
+    fn clone(&self) -> Self {
+        Self {
+            version: self.version,
+            len: self.len,
+            chunks: Arc::clone(&self.chunks),
+        }
+    }
+}
+
+impl<T> QueueView<T> {
+    /// The queue version this view was taken at.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> ViewIter<'_, T> {
+        ViewIter::new(&self.chunks, self.len)
+    }
+}
+
+impl<T> IntoIterator for QueueView<T> {
+    type Item = Arc<T>;
+    type IntoIter = ViewIntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ViewIntoIter::new(self.chunks, self.len)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a QueueView<T> {
+    type Item = &'a Arc<T>;
+    type IntoIter = ViewIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The view cached by [`DelayQueue::view`].
+pub(crate) struct ViewCache<T> {
+    /// Set for queues that keep their chunks up to date, whose views are
+    /// never cached.
+    chunked: bool,
+    /// The version of the cached view plus one, or zero if none is cached,
+    /// so deliveries can skip the lock when there is nothing to drop.
+    cached: AtomicU64,
+    view: Mutex<Option<QueueView<T>>>,
+}
+
+impl<T> ViewCache<T> {
+    pub(crate) fn new(chunked: bool) -> Self {
+        Self {
+            chunked,
+            cached: AtomicU64::new(0),
+            view: Mutex::new(None),
+        }
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Sync + Send,
+{
+    /// Returns every queued element in delivery order.
+    ///
+    /// Only the `Arc` handles are copied, never the elements themselves.
+    pub fn snapshot(&self) -> Vec<Arc<T>> {
+        self.sorted_handles().1
+    }
+
+    /// Returns a cached copy of the queue contents.
+    ///
+    /// While the queue is unchanged this neither copies nor takes the queue
+    /// lock, so metrics and dashboards can poll it without blocking
+    /// producers and consumers. The first call after a change copies every
+    /// `Arc` handle, a bounded slice per lock hold, and sorts them after
+    /// releasing the lock.
+    /// Deliveries drop the cached copy, so it does not keep delivered
+    /// elements alive; views already handed out still do.
+    ///
+    /// With [`chunked_views`](crate::DelayQueueBuilder::chunked_views) every
+    /// call takes the queue lock for O(1) instead and nothing is cached.
+    pub fn view(&self) -> QueueView<T> {
+        if self.view.chunked {
+            let guard = self.queue.lock();
+            if let Some(chunks) = guard.chunks.as_ref() {
+                return QueueView {
+                    version: guard.version.load(Ordering::Acquire),
+                    len: chunks.len(),
+                    chunks: chunks.share(),
+                };
+            }
+        }
+        let current = self.version.load(Ordering::Acquire);
+        if let Some(view) = self.view.view.lock().as_ref() {
+            if view.version == current {
+                return view.clone();
+            }
+        }
+        let (version, entries) = self.sorted_entries();
+        let chunked = ChunkedEntries::from_sorted(entries);
+        let view = QueueView {
+            version,
+            len: chunked.len(),
+            chunks: chunked.share(),
+        };
+        let mut cache = self.view.view.lock();
+        if cache.as_ref().is_none_or(|cached| cached.version < version) {
+            *cache = Some(view.clone());
+            self.view.cached.store(version + 1, Ordering::Release);
+        }
+        view
+    }
+
+    /// Drops the cached view if the queue changed since it was taken.
+    ///
+    /// Called on the delivery path, so it never waits for the cache lock:
+    /// without a cached view it does not touch the lock at all, and while a
+    /// reader holds it the stale view is left for a later delivery to drop.
+    pub(crate) fn drop_stale_view(&self) {
+        let cached = self.view.cached.load(Ordering::Acquire);
+        if cached == 0 || cached == self.version.load(Ordering::Acquire) + 1 {
+            return;
+        }
+        if let Some(mut cache) = self.view.view.try_lock() {
+            if cache
+                .as_ref()
+                .is_some_and(|view| view.version != self.version.load(Ordering::Acquire))
+            {
+                *cache = None;
+                self.view.cached.store(0, Ordering::Release);
+            }
+        }
+    }
+
+    /// Streams every queued element in delivery order, as they were at one
+    /// instant.
+    ///
+    /// With [`chunked_views`](crate::DelayQueueBuilder::chunked_views) the
+    /// stream shares the queue's chunks and nothing is copied. Otherwise the
+    /// entries are copied a bounded slice at a time like for
+    /// [`view`](Self::view), so producers and consumers are never held up
+    /// for more than one slice, and sorted before the first element is
+    /// handed out.
+    pub fn snapshot_stream(&self) -> ViewIntoIter<T> {
+        self.view().into_iter()
+    }
+
+    /// Writes every queued element in delivery order through `write`, from
+    /// [`snapshot_stream`](Self::snapshot_stream), so encoding and I/O
+    /// never hold the lock. Returns the number of elements written.
+    pub fn snapshot_streaming<W, F>(&self, writer: &mut W, mut write: F) -> io::Result<usize>
+    where
+        W: io::Write,
+        F: FnMut(&mut W, &T) -> io::Result<()>,
+    {
+        let fails_after = self.queue.lock().persistence_fails_after();
+        let mut written = 0;
+        for item in self.snapshot_stream() {
+            if fails_after == Some(written) {
+                return Err(failpoints::persistence_failure());
+            }
+            write(writer, &item)?;
+            written += 1;
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+
+    fn sorted_handles(&self) -> (u64, Vec<Arc<T>>) {
+        let (version, entries) = self.sorted_entries();
+        (
+            version,
+            entries.into_iter().map(|entry| entry.item).collect(),
+        )
+    }
+
+    /// Copies of the queued entries in delivery order, and the queue version
+    /// they were taken at.
+    ///
+    /// The entries are copied `COPY_SLICE` at a time, releasing the lock in
+    /// between. If the queue changes between two slices the copy starts
+    /// over; after `COPY_RESTARTS` such restarts the rest is copied in one
+    /// go, so a busy queue still gets a consistent copy.
+    pub(crate) fn sorted_entries(&self) -> (u64, Vec<Entry<T>>) {
+        let mut entries = Vec::new();
+        let mut copied_at = None;
+        let mut restarts = 0;
+        let version = loop {
+            let guard = self.queue.lock();
+            let version = guard.version.load(Ordering::Acquire);
+            if copied_at.is_some_and(|copied_at| copied_at != version) {
+                entries.clear();
+                restarts += 1;
+            }
+            copied_at = Some(version);
+            entries.reserve(guard.queue.len() - entries.len());
+            let slice = if restarts < COPY_RESTARTS {
+                COPY_SLICE
+            } else {
+                usize::MAX
+            };
+            entries.extend(
+                guard
+                    .queue
+                    .iter()
+                    .skip(entries.len())
+                    .take(slice)
+                    .map(|entry| entry.0.clone()),
+            );
+            if entries.len() == guard.queue.len() {
+                break version;
+            }
+        };
+        entries.sort();
+        (version, entries)
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    hint,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use crate::{DelayQueue, Delayed, Head};
+
+/// Spins between lock checks while the queue is empty, so a close is
+/// noticed.
+const EMPTY_SPINS: u32 = 1 << 10;
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    /// Like [`take_or_closed`](DelayQueue::take_or_closed), but busy-polls
+    /// the head deadline instead of sleeping on a condition variable.
+    ///
+    /// This removes wakeup latency entirely at the cost of one CPU core
+    /// running at 100% for as long as the call blocks, even while the queue
+    /// is idle. Only use it with a core reserved for the caller; pinning the
+    /// thread is left to the caller.
+    pub fn take_busy(&self) -> Option<Arc<T>> {
+        loop {
+            let seen = self.version.load(Ordering::Acquire);
+            let deadline = match self.poll_busy()? {
+                Ok(item) => return Some(item),
+                Err(deadline) => deadline,
+            };
+            self.spin_while_unchanged(seen, deadline);
+        }
+    }
+
+    /// Like [`take_or_closed`](DelayQueue::take_or_closed), but spins instead
+    /// of sleeping once the head is due within `spin`.
+    ///
+    /// Decided afresh on every iteration: far deadlines cost no CPU, and
+    /// imminent ones skip the wakeup latency of a condition variable for at
+    /// most `spin` of busy CPU per delivery.
+    pub fn take_spin_then_park(&self, spin: Duration) -> Option<Arc<T>> {
+        loop {
+            let seen = self.version.load(Ordering::Acquire);
+            let deadline = match self.poll_busy()? {
+                Ok(item) => return Some(item),
+                Err(deadline) => deadline,
+            };
+            let now = self.clock.now();
+            match deadline {
+                Some(deadline) if deadline.saturating_duration_since(now) <= spin => {
+                    self.spin_while_unchanged(seen, Some(deadline));
+                }
+                _ => {
+                    let cutoff = deadline.map(|deadline| deadline - spin);
+                    if let Some(delivery) = self.take_before(cutoff, None) {
+                        return Some(delivery.item);
+                    }
+                    // Without a cutoff only a closed and empty queue ends
+                    // the wait.
+                    cutoff?;
+                }
+            }
+        }
+    }
+
+    /// Delivers the head as `Ok` if it has expired, or else returns its
+    /// deadline, if any, as `Err`. `None` once the queue is closed and empty.
+    fn poll_busy(&self) -> Option<Result<Arc<T>, Option<Instant>>> {
+        let mut guard = self.queue.lock();
+        match self.poll_head(&mut guard) {
+            Head::Ready(delivery) => {
+                if let Some(delay) = self.hand_out(guard, &delivery) {
+                    std::thread::sleep(delay);
+                }
+                Some(Ok(delivery.item))
+            }
+            Head::Empty if guard.closed => None,
+            Head::Empty => Some(Err(None)),
+            Head::Pending(deadline) => Some(Err(Some(deadline))),
+        }
+    }
+
+    /// Spins until `deadline` passes or the queue changes since `seen`.
+    fn spin_while_unchanged(&self, seen: u64, deadline: Option<Instant>) {
+        let mut spins = 0;
+        while self.version.load(Ordering::Acquire) == seen {
+            match deadline {
+                Some(deadline) if self.clock.now() >= deadline => return,
+                None if spins >= EMPTY_SPINS => return,
+                _ => {}
+            }
+            spins += 1;
+            hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{thread, time::Duration};
+
+    use super::*;
+    use crate::{testing::Fixed, DeadlineMode};
+
+    #[test]
+    fn test_take_busy() {
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let start = Instant::now();
+        queue.try_put(Fixed(5_000_000)).unwrap();
+        assert_eq!(Fixed(5_000_000), *queue.take_busy().unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(5));
+
+        let closer = {
+            let queue = queue.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                queue.close();
+            })
+        };
+        assert!(queue.take_busy().is_none());
+        closer.join().unwrap();
+    }
+
+    #[test]
+    fn test_take_spin_then_park() {
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        assert_eq!(None, queue.duration_until_next());
+        queue.try_put(Fixed(20_000_000)).unwrap();
+        queue.try_put(Fixed(-1)).unwrap();
+        assert_eq!(Some(Duration::ZERO), queue.duration_until_next());
+
+        let spin = Duration::from_millis(5);
+        assert_eq!(Fixed(-1), *queue.take_spin_then_park(spin).unwrap());
+        let start = Instant::now();
+        assert_eq!(Fixed(20_000_000), *queue.take_spin_then_park(spin).unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(15));
+        queue.close();
+        assert!(queue.take_spin_then_park(spin).is_none());
+    }
+}
@@ -0,0 +1,119 @@
+use std::{sync::Arc, time::Instant};
+
+use crate::{delayed_until, DeadlineMode, DelayQueue, Delayed};
+
+/// One stage of an element of a [`StagedQueue`], as stored in a core queue.
+struct Stage<T> {
+    deadline: Instant,
+    item: Arc<T>,
+}
+
+impl<T> Delayed for Stage<T> {
+    fn delayed(&self) -> i64 {
+        delayed_until(Instant::now(), self.deadline)
+    }
+}
+
+// Stages with equal deadlines are delivered in insertion order.
+unordered!(Stage<T>);
+
+/// A delay queue of elements with two deadlines: each element is first
+/// handed to [`take_prepare`](Self::take_prepare) at its prepare time, e.g.
+/// to warm up, and then to [`take`](Self::take) at its fire time.
+pub struct StagedQueue<T> {
+    prepare: DelayQueue<Stage<T>>,
+    fire: DelayQueue<Stage<T>>,
+}
+
+impl<T> Default for StagedQueue<T> {
+    fn default() -> Self {
+        Self {
+            prepare: DelayQueue::with_deadline_mode(DeadlineMode::Captured),
+            fire: DelayQueue::with_deadline_mode(DeadlineMode::Captured),
+        }
+    }
+}
+
+impl<T> Clone for StagedQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            prepare: self.prepare.clone(),
+            fire: self.fire.clone(),
+        }
+    }
+}
+
+impl<T: Send + Sync> StagedQueue<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Elements not fired yet, prepared or not.
+    pub fn len(&self) -> usize {
+        self.fire.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fire.is_empty()
+    }
+
+    /// Schedules `item` for preparation at `prepare_at` and delivery at
+    /// `fire_at`, both measured on the queue's clock. A prepare time after
+    /// the fire time is moved to the fire time. Dropped on a closed queue.
+    pub fn put(&self, item: T, prepare_at: Instant, fire_at: Instant) {
+        let item = Arc::new(item);
+        let prepare_at = prepare_at.min(fire_at);
+        let stage = |deadline| {
+            Arc::new(Stage {
+                deadline,
+                item: Arc::clone(&item),
+            })
+        };
+        if self.fire.put_at(fire_at, stage(fire_at)) {
+            self.prepare.put_at(prepare_at, stage(prepare_at));
+        }
+    }
+
+    /// Blocks until an element reaches its prepare time and returns it;
+    /// `None` once the queue is closed and every element was prepared.
+    pub fn take_prepare(&self) -> Option<Arc<T>> {
+        let stage = self.prepare.take_or_closed()?;
+        Some(Arc::clone(&stage.item))
+    }
+
+    /// Blocks until an element reaches its fire time and returns it; `None`
+    /// once the queue is closed and empty.
+    pub fn take(&self) -> Option<Arc<T>> {
+        let stage = self.fire.take_or_closed()?;
+        Some(Arc::clone(&stage.item))
+    }
+
+    /// Stops accepting new elements, see [`DelayQueue::close`].
+    pub fn close(&self) {
+        self.prepare.close();
+        self.fire.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_staged() {
+        let queue = StagedQueue::new();
+        let now = Instant::now();
+        queue.put("report", now, now + Duration::from_millis(20));
+        queue.put("late", now + Duration::from_secs(60), now);
+
+        assert_eq!("report", *queue.take_prepare().unwrap());
+        assert_eq!("late", *queue.take_prepare().unwrap());
+        assert_eq!("late", *queue.take().unwrap());
+        assert_eq!("report", *queue.take().unwrap());
+        assert!(now.elapsed() >= Duration::from_millis(20));
+        queue.close();
+        assert!(queue.take_prepare().is_none());
+    }
+}
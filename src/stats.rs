@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use crate::{timer, DelayQueue, Delayed, Headers};
+
+/// Point-in-time counters of a queue.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// See [`DelayQueue::name`].
+    pub name: Option<String>,
+    /// See [`DelayQueue::labels`].
+    pub labels: Headers,
+    /// Elements currently queued.
+    pub len: usize,
+    /// `None` for unbounded queues.
+    pub capacity: Option<usize>,
+    /// Slots held by prepared but uncommitted elements.
+    pub reserved: usize,
+    /// Elements inserted since creation.
+    pub enqueued: u64,
+    /// Elements delivered since creation.
+    pub delivered: u64,
+    /// Times a waiting leader's timer fired with nothing deliverable, so
+    /// another taker was woken as a safety net.
+    pub renotified: u64,
+    /// Undecodable entries quarantined by
+    /// [`load_quarantining`](DelayQueue::load_quarantining).
+    pub quarantined: u64,
+    /// How late timed waits wake up on this host, once measured by the
+    /// `calibrate` feature.
+    pub timer_resolution: Option<Duration>,
+    /// How far ahead of their deadline elements are currently delivered;
+    /// zero unless lateness compensation is enabled on the builder.
+    pub pre_fire: Duration,
+}
+
+impl Stats {
+    /// Adds the counters of `other` to `self`, keeping the name and labels
+    /// of `self`.
+    ///
+    /// The merged capacity is only known if every merged queue is bounded.
+    pub fn merge(&mut self, other: &Stats) {
+        self.len += other.len;
+        self.capacity = self
+            .capacity
+            .zip(other.capacity)
+            .map(|(a, b)| a.saturating_add(b));
+        self.reserved += other.reserved;
+        self.enqueued += other.enqueued;
+        self.delivered += other.delivered;
+        self.renotified += other.renotified;
+        self.quarantined += other.quarantined;
+        self.timer_resolution = self.timer_resolution.max(other.timer_resolution);
+        self.pre_fire = self.pre_fire.max(other.pre_fire);
+    }
+}
+
+impl<T: Delayed> DelayQueue<T> {
+    pub fn stats(&self) -> Stats {
+        let guard = self.queue.lock();
+        Stats {
+            name: self.name().map(str::to_owned),
+            labels: self.labels().clone(),
+            len: guard.queue.len(),
+            capacity: guard.capacity,
+            reserved: guard.reserved,
+            enqueued: guard.next_seq,
+            delivered: guard.delivered,
+            renotified: guard.renotified,
+            quarantined: guard.quarantined,
+            timer_resolution: timer::resolution(),
+            pre_fire: guard.pre_fire(),
+        }
+    }
+}
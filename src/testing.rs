@@ -0,0 +1,271 @@
+//! Test doubles and helpers for code that embeds a [`DelayQueue`](crate::DelayQueue).
+//!
+//! [`MockDelayQueue`] stands in for a queue in unit tests, delivering only
+//! when told to. With the `proptest` feature, [`ops`] generates random
+//! operation sequences, [`ops_with`] does the same over elements drawn from
+//! a caller's own strategy, and [`check`] applies them both to a real queue
+//! driven by a [`SimClock`](crate::SimClock) and to a sorted-vec reference
+//! model, failing on the first divergence.
+
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{Delayed, PutError, Scheduler, Stats};
+
+#[cfg(feature = "proptest")]
+mod model;
+
+#[cfg(feature = "proptest")]
+pub use model::{check, op, op_with, ops, ops_with, Model, ModelItem, Op};
+
+/// A [`Scheduler`] that never blocks and never delivers on its own, so
+/// scheduling logic can be unit-tested without threads or sleeps.
+///
+/// Deadlines are kept relative to a virtual clock that starts at zero and
+/// only moves with [`advance`](Self::advance) and
+/// [`deliver_next`](Self::deliver_next). Delivered elements are handed out
+/// by `take_or_closed` and `take_until`, which return `None` instead of
+/// blocking when nothing was delivered.
+pub struct MockDelayQueue<T> {
+    state: Mutex<MockState<T>>,
+}
+
+struct MockState<T> {
+    now: Duration,
+    /// `(deadline, item)` in delivery order.
+    scheduled: Vec<(Duration, Arc<T>)>,
+    delivered: VecDeque<Arc<T>>,
+    capacity: Option<usize>,
+    closed: bool,
+    enqueued: u64,
+    taken: u64,
+}
+
+impl<T> Default for MockDelayQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> MockDelayQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(MockState {
+                now: Duration::ZERO,
+                scheduled: Vec::new(),
+                delivered: VecDeque::new(),
+                capacity: None,
+                closed: false,
+                enqueued: 0,
+                taken: 0,
+            }),
+        }
+    }
+
+    /// A mock whose `try_put` fails with [`PutError::Full`] once `capacity`
+    /// elements are scheduled.
+    pub fn bounded(capacity: usize) -> Self {
+        let mock = Self::new();
+        mock.state.lock().capacity = Some(capacity);
+        mock
+    }
+
+    /// The virtual time elapsed since the mock was created.
+    pub fn now(&self) -> Duration {
+        self.state.lock().now
+    }
+
+    /// Every scheduled element with its deadline, in delivery order.
+    pub fn scheduled(&self) -> Vec<(Duration, Arc<T>)> {
+        self.state.lock().scheduled.clone()
+    }
+
+    /// Delivers the next scheduled element, moving the virtual clock to its
+    /// deadline if that is later, and returns it.
+    pub fn deliver_next(&self) -> Option<Arc<T>> {
+        let mut state = self.state.lock();
+        if state.scheduled.is_empty() {
+            return None;
+        }
+        let (deadline, item) = state.scheduled.remove(0);
+        state.now = state.now.max(deadline);
+        state.delivered.push_back(Arc::clone(&item));
+        Some(item)
+    }
+
+    /// Moves the virtual clock forward by `by` and delivers every element
+    /// that is due, returning how many were.
+    pub fn advance(&self, by: Duration) -> usize {
+        let mut state = self.state.lock();
+        state.now += by;
+        let now = state.now;
+        let due = state
+            .scheduled
+            .iter()
+            .take_while(|(deadline, _)| *deadline <= now)
+            .count();
+        let items: Vec<_> = state.scheduled.drain(..due).map(|(_, item)| item).collect();
+        state.delivered.extend(items);
+        due
+    }
+}
+
+impl<T: PartialEq + Debug> MockDelayQueue<T> {
+    /// Panics unless an element equal to `item` is scheduled at `at` on the
+    /// virtual clock.
+    #[track_caller]
+    pub fn assert_scheduled(&self, item: &T, at: Duration) {
+        let state = self.state.lock();
+        let found = state
+            .scheduled
+            .iter()
+            .any(|(deadline, scheduled)| **scheduled == *item && *deadline == at);
+        assert!(
+            found,
+            "{:?} is not scheduled at {:?}; scheduled: {:?}",
+            item, at, state.scheduled
+        );
+    }
+}
+
+impl<T: Delayed> Scheduler<T> for MockDelayQueue<T> {
+    fn try_put(&self, item: T) -> Result<(), PutError<T>> {
+        let mut state = self.state.lock();
+        if state.closed {
+            return Err(PutError::Closed(item));
+        }
+        if state
+            .capacity
+            .is_some_and(|capacity| state.scheduled.len() >= capacity)
+        {
+            return Err(PutError::Full(item));
+        }
+        let delayed = Duration::from_nanos(item.delayed().max(0) as u64);
+        let deadline = state.now.saturating_add(delayed);
+        // After every element due at the same time, like the real queue.
+        let at = state
+            .scheduled
+            .partition_point(|(scheduled, _)| *scheduled <= deadline);
+        state.scheduled.insert(at, (deadline, Arc::new(item)));
+        state.enqueued += 1;
+        Ok(())
+    }
+
+    fn take_or_closed(&self) -> Option<Arc<T>> {
+        let mut state = self.state.lock();
+        let item = state.delivered.pop_front()?;
+        state.taken += 1;
+        Some(item)
+    }
+
+    fn take_until(&self, _cutoff: Instant) -> Option<Arc<T>> {
+        self.take_or_closed()
+    }
+
+    fn cancel(&self, pred: &mut dyn FnMut(&T) -> bool) -> Vec<Arc<T>> {
+        let mut state = self.state.lock();
+        let mut cancelled = Vec::new();
+        state.scheduled.retain(|(_, item)| {
+            let matched = pred(item);
+            if matched {
+                cancelled.push(Arc::clone(item));
+            }
+            !matched
+        });
+        cancelled
+    }
+
+    fn len(&self) -> usize {
+        self.state.lock().scheduled.len()
+    }
+
+    fn close(&self) {
+        self.state.lock().closed = true;
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().closed
+    }
+
+    fn stats(&self) -> Stats {
+        let state = self.state.lock();
+        Stats {
+            len: state.scheduled.len(),
+            capacity: state.capacity,
+            enqueued: state.enqueued,
+            delivered: state.taken,
+            ..Stats::default()
+        }
+    }
+}
+
+/// An element with a constant delay in nanoseconds, shared by the unit
+/// tests of the crate.
+#[cfg(test)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Fixed(pub(crate) i64);
+
+#[cfg(test)]
+impl Delayed for Fixed {
+    fn delayed(&self) -> i64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Reminder(&'static str, i64);
+
+    impl Delayed for Reminder {
+        fn delayed(&self) -> i64 {
+            self.1
+        }
+    }
+
+    /// The code under test: schedules a reminder and a follow-up.
+    fn schedule(scheduler: &dyn Scheduler<Reminder>) {
+        scheduler
+            .try_put(Reminder("follow-up", 3_600_000_000_000))
+            .unwrap();
+        scheduler
+            .try_put(Reminder("remind", 60_000_000_000))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_mock_delay_queue() {
+        let mock = MockDelayQueue::new();
+        schedule(&mock);
+        mock.assert_scheduled(&Reminder("remind", 60_000_000_000), Duration::from_secs(60));
+        assert_eq!(None, mock.take_or_closed());
+
+        assert_eq!(
+            Reminder("remind", 60_000_000_000),
+            *mock.deliver_next().unwrap()
+        );
+        assert_eq!(Duration::from_secs(60), mock.now());
+        assert_eq!("remind", mock.take_or_closed().unwrap().0);
+
+        assert_eq!(0, mock.advance(Duration::from_secs(60)));
+        assert_eq!(1, mock.advance(Duration::from_secs(3600)));
+        assert_eq!("follow-up", mock.take_or_closed().unwrap().0);
+        assert_eq!(2, mock.stats().delivered);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not scheduled")]
+    fn test_assert_scheduled() {
+        let mock = MockDelayQueue::new();
+        schedule(&mock);
+        mock.assert_scheduled(&Reminder("remind", 60_000_000_000), Duration::from_secs(59));
+    }
+}
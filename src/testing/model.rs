@@ -0,0 +1,227 @@
+//! Property-testing a [`DelayQueue`] against a reference model.
+
+use std::{cmp::Ordering, fmt::Debug, time::Duration};
+
+use proptest::{
+    prelude::*,
+    test_runner::{TestCaseError, TestCaseResult},
+};
+
+use crate::{DeadlineMode, DelayQueue, Delayed, SimClock};
+
+/// One step applied to the queue under test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op<T> {
+    /// Insert an element due after its own [`delayed`](Delayed::delayed).
+    Put(T),
+    /// Insert an element with `put_now`.
+    PutNow(T),
+    /// Move virtual time forward.
+    Advance { ms: u64 },
+    /// Take every element that is due.
+    TakeExpired,
+}
+
+/// Strategy for a single [`Op`] on elements drawn from `item`, advancing
+/// time by up to `max_advance_ms`.
+pub fn op_with<T, S>(item: S, max_advance_ms: u64) -> impl Strategy<Value = Op<T>>
+where
+    T: Clone + Debug,
+    S: Strategy<Value = T> + Clone,
+{
+    prop_oneof![
+        4 => item.clone().prop_map(Op::Put),
+        1 => item.prop_map(Op::PutNow),
+        2 => (0..=max_advance_ms).prop_map(|ms| Op::Advance { ms }),
+        2 => Just(Op::TakeExpired),
+    ]
+}
+
+/// Strategy for sequences of up to `max_len` operations on elements drawn
+/// from `item`.
+pub fn ops_with<T, S>(
+    item: S,
+    max_len: usize,
+    max_advance_ms: u64,
+) -> impl Strategy<Value = Vec<Op<T>>>
+where
+    T: Clone + Debug,
+    S: Strategy<Value = T> + Clone,
+{
+    proptest::collection::vec(op_with(item, max_advance_ms), 0..=max_len)
+}
+
+/// Strategy for a single [`Op`] on [`ModelItem`]s, with delays up to
+/// `max_delay_ms`.
+pub fn op(max_delay_ms: u64) -> impl Strategy<Value = Op<ModelItem>> {
+    op_with(model_item(max_delay_ms), max_delay_ms)
+}
+
+/// Strategy for sequences of up to `max_len` operations on [`ModelItem`]s.
+pub fn ops(max_len: usize, max_delay_ms: u64) -> impl Strategy<Value = Vec<Op<ModelItem>>> {
+    ops_with(model_item(max_delay_ms), max_len, max_delay_ms)
+}
+
+fn model_item(max_delay_ms: u64) -> impl Strategy<Value = ModelItem> + Clone {
+    (any::<u64>(), 0..=max_delay_ms).prop_map(|(id, delay_ms)| ModelItem {
+        id,
+        delay: Duration::from_millis(delay_ms).as_nanos() as i64,
+    })
+}
+
+/// The element type generated by [`ops`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelItem {
+    pub id: u64,
+    delay: i64,
+}
+
+impl Delayed for ModelItem {
+    fn delayed(&self) -> i64 {
+        self.delay
+    }
+}
+
+impl Ord for ModelItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl PartialOrd for ModelItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Reference model: a vector kept sorted by delivery order.
+#[derive(Debug)]
+pub struct Model<T> {
+    /// Virtual nanoseconds since the start.
+    now: i128,
+    next_seq: u64,
+    /// `(urgent order, deadline, item, seq)`, sorted, matching
+    /// [`OrderingMode::StrictDeadline`](crate::OrderingMode::StrictDeadline).
+    items: Vec<(u64, i128, T, u64)>,
+    urgent: u64,
+}
+
+impl<T> Default for Model<T> {
+    fn default() -> Self {
+        Self {
+            now: 0,
+            next_seq: 0,
+            items: Vec::new(),
+            urgent: 0,
+        }
+    }
+}
+
+impl<T: Delayed> Model<T> {
+    fn insert(&mut self, item: T, urgent: bool) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let key = if urgent {
+            self.urgent += 1;
+            (self.urgent, self.now, item, seq)
+        } else {
+            let deadline = self.now + i128::from(item.delayed());
+            (u64::MAX, deadline, item, seq)
+        };
+        let at = self.items.partition_point(|entry| *entry < key);
+        self.items.insert(at, key);
+    }
+
+    fn take_expired(&mut self) -> Vec<T> {
+        let due = self
+            .items
+            .iter()
+            .take_while(|(urgent, deadline, ..)| *urgent != u64::MAX || *deadline <= self.now)
+            .count();
+        self.items
+            .drain(..due)
+            .map(|(_, _, item, _)| item)
+            .collect()
+    }
+}
+
+/// Applies `ops` to a real queue and to the [`Model`], failing on the first
+/// difference in delivered elements or length.
+///
+/// Works for any element type; its deadline is taken from `delayed` once,
+/// at insert, as in [`DeadlineMode::Captured`]. Delays so large that the
+/// platform's `Instant` saturates are not modelled.
+pub fn check<T>(ops: &[Op<T>]) -> TestCaseResult
+where
+    T: Delayed + Clone + Debug + Send + Sync + 'static,
+{
+    let clock = SimClock::new();
+    let queue = DelayQueue::with_clock(DeadlineMode::Captured, clock.clone());
+    let mut model = Model::default();
+    for op in ops {
+        match op {
+            Op::Put(item) => {
+                model.insert(item.clone(), false);
+                queue
+                    .try_put(item.clone())
+                    .map_err(|_| TestCaseError::fail("unbounded queue is full"))?;
+            }
+            Op::PutNow(item) => {
+                model.insert(item.clone(), true);
+                queue.put_now(item.clone());
+            }
+            Op::Advance { ms } => {
+                model.now += i128::from(*ms) * 1_000_000;
+                clock.advance(Duration::from_millis(*ms));
+            }
+            Op::TakeExpired => {
+                let expected = model.take_expired();
+                let mut actual = Vec::new();
+                while let Some(delivery) = queue.take_expired() {
+                    actual.push((*delivery.item).clone());
+                }
+                prop_assert_eq!(expected, actual, "after {:?}", op);
+            }
+        }
+        prop_assert_eq!(model.items.len(), queue.len(), "after {:?}", op);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A caller-defined element: negative delays are already expired, and
+    /// equal deadlines are broken by name.
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    struct Task {
+        name: char,
+        delay: i64,
+    }
+
+    impl Delayed for Task {
+        fn delayed(&self) -> i64 {
+            self.delay
+        }
+    }
+
+    fn task() -> impl Strategy<Value = Task> + Clone {
+        (proptest::char::range('a', 'e'), -5i64..=20).prop_map(|(name, delay_ms)| Task {
+            name,
+            delay: delay_ms * 1_000_000,
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_model(ops in ops(64, 100)) {
+            check(&ops)?;
+        }
+
+        #[test]
+        fn test_model_with(ops in ops_with(task(), 64, 10)) {
+            check(&ops)?;
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{delayed_until, instant_after, DeadlineMode, DelayQueue, Delayed, QueueConfig};
+
+/// An element due for another attempt, handed out by [`RetryTiers`].
+#[derive(Debug)]
+pub struct RetryAttempt<T> {
+    /// The index of the tier the element waited in.
+    pub tier: usize,
+    pub item: Arc<T>,
+}
+
+/// An element waiting in a tier, as stored in the core queue.
+struct Waiting<T> {
+    tier: usize,
+    deadline: Instant,
+    item: Arc<T>,
+}
+
+impl<T> Delayed for Waiting<T> {
+    fn delayed(&self) -> i64 {
+        delayed_until(Instant::now(), self.deadline)
+    }
+}
+
+// Elements with equal deadlines are delivered in insertion order.
+unordered!(Waiting<T>);
+
+/// Retries failed elements with growing delays: an element entering with
+/// [`retry`](Self::retry) is handed out again after the first tier's delay,
+/// and each time it is reported [`failed`](Self::failed) it moves down to
+/// the next tier. After the last tier it is dead-lettered.
+pub struct RetryTiers<T> {
+    delays: Arc<[Duration]>,
+    queue: DelayQueue<Waiting<T>>,
+    dead_letters: Arc<Mutex<Vec<Arc<T>>>>,
+}
+
+impl<T> Clone for RetryTiers<T> {
+    fn clone(&self) -> Self {
+        Self {
+            delays: Arc::clone(&self.delays),
+            queue: self.queue.clone(),
+            dead_letters: Arc::clone(&self.dead_letters),
+        }
+    }
+}
+
+impl<T: Send + Sync> RetryTiers<T> {
+    /// Tiers waiting `delays` each, e.g. one minute, ten minutes and an
+    /// hour.
+    pub fn new(delays: &[Duration]) -> Self {
+        Self {
+            delays: delays.into(),
+            queue: DelayQueue::with_deadline_mode(DeadlineMode::Captured),
+            dead_letters: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Tiers waiting `config.retry_delays_ms` each, on a queue with the
+    /// capacity and rate limit of `config`. The deadline mode and ordering
+    /// of `config` are ignored.
+    pub fn from_config(config: &QueueConfig) -> Self
+    where
+        T: 'static,
+    {
+        let delays = config
+            .retry_delays_ms
+            .iter()
+            .map(|ms| Duration::from_millis(*ms))
+            .collect::<Vec<_>>();
+        Self {
+            delays: delays.into(),
+            queue: DelayQueue::builder()
+                .config(config)
+                .deadline_mode(DeadlineMode::Captured)
+                .build(),
+            dead_letters: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Elements waiting in any tier.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Puts a failed element into the first tier. Returns `false` if it was
+    /// dead-lettered right away because there are no tiers, or dropped
+    /// because the tiers are closed.
+    pub fn retry(&self, item: T) -> bool {
+        self.enter(0, Arc::new(item))
+    }
+
+    /// Moves an element whose retry failed again down to the next tier.
+    /// Returns `false` if it was dead-lettered after the last tier, or
+    /// dropped because the tiers are closed.
+    pub fn failed(&self, attempt: RetryAttempt<T>) -> bool {
+        self.enter(attempt.tier + 1, attempt.item)
+    }
+
+    fn enter(&self, tier: usize, item: Arc<T>) -> bool {
+        let delay = match self.delays.get(tier) {
+            Some(delay) => *delay,
+            None => {
+                self.dead_letters.lock().push(item);
+                return false;
+            }
+        };
+        let deadline = instant_after(self.queue.clock.now(), delay);
+        let waiting = Waiting {
+            tier,
+            deadline,
+            item,
+        };
+        self.queue.put_at(deadline, Arc::new(waiting))
+    }
+
+    /// Removes and returns the elements dead-lettered so far, oldest first.
+    pub fn take_dead_letters(&self) -> Vec<Arc<T>> {
+        std::mem::take(&mut *self.dead_letters.lock())
+    }
+
+    /// Blocks until an element is due for another attempt and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tiers are closed and empty.
+    pub fn take(&self) -> RetryAttempt<T> {
+        self.take_or_closed()
+            .expect("take on closed and empty RetryTiers")
+    }
+
+    /// Like [`take`](Self::take), but returns `None` once the tiers are
+    /// closed and empty.
+    pub fn take_or_closed(&self) -> Option<RetryAttempt<T>> {
+        let waiting = self.queue.take_or_closed()?;
+        Some(RetryAttempt {
+            tier: waiting.tier,
+            item: Arc::clone(&waiting.item),
+        })
+    }
+
+    /// Like [`take_or_closed`](Self::take_or_closed), but gives up at
+    /// `cutoff`.
+    pub fn take_until(&self, cutoff: Instant) -> Option<RetryAttempt<T>> {
+        let waiting = self.queue.take_until(cutoff)?;
+        Some(RetryAttempt {
+            tier: waiting.tier,
+            item: Arc::clone(&waiting.item),
+        })
+    }
+
+    /// Stops accepting elements, see [`DelayQueue::close`].
+    pub fn close(&self) {
+        self.queue.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_retry_tiers() {
+        let tiers = RetryTiers::new(&[Duration::ZERO, Duration::from_millis(10)]);
+        assert!(tiers.retry("send email"));
+        let first = tiers.take();
+        assert_eq!((0, "send email"), (first.tier, *first.item));
+
+        assert!(tiers.failed(first));
+        assert!(tiers.take_until(Instant::now()).is_none());
+        let second = tiers.take();
+        assert_eq!(1, second.tier);
+
+        assert!(!tiers.failed(second));
+        assert!(tiers.is_empty());
+        let dead = tiers.take_dead_letters();
+        assert_eq!(
+            vec!["send email"],
+            dead.iter().map(|item| **item).collect::<Vec<_>>()
+        );
+        assert!(tiers.take_dead_letters().is_empty());
+
+        let parked = RetryTiers::new(&[Duration::MAX]);
+        assert!(parked.retry("never"));
+        assert_eq!(1, parked.len());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = QueueConfig {
+            capacity: Some(1),
+            retry_delays_ms: vec![0],
+            ..QueueConfig::default()
+        };
+        let tiers = RetryTiers::from_config(&config);
+        assert!(tiers.retry("charge card"));
+        assert_eq!(Some(1), tiers.queue.capacity());
+        let attempt = tiers.take();
+        assert_eq!((0, "charge card"), (attempt.tier, *attempt.item));
+        assert!(!tiers.failed(attempt));
+        assert_eq!(1, tiers.take_dead_letters().len());
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        mpsc, Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{delayed_until, instant_after, DeadlineMode, DelayQueue, Delayed};
+
+/// A pending timeout, as stored in the core queue.
+struct Timeout<K> {
+    key: K,
+    token: u64,
+    deadline: Instant,
+}
+
+impl<K> Delayed for Timeout<K> {
+    fn delayed(&self) -> i64 {
+        delayed_until(Instant::now(), self.deadline)
+    }
+}
+
+// Timeouts with equal deadlines expire in insertion order.
+unordered!(Timeout<K>);
+
+struct Slot {
+    ttl: Duration,
+    deadline: Instant,
+    /// The token and deadline of the core entry standing for this key; it
+    /// may be earlier than `deadline` after a refresh.
+    token: u64,
+    scheduled: Instant,
+}
+
+struct Slots<K> {
+    slots: HashMap<K, Slot>,
+    next_token: u64,
+}
+
+/// Expires keys that are not refreshed within their TTL, e.g. idle
+/// connections or sessions, and emits them on the channel returned by
+/// [`new`](Self::new).
+///
+/// Refreshing only moves the deadline in the key index; the timeout in the
+/// core queue is pushed back lazily when it comes due, so a refresh costs
+/// O(1) however many keys are tracked. Removed keys likewise stay in the
+/// core queue until their last scheduled deadline.
+pub struct TimeoutMap<K: Hash + Eq + Clone + Send + Sync + 'static> {
+    queue: DelayQueue<Timeout<K>>,
+    slots: Arc<Mutex<Slots<K>>>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<K: Hash + Eq + Clone + Send + Sync + 'static> TimeoutMap<K> {
+    /// Returns the map and the receiving end of its expired keys. Keys
+    /// expiring after the receiver is dropped are discarded.
+    pub fn new() -> (Self, mpsc::Receiver<K>) {
+        let (sender, expired) = mpsc::channel();
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let slots = Arc::new(Mutex::new(Slots {
+            slots: HashMap::new(),
+            next_token: 0,
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let queue: DelayQueue<Timeout<K>> = queue.clone();
+            let slots = Arc::clone(&slots);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while let Some(delivery) = queue.take_unless(&stop) {
+                    let timeout = &delivery.item;
+                    let mut guard = slots.lock();
+                    let deadline = match guard.slots.get(&timeout.key) {
+                        Some(slot) if slot.token == timeout.token => slot.deadline,
+                        _ => continue,
+                    };
+                    if deadline > queue.clock.now() {
+                        let rescheduled = Timeout {
+                            key: timeout.key.clone(),
+                            token: timeout.token,
+                            deadline,
+                        };
+                        if let Some(slot) = guard.slots.get_mut(&timeout.key) {
+                            slot.scheduled = deadline;
+                        }
+                        queue.put_at(deadline, Arc::new(rescheduled));
+                        continue;
+                    }
+                    guard.slots.remove(&timeout.key);
+                    drop(guard);
+                    let _ = sender.send(timeout.key.clone());
+                }
+            })
+        };
+        let map = Self {
+            queue,
+            slots,
+            stop,
+            worker: Some(worker),
+        };
+        (map, expired)
+    }
+
+    /// Keys being tracked.
+    pub fn len(&self) -> usize {
+        self.slots.lock().slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.slots.lock().slots.contains_key(key)
+    }
+
+    /// When `key` expires unless refreshed.
+    pub fn deadline(&self, key: &K) -> Option<Instant> {
+        self.slots.lock().slots.get(key).map(|slot| slot.deadline)
+    }
+
+    /// Tracks `key`, expiring after `ttl` unless refreshed. A key already
+    /// tracked gets the new TTL and is refreshed; returns `false` then.
+    pub fn insert(&self, key: K, ttl: Duration) -> bool {
+        let deadline = instant_after(self.queue.clock.now(), ttl);
+        let mut guard = self.slots.lock();
+        if let Some(slot) = guard.slots.get_mut(&key) {
+            slot.ttl = ttl;
+            slot.deadline = deadline;
+            if slot.scheduled <= deadline {
+                return false;
+            }
+        }
+        let token = guard.next_token;
+        guard.next_token += 1;
+        let inserted = guard
+            .slots
+            .insert(
+                key.clone(),
+                Slot {
+                    ttl,
+                    deadline,
+                    token,
+                    scheduled: deadline,
+                },
+            )
+            .is_none();
+        let timeout = Timeout {
+            key,
+            token,
+            deadline,
+        };
+        self.queue.put_at(deadline, Arc::new(timeout));
+        inserted
+    }
+
+    /// Restarts the TTL of `key`, e.g. on activity on a connection. Returns
+    /// `false` if the key is not tracked.
+    pub fn refresh(&self, key: &K) -> bool {
+        let now = self.queue.clock.now();
+        match self.slots.lock().slots.get_mut(key) {
+            Some(slot) => {
+                slot.deadline = instant_after(now, slot.ttl);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops tracking `key` without emitting it. Returns `false` if it was
+    /// not tracked.
+    pub fn remove(&self, key: &K) -> bool {
+        self.slots.lock().slots.remove(key).is_some()
+    }
+
+    /// Stops tracking every key without emitting them and stops the
+    /// background thread.
+    pub fn close(&mut self) {
+        self.slots.lock().slots.clear();
+        self.queue.close_and_drain();
+        self.stop.store(true, AtomicOrdering::SeqCst);
+        self.queue.wake_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone + Send + Sync + 'static> Drop for TimeoutMap<K> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_timeout_map() {
+        let (mut timeouts, expired) = TimeoutMap::new();
+        let ttl = Duration::from_millis(30);
+        let hour = Duration::from_secs(3600);
+        assert!(timeouts.insert("idle", ttl));
+        assert!(timeouts.insert("busy", ttl));
+        assert!(timeouts.insert("gone", ttl));
+        assert!(timeouts.insert("slow", hour));
+        assert!(!timeouts.insert("slow", ttl));
+        assert!(timeouts.remove(&"gone"));
+
+        thread::sleep(Duration::from_millis(15));
+        assert!(timeouts.refresh(&"busy"));
+        assert_eq!(Ok("idle"), expired.recv_timeout(Duration::from_secs(1)));
+        assert_eq!(Ok("slow"), expired.recv_timeout(Duration::from_secs(1)));
+        assert!(timeouts.contains_key(&"busy"));
+        assert_eq!(Ok("busy"), expired.recv_timeout(Duration::from_secs(1)));
+        assert!(timeouts.is_empty());
+        assert!(!timeouts.refresh(&"busy"));
+
+        assert!(timeouts.insert("open", Duration::MAX));
+        assert!(timeouts.refresh(&"open"));
+        timeouts.insert("open", hour);
+        timeouts.close();
+        assert!(timeouts.is_empty());
+        assert!(expired.recv_timeout(Duration::from_millis(10)).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+//! How precisely the host wakes timed waits, used as an early-wake margin.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+#[cfg(feature = "calibrate")]
+use std::{sync::Once, time::Instant};
+
+#[cfg(feature = "calibrate")]
+use parking_lot::{Condvar, Mutex};
+
+/// Measured oversleep of a timed wait in nanoseconds; zero until calibrated.
+static RESOLUTION: AtomicU64 = AtomicU64::new(0);
+
+/// The measured timer resolution, if calibration ran.
+pub(crate) fn resolution() -> Option<Duration> {
+    match RESOLUTION.load(Ordering::Relaxed) {
+        0 => None,
+        nanos => Some(Duration::from_nanos(nanos)),
+    }
+}
+
+/// Measures how late timed condvar waits wake up on this host and makes
+/// every queue wake that much earlier, spinning for the remainder.
+///
+/// Runs automatically once when the first queue is created; call again to
+/// re-measure. Returns the measured resolution.
+#[cfg(feature = "calibrate")]
+pub fn calibrate_timer() -> Duration {
+    const SAMPLES: usize = 16;
+    const WAIT: Duration = Duration::from_millis(1);
+
+    let lock = Mutex::new(());
+    let condvar = Condvar::new();
+    let mut guard = lock.lock();
+    let mut oversleep = (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            condvar.wait_for(&mut guard, WAIT);
+            start.elapsed().saturating_sub(WAIT)
+        })
+        .collect::<Vec<_>>();
+    oversleep.sort_unstable();
+    // The median, so one descheduled sample doesn't turn into a long spin.
+    let resolution = oversleep[SAMPLES / 2].max(Duration::from_nanos(1));
+    RESOLUTION.store(resolution.as_nanos() as u64, Ordering::Relaxed);
+    resolution
+}
+
+#[cfg(feature = "calibrate")]
+pub(crate) fn calibrate_once() {
+    static CALIBRATED: Once = Once::new();
+    CALIBRATED.call_once(|| {
+        calibrate_timer();
+    });
+}
+
+#[cfg(not(feature = "calibrate"))]
+pub(crate) fn calibrate_once() {}
+
+#[cfg(all(test, feature = "calibrate"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_calibrate() {
+        assert!(calibrate_timer() > Duration::ZERO);
+        assert!(resolution().is_some());
+    }
+}
@@ -0,0 +1,105 @@
+//! Moving entries between a [`DelayQueue`] and
+//! [`tokio_util::time::DelayQueue`], for projects migrating one way or the
+//! other. Both sides must run on the real clock: deadlines are carried over
+//! as instants, and tokio rounds them to the millisecond.
+
+use std::sync::Arc;
+
+use tokio::time::Instant;
+use tokio_util::time::{delay_queue::Key, DelayQueue as TokioDelayQueue};
+
+use crate::{DelayQueue, Delayed};
+
+impl<T: Delayed> DelayQueue<T> {
+    /// Moves every pending element into `target` under its current
+    /// deadline, returning the keys `target` assigned in delivery order.
+    ///
+    /// Must be called within a tokio runtime with the time driver enabled.
+    pub fn export_to_tokio(&self, target: &mut TokioDelayQueue<Arc<T>>) -> Vec<Key> {
+        let mut entries = self.queue.lock().drain();
+        self.not_full.notify_all();
+        entries.sort();
+        entries
+            .into_iter()
+            .map(|entry| target.insert_at(entry.item, Instant::from_std(entry.deadline)))
+            .collect()
+    }
+
+    /// Moves every entry of `source` into this queue under its deadline,
+    /// ignoring the capacity, and returns how many were moved. Nothing is
+    /// moved into a closed queue.
+    ///
+    /// The deadlines stay fixed in every [`DeadlineMode`](crate::DeadlineMode).
+    /// The keys of `source` are dropped, since elements of this queue have
+    /// none; code that cancels by key should move to
+    /// [`KeyedDelayQueue`](crate::KeyedDelayQueue) instead.
+    pub fn import_from_tokio(&self, source: &mut TokioDelayQueue<T>) -> usize {
+        let mut guard = self.queue.lock();
+        if guard.closed {
+            return 0;
+        }
+        let mut imported = 0;
+        let mut wake = false;
+        while let Some(key) = source.peek() {
+            let expired = source.remove(&key);
+            let deadline = expired.deadline().into_std();
+            let entry = guard.pinned_entry(deadline, Arc::new(expired.into_inner()));
+            wake |= guard.push_entry_wakes(entry);
+            imported += 1;
+        }
+        if wake {
+            self.available.notify_one();
+        }
+        imported
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{testing::Fixed, DeadlineMode};
+
+    #[test]
+    fn test_round_trip() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let _context = runtime.enter();
+
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        queue.try_put(Fixed(20_000_000_000)).unwrap();
+        queue.try_put(Fixed(10_000_000_000)).unwrap();
+        let mut tokio_queue = TokioDelayQueue::new();
+        let keys = queue.export_to_tokio(&mut tokio_queue);
+        assert!(queue.is_empty());
+        assert_eq!(2, tokio_queue.len());
+        assert!(tokio_queue.deadline(&keys[0]) < tokio_queue.deadline(&keys[1]));
+
+        let mut source = TokioDelayQueue::new();
+        source.insert(Fixed(1), Duration::from_secs(30));
+        source.insert(Fixed(2), Duration::ZERO);
+        assert_eq!(2, queue.import_from_tokio(&mut source));
+        assert!(source.is_empty());
+        assert_eq!(Fixed(2), *queue.take_or_closed().unwrap());
+        assert_eq!(1, queue.len());
+    }
+
+    #[test]
+    fn test_import_dynamic() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let _context = runtime.enter();
+
+        let queue = DelayQueue::default();
+        let mut source = TokioDelayQueue::new();
+        source.insert(Fixed(3_600_000_000_000), Duration::ZERO);
+        assert_eq!(1, queue.import_from_tokio(&mut source));
+        let imported = queue.take_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(Fixed(3_600_000_000_000), *imported);
+    }
+}
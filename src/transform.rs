@@ -0,0 +1,109 @@
+use std::{sync::Arc, time::Instant};
+
+use crate::{DelayQueue, Delayed};
+
+/// A consumer receiving a projection of every element taken from a shared
+/// queue, see [`DelayQueue::map_view`].
+pub struct TransformingConsumer<T: Delayed, F> {
+    queue: DelayQueue<T>,
+    project: Arc<F>,
+}
+
+impl<T: Delayed, F> Clone for TransformingConsumer<T, F> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+            project: Arc::clone(&self.project),
+        }
+    }
+}
+
+impl<T, U, F> TransformingConsumer<T, F>
+where
+    T: Delayed + Send + Sync,
+    F: Fn(&T) -> U,
+{
+    pub fn queue(&self) -> &DelayQueue<T> {
+        &self.queue
+    }
+
+    /// Like [`DelayQueue::take`], projected.
+    pub fn take(&self) -> U {
+        (self.project)(&self.queue.take_delivery().item)
+    }
+
+    /// Like [`DelayQueue::take_or_closed`], projected.
+    pub fn take_or_closed(&self) -> Option<U> {
+        self.queue
+            .take_or_closed()
+            .map(|item| (self.project)(&item))
+    }
+
+    /// Like [`DelayQueue::take_until`], projected.
+    pub fn take_until(&self, cutoff: Instant) -> Option<U> {
+        self.queue
+            .take_until(cutoff)
+            .map(|item| (self.project)(&item))
+    }
+
+    /// Like [`DelayQueue::run_pending`], projected.
+    pub fn run_pending<G: FnMut(U)>(&self, mut f: G) -> usize {
+        self.queue.run_pending(|item| f((self.project)(&item)))
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    /// Returns a consumer of this queue that receives `project(&element)`
+    /// instead of the element, so a module can consume a shared queue in its
+    /// own terms without the producer knowing about it.
+    pub fn map_view<U, F>(&self, project: F) -> TransformingConsumer<T, F>
+    where
+        F: Fn(&T) -> U,
+    {
+        TransformingConsumer {
+            queue: self.clone(),
+            project: Arc::new(project),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Email {
+        to: &'static str,
+        delay: i64,
+    }
+
+    impl Delayed for Email {
+        fn delayed(&self) -> i64 {
+            self.delay
+        }
+    }
+
+    #[test]
+    fn test_map_view() {
+        let queue = DelayQueue::default();
+        let recipients = queue.map_view(|email: &Email| email.to.to_uppercase());
+        queue
+            .try_put(Email {
+                to: "alice",
+                delay: -1,
+            })
+            .unwrap();
+        queue
+            .try_put(Email {
+                to: "bob",
+                delay: 3_600_000_000_000,
+            })
+            .unwrap();
+        assert_eq!("ALICE", recipients.take());
+        assert_eq!(0, recipients.run_pending(drop));
+        assert_eq!(1, recipients.queue().len());
+    }
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use crate::{worker::Periodic, DelayQueue, Delayed, Health};
+
+/// Background detection of stalled delivery, see
+/// [`DelayQueue::spawn_watchdog`].
+pub struct Watchdog {
+    worker: Periodic,
+}
+
+impl Watchdog {
+    pub fn stop(&mut self) {
+        self.worker.stop();
+    }
+}
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync + 'static,
+{
+    /// Checks the queue every `interval` for a head that expired more than
+    /// `stall` ago while nothing was delivered since the previous check.
+    ///
+    /// On a stall `alert` is called with the current [`Health`] and every
+    /// blocked consumer is woken, so a lost wakeup cannot wedge the queue.
+    pub fn spawn_watchdog<F>(&self, interval: Duration, stall: Duration, alert: F) -> Watchdog
+    where
+        F: Fn(&Health) + Send + 'static,
+    {
+        let queue = self.clone();
+        let mut delivered = None;
+        Watchdog {
+            worker: Periodic::spawn(interval, move || {
+                let health = queue.health();
+                let now_delivered = queue.stats().delivered;
+                let progressed = delivered.replace(now_delivered) != Some(now_delivered);
+                if health.head_overdue > stall && !progressed {
+                    alert(&health);
+                    queue.wake_all();
+                }
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::{testing::Fixed, DeadlineMode};
+
+    #[test]
+    fn test_watchdog() {
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        queue.try_put(Fixed(-1_000_000_000)).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let mut watchdog = queue.spawn_watchdog(
+            Duration::from_millis(5),
+            Duration::from_millis(100),
+            move |health| {
+                let _ = sender.send(health.head_overdue);
+            },
+        );
+        let overdue = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        watchdog.stop();
+        assert!(overdue >= Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::BinaryHeap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{AdminAction, DelayQueue, Delayed, Entry};
+
+impl<T> DelayQueue<T>
+where
+    T: Delayed + Send + Sync,
+{
+    /// Counts the elements due within `window` from now, e.g. to plan
+    /// capacity for the next minute. Uses the
+    /// [bucket index](crate::DelayQueueBuilder::bucket_index) if enabled.
+    /// A window reaching beyond the range of `Instant` counts everything.
+    pub fn due_within(&self, window: Duration) -> usize {
+        let cutoff = match self.clock.now().checked_add(window) {
+            Some(cutoff) => cutoff,
+            None => return self.len(),
+        };
+        let guard = self.queue.lock();
+        if let Some(buckets) = guard.buckets.as_ref() {
+            return buckets.count_until(cutoff);
+        }
+        guard
+            .queue
+            .iter()
+            .filter(|entry| entry.0.deadline <= cutoff)
+            .count()
+    }
+
+    /// Counts the elements due in each of `buckets` consecutive windows of
+    /// `width` from now, e.g. so an autoscaler can add workers before a
+    /// scheduled spike. The first bucket also counts the expired elements;
+    /// elements due after the last bucket are not counted. Buckets reaching
+    /// beyond the range of `Instant` count everything left.
+    pub fn forecast(&self, buckets: usize, width: Duration) -> Vec<usize> {
+        let now = self.clock.now();
+        let guard = self.queue.lock();
+        if let Some(index) = guard.buckets.as_ref() {
+            let mut counted = 0;
+            return (1..=buckets as u32)
+                .map(|bucket| {
+                    let until = width
+                        .checked_mul(bucket)
+                        .and_then(|offset| now.checked_add(offset))
+                        .map_or(guard.queue.len(), |until| index.count_until(until));
+                    let count = until - counted;
+                    counted = until;
+                    count
+                })
+                .collect();
+        }
+        let mut forecast = vec![0; buckets];
+        let width = width.max(Duration::from_nanos(1)).as_nanos();
+        for entry in guard.queue.iter() {
+            let offset = entry.0.deadline.saturating_duration_since(now).as_nanos();
+            // Deadlines on a bucket's upper edge belong to that bucket.
+            let bucket = (offset.saturating_sub(1) / width) as usize;
+            if let Some(count) = forecast.get_mut(bucket) {
+                *count += 1;
+            }
+        }
+        forecast
+    }
+
+    /// Returns the elements due within `window` from now, in delivery order,
+    /// without removing them. A window reaching beyond the range of
+    /// `Instant` returns everything.
+    pub fn peek_due_within(&self, window: Duration) -> Vec<Arc<T>> {
+        let cutoff = self.clock.now().checked_add(window);
+        let mut entries = {
+            let guard = self.queue.lock();
+            guard
+                .queue
+                .iter()
+                .filter(|entry| cutoff.is_none_or(|cutoff| entry.0.deadline <= cutoff))
+                .map(|entry| entry.0.clone())
+                .collect::<Vec<_>>()
+        };
+        entries.sort();
+        entries.into_iter().map(|entry| entry.item).collect()
+    }
+
+    /// Removes every element whose deadline falls in `from..=to` and returns
+    /// them in delivery order, e.g. to drop everything scheduled during a
+    /// maintenance window. The [`on_discard`](Self::on_discard) hook is not
+    /// called.
+    pub fn cancel_range(&self, from: Instant, to: Instant) -> Vec<Arc<T>> {
+        self.cancel_range_as(from, to, None)
+    }
+
+    pub(crate) fn cancel_range_as(
+        &self,
+        from: Instant,
+        to: Instant,
+        actor: Option<&str>,
+    ) -> Vec<Arc<T>> {
+        let mut removed = self.remove_range(from, to);
+        if !removed.is_empty() {
+            self.not_full.notify_all();
+        }
+        self.audit(
+            actor,
+            AdminAction::CancelRange {
+                from,
+                to,
+                removed: removed.len(),
+            },
+        );
+        removed.sort();
+        removed.into_iter().map(|entry| entry.item).collect()
+    }
+
+    fn remove_range(&self, from: Instant, to: Instant) -> Vec<Entry<T>> {
+        let mut guard = self.queue.lock();
+        if guard
+            .buckets
+            .as_ref()
+            .is_some_and(|buckets| buckets.count(from, to) == 0)
+        {
+            return Vec::new();
+        }
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut guard.queue)
+            .into_vec()
+            .into_iter()
+            .partition(|entry| (from..=to).contains(&entry.0.deadline));
+        guard.queue = BinaryHeap::from(kept);
+        if !removed.is_empty() {
+            guard.reindex();
+            guard.touch();
+        }
+        removed.into_iter().map(|entry| entry.0).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testing::Fixed, Clock, DeadlineMode, SimClock};
+
+    #[test]
+    fn test_due_within() {
+        let queue = DelayQueue::default();
+        for delay in &[30_000_000_000, -1, 3_600_000_000_000, 10_000_000_000] {
+            queue.try_put(Fixed(*delay)).unwrap();
+        }
+        let minute = Duration::from_secs(60);
+        assert_eq!(3, queue.due_within(minute));
+        assert_eq!(
+            vec![Fixed(-1), Fixed(10_000_000_000), Fixed(30_000_000_000)],
+            queue
+                .peek_due_within(minute)
+                .iter()
+                .map(|item| Fixed(item.0))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(4, queue.len());
+        assert_eq!(4, queue.due_within(Duration::MAX));
+        assert_eq!(4, queue.peek_due_within(Duration::MAX).len());
+    }
+
+    #[test]
+    fn test_forecast() {
+        let clock = SimClock::new();
+        let indexed = DelayQueue::builder()
+            .deadline_mode(DeadlineMode::Captured)
+            .clock(clock.clone())
+            .bucket_index(Duration::from_secs(7))
+            .build();
+        let scanned = DelayQueue::builder()
+            .deadline_mode(DeadlineMode::Captured)
+            .clock(clock.clone())
+            .build();
+        for delay in &[-5, 0, 30, 60, 61, 90, 170, 180, 181] {
+            indexed.try_put(Fixed(delay * 1_000_000_000)).unwrap();
+            scanned.try_put(Fixed(delay * 1_000_000_000)).unwrap();
+        }
+        let minute = Duration::from_secs(60);
+        assert_eq!(vec![4, 2, 2], indexed.forecast(3, minute));
+        assert_eq!(vec![4, 2, 2], scanned.forecast(3, minute));
+        assert!(scanned.forecast(0, minute).is_empty());
+        assert_eq!(vec![9, 0], indexed.forecast(2, Duration::MAX));
+        assert_eq!(vec![9, 0], scanned.forecast(2, Duration::MAX));
+    }
+
+    #[test]
+    fn test_cancel_range() {
+        let queue = DelayQueue::with_deadline_mode(DeadlineMode::Captured);
+        let now = Instant::now();
+        for delay in &[3_000_000_000, 1_000_000_000, 2_000_000_000, 60_000_000_000] {
+            queue.try_put(Fixed(*delay)).unwrap();
+        }
+        let cancelled = queue.cancel_range(now, now + Duration::from_secs(30));
+        assert_eq!(
+            vec![1_000_000_000, 2_000_000_000, 3_000_000_000],
+            cancelled.iter().map(|item| item.0).collect::<Vec<_>>()
+        );
+        assert_eq!(1, queue.len());
+        assert!(queue.cancel_range(now, now).is_empty());
+    }
+
+    #[test]
+    fn test_due_within_bucket_index() {
+        let clock = SimClock::new();
+        let queue = DelayQueue::builder()
+            .deadline_mode(DeadlineMode::Captured)
+            .clock(clock.clone())
+            .bucket_index(Duration::from_secs(60))
+            .build();
+        for delay in &[30_000_000_000, 0, 3_600_000_000_000, 90_000_000_000] {
+            queue.try_put(Fixed(*delay)).unwrap();
+        }
+        let minute = Duration::from_secs(60);
+        assert_eq!(2, queue.due_within(minute));
+
+        queue.take_or_closed().unwrap();
+        queue.shift_deadlines_earlier(Duration::from_secs(30));
+        assert_eq!(2, queue.due_within(minute));
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(1, queue.due_within(Duration::ZERO));
+        let now = clock.now();
+        let cancelled = queue.cancel_range(now, now + Duration::from_secs(3600));
+        assert_eq!(2, cancelled.len());
+        assert_eq!(1, queue.due_within(Duration::from_secs(7200)));
+        assert_eq!(1, queue.clear());
+        assert_eq!(0, queue.due_within(Duration::from_secs(7200)));
+    }
+}
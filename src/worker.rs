@@ -0,0 +1,55 @@
+use std::{
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use parking_lot::{Condvar, Mutex};
+
+/// A background thread running a closure every `interval` until stopped.
+pub(crate) struct Periodic {
+    stopped: Arc<(Mutex<bool>, Condvar)>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Periodic {
+    pub(crate) fn spawn<F>(interval: Duration, mut f: F) -> Self
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let stopped = Arc::new((Mutex::new(false), Condvar::new()));
+        let worker = {
+            let stopped = Arc::clone(&stopped);
+            thread::spawn(move || loop {
+                f();
+                let (lock, cvar) = &*stopped;
+                let mut stopped = lock.lock();
+                if !*stopped {
+                    cvar.wait_for(&mut stopped, interval);
+                }
+                if *stopped {
+                    return;
+                }
+            })
+        };
+        Self {
+            stopped,
+            worker: Some(worker),
+        }
+    }
+
+    pub(crate) fn stop(&mut self) {
+        let (lock, cvar) = &*self.stopped;
+        *lock.lock() = true;
+        cvar.notify_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for Periodic {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}